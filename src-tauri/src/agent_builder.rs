@@ -0,0 +1,208 @@
+use serde::Serialize;
+
+use crate::database::Agent;
+use crate::llm::{ChatMessage, ChatRequest, LlmProvider};
+
+const VALID_SCHEDULES: &[&str] = &["hourly", "daily", "weekly"];
+// One retry with the validation errors fed back to the model
+const MAX_ATTEMPTS: usize = 2;
+
+const SYSTEM_PROMPT: &str = r#"You turn plain-English descriptions of automation agents into agent config JSON.
+Reply with a single JSON object and nothing else. Fields:
+- "name" (string, required): short display name
+- "description" (string, required): what the agent does
+- "schedule" (string, required): one of "hourly", "daily", "weekly"
+- "schedule_time" (string, optional): "HH:MM" for daily/weekly agents
+- "schedule_day" (string, optional): weekday for weekly agents, e.g. "monday"
+- "command" (string, required): executable to run, usually "node"
+- "args" (array of strings, required): script path followed by its arguments
+- "timeout" (number, optional): max runtime in milliseconds, default 300000
+- "enabled" (boolean, optional): default true
+- "notifications" (object, optional): {"on_success": bool, "on_failure": bool}
+- "metadata" (object, optional): may include "role", "goal", "agent_type", "hashtag"
+Available scripts: linkedin_bot.js <post text>, linkedin_hashtag_monitor.js <#hashtag>,
+linkedin_trending_scraper.js, linkedin_comment_bot.js <#hashtag> <comment>."#;
+
+/// An agent config proposed by the model, not yet saved. The frontend shows
+/// it for confirmation and saves `config_json` with create_agent_file.
+#[derive(Debug, Serialize)]
+pub struct AgentDraft {
+    pub name: String,
+    pub config_json: String,
+    pub agent: Agent,
+    pub provider: String,
+    pub model: String,
+}
+
+/// Asks the model for an agent config matching `description` and validates it.
+pub fn draft_agent(provider: &dyn LlmProvider, description: &str) -> Result<AgentDraft, String> {
+    let mut request = ChatRequest {
+        messages: vec![ChatMessage {
+            role: "user".to_string(),
+            content: description.to_string(),
+        }],
+        system: Some(SYSTEM_PROMPT.to_string()),
+        temperature: Some(0.2),
+        ..Default::default()
+    };
+
+    let mut last_error = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let response = provider.chat(&request).map_err(|e| e.to_string())?;
+
+        match extract_json(&response.content).and_then(|config| validate_config(&config)) {
+            Ok((name, config_json)) => {
+                let agent = Agent::from_config(&name, &config_json)?;
+                return Ok(AgentDraft {
+                    name,
+                    config_json,
+                    agent,
+                    provider: response.provider,
+                    model: response.model,
+                });
+            }
+            Err(error) => {
+                last_error = error;
+                request.messages.push(ChatMessage {
+                    role: "assistant".to_string(),
+                    content: response.content,
+                });
+                request.messages.push(ChatMessage {
+                    role: "user".to_string(),
+                    content: format!("That config is invalid: {}. Reply with the corrected JSON only.", last_error),
+                });
+            }
+        }
+    }
+
+    Err(format!("The model did not produce a valid agent config: {}", last_error))
+}
+
+/// Pulls the JSON object out of a reply, tolerating code fences and chatter.
+fn extract_json(reply: &str) -> Result<serde_json::Value, String> {
+    let start = reply.find('{').ok_or("reply contains no JSON object")?;
+    let end = reply.rfind('}').ok_or("reply contains no JSON object")?;
+    if end < start {
+        return Err("reply contains no JSON object".to_string());
+    }
+    serde_json::from_str(&reply[start..=end]).map_err(|e| format!("reply is not valid JSON: {}", e))
+}
+
+/// Checks a config against the agent schema, returning the agent name and
+/// the pretty-printed config.
+fn validate_config(config: &serde_json::Value) -> Result<(String, String), String> {
+    let mut errors = Vec::new();
+
+    if !config.is_object() {
+        return Err("config must be a JSON object".to_string());
+    }
+
+    let name = config["name"].as_str().map(str::trim).unwrap_or_default();
+    if name.is_empty() {
+        errors.push("\"name\" must be a non-empty string".to_string());
+    }
+    if config["description"].as_str().is_none() {
+        errors.push("\"description\" must be a string".to_string());
+    }
+    match config["schedule"].as_str() {
+        Some(schedule) if VALID_SCHEDULES.contains(&schedule) => {}
+        _ => errors.push(format!("\"schedule\" must be one of {}", VALID_SCHEDULES.join(", "))),
+    }
+    if let Some(time) = config.get("schedule_time") {
+        if !time.as_str().is_some_and(is_valid_time) {
+            errors.push("\"schedule_time\" must be HH:MM".to_string());
+        }
+    }
+    if config["command"].as_str().is_none_or(str::is_empty) {
+        errors.push("\"command\" must be a non-empty string".to_string());
+    }
+    match config["args"].as_array() {
+        Some(args) if args.iter().all(|a| a.is_string()) => {}
+        _ => errors.push("\"args\" must be an array of strings".to_string()),
+    }
+    if let Some(timeout) = config.get("timeout") {
+        if timeout.as_i64().is_none_or(|t| t <= 0) {
+            errors.push("\"timeout\" must be a positive number of milliseconds".to_string());
+        }
+    }
+    if let Some(enabled) = config.get("enabled") {
+        if !enabled.is_boolean() {
+            errors.push("\"enabled\" must be true or false".to_string());
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    let config_json = serde_json::to_string_pretty(config)
+        .map_err(|e| format!("Failed to serialize config: {}", e))?;
+    Ok((name.to_string(), config_json))
+}
+
+fn is_valid_time(time: &str) -> bool {
+    chrono::NaiveTime::parse_from_str(time, "%H:%M").is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::OllamaProvider;
+    use crate::test_support::MockServer;
+
+    const VALID: &str = r##"{"name":"Hashtag Watcher","description":"Summarise #openclaw posts",
+        "schedule":"hourly","command":"node","args":["linkedin_hashtag_monitor.js","#openclaw"],
+        "metadata":{"goal":"Summarise new posts"}}"##;
+
+    fn ollama_reply(content: &str) -> (u16, &'static str, String) {
+        let body = serde_json::json!({"message": {"role": "assistant", "content": content}});
+        (200, "application/json", body.to_string())
+    }
+
+    #[test]
+    fn test_extract_json_from_fenced_reply() {
+        let reply = format!("Here you go:\n```json\n{}\n```", VALID);
+        let config = extract_json(&reply).unwrap();
+        assert_eq!(config["name"], "Hashtag Watcher");
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let config = serde_json::json!({"name": "", "schedule": "sometimes", "command": "node", "args": [1]});
+        let errors = validate_config(&config).unwrap_err();
+        assert!(errors.contains("\"name\""));
+        assert!(errors.contains("\"description\""));
+        assert!(errors.contains("\"schedule\""));
+        assert!(errors.contains("\"args\""));
+        assert!(!errors.contains("\"command\""));
+    }
+
+    #[test]
+    fn test_draft_agent() {
+        let server = MockServer::start(vec![ollama_reply(VALID)]);
+        let provider = OllamaProvider::new(&server.url, "phi3");
+
+        let draft = draft_agent(&provider, "check #openclaw every hour and summarise new posts").unwrap();
+        assert_eq!(draft.name, "Hashtag Watcher");
+        assert_eq!(draft.agent.schedule, "hourly");
+        assert_eq!(draft.agent.goal.as_deref(), Some("Summarise new posts"));
+        assert_eq!(draft.provider, "ollama");
+
+        let body = server.request().json();
+        assert!(body["messages"][0]["content"].as_str().unwrap().contains("\"schedule\""));
+    }
+
+    #[test]
+    fn test_draft_agent_retries_invalid_reply() {
+        let server = MockServer::start(vec![ollama_reply("{\"name\": \"Oops\"}"), ollama_reply(VALID)]);
+        let provider = OllamaProvider::new(&server.url, "phi3");
+
+        let draft = draft_agent(&provider, "check #openclaw every hour").unwrap();
+        assert_eq!(draft.name, "Hashtag Watcher");
+
+        let _first = server.request();
+        let retry = server.request().json();
+        let messages = retry["messages"].as_array().unwrap();
+        assert!(messages.last().unwrap()["content"].as_str().unwrap().contains("invalid"));
+    }
+}
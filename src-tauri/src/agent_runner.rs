@@ -0,0 +1,228 @@
+use serde::Serialize;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::channels;
+use crate::database::{Agent, AgentLog, Database};
+use crate::notifications;
+use crate::process;
+use crate::secrets::SecretStore;
+
+// Output kept in the agent log, per stream
+const MAX_LOGGED_OUTPUT: usize = 4000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentRun {
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration_ms: u64,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs an agent's command to completion, killing it after the agent's
+/// timeout along with anything it started. Relative script paths in `args` resolve against `script_dir`.
+/// The run is recorded in the agent log as "executed" followed by "success"
+/// or "error".
+pub fn run_agent(db: &Arc<Mutex<Database>>, agent: &Agent, script_dir: &Path) -> Result<AgentRun, String> {
+    let agent_id = agent.id.ok_or("Agent has not been saved")?;
+    let args: Vec<String> = serde_json::from_str(&agent.args)
+        .map_err(|e| format!("Agent {} has invalid args: {}", agent.name, e))?;
+    let args: Vec<String> = args.into_iter().map(|arg| resolve_script(&arg, script_dir)).collect();
+
+    log(db, agent, agent_id, "executed", &format!("Running {} {}", agent.command, args.join(" ")), None);
+    println!("[AgentRunner] Running {}", agent.name);
+
+    let started = Instant::now();
+    let mut command = Command::new(&agent.command);
+    let mut child = process::own_group(&mut command)
+        .args(&args)
+        .current_dir(script_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            let error = format!("Failed to start {}: {}", agent.command, e);
+            log(db, agent, agent_id, "error", &error, None);
+            error
+        })?;
+
+    // Drain both pipes on their own threads so a chatty script can't block on a full pipe
+    let stdout = child.stdout.take().map(capture);
+    let stderr = child.stderr.take().map(capture);
+
+    let timeout = Duration::from_millis(agent.timeout.max(1) as u64);
+    let mut timed_out = false;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) if started.elapsed() >= timeout => {
+                timed_out = true;
+                // Scripts drive browsers, which outlive a kill of the script alone
+                if let Err(e) = process::kill_tree(&mut child) {
+                    eprintln!("[AgentRunner] Failed to stop {}: {}", agent.name, e);
+                }
+                break child.wait().ok();
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(100)),
+            Err(e) => {
+                eprintln!("[AgentRunner] Failed to wait for {}: {}", agent.name, e);
+                break None;
+            }
+        }
+    };
+
+    let run = AgentRun {
+        agent_id,
+        agent_name: agent.name.clone(),
+        success: !timed_out && status.is_some_and(|s| s.success()),
+        exit_code: status.and_then(|s| s.code()),
+        timed_out,
+        duration_ms: started.elapsed().as_millis() as u64,
+        stdout: stdout.map(Capture::finish).unwrap_or_default(),
+        stderr: stderr.map(Capture::finish).unwrap_or_default(),
+    };
+
+    let details = serde_json::json!({
+        "exit_code": run.exit_code,
+        "timed_out": run.timed_out,
+        "duration_ms": run.duration_ms,
+        "stdout": tail(&run.stdout),
+        "stderr": tail(&run.stderr),
+    })
+    .to_string();
+    if run.success {
+        log(db, agent, agent_id, "success", &format!("Finished in {} ms", run.duration_ms), Some(details));
+    } else if run.timed_out {
+        log(db, agent, agent_id, "error", &format!("Timed out after {} ms", agent.timeout), Some(details));
+    } else {
+        let message = format!("Exited with code {}", run.exit_code.map_or("unknown".to_string(), |c| c.to_string()));
+        log(db, agent, agent_id, "error", &message, Some(details));
+    }
+
+    Ok(run)
+}
+
+/// Side effects of a finished run, shared by every way of starting one (the
+/// daemon's scheduler and `personaliz agents run`): the desktop notification
+/// the agent asks for, if any, and the email and webhook alerts. Alerts are
+/// sent before returning, so a CLI run doesn't exit with them unsent.
+pub fn run_finished(db: &Arc<Mutex<Database>>, run: &AgentRun, secrets: &SecretStore) {
+    notifications::notify_run(db, run, notifications::show_desktop_notification);
+    channels::dispatch(db, run, secrets);
+}
+
+/// `linkedin_bot.js` becomes `<script_dir>/linkedin_bot.js` if that file
+/// exists; anything else is passed through unchanged.
+fn resolve_script(arg: &str, script_dir: &Path) -> String {
+    let path = Path::new(arg);
+    if path.is_relative() && script_dir.join(path).is_file() {
+        script_dir.join(path).display().to_string()
+    } else {
+        arg.to_string()
+    }
+}
+
+/// Output read so far from one of the child's pipes.
+struct Capture {
+    output: Arc<Mutex<Vec<u8>>>,
+    closed: mpsc::Receiver<()>,
+}
+
+fn capture(mut stream: impl Read + Send + 'static) -> Capture {
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let (tx, closed) = mpsc::channel();
+    let sink = Arc::clone(&output);
+    thread::spawn(move || {
+        let mut chunk = [0u8; 8192];
+        while let Ok(n @ 1..) = stream.read(&mut chunk) {
+            sink.lock().unwrap().extend_from_slice(&chunk[..n]);
+        }
+        let _ = tx.send(());
+    });
+    Capture { output, closed }
+}
+
+impl Capture {
+    /// Waits up to process::OUTPUT_GRACE for the pipe to close, then returns what was
+    /// read; the reader thread is left to finish on its own.
+    fn finish(self) -> String {
+        let _ = self.closed.recv_timeout(process::OUTPUT_GRACE);
+        let output = self.output.lock().unwrap();
+        String::from_utf8_lossy(&output).into_owned()
+    }
+}
+
+fn tail(output: &str) -> &str {
+    let mut start = output.len().saturating_sub(MAX_LOGGED_OUTPUT);
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    &output[start..]
+}
+
+fn log(db: &Arc<Mutex<Database>>, agent: &Agent, agent_id: i64, event_type: &str, message: &str, details: Option<String>) {
+    let entry = AgentLog {
+        id: None,
+        agent_id,
+        agent_name: agent.name.clone(),
+        event_type: event_type.to_string(),
+        message: message.to_string(),
+        details,
+        timestamp: String::new(),
+    };
+    if let Err(e) = db.lock().unwrap().log_agent_event(&entry) {
+        eprintln!("[AgentRunner] Failed to log {} for {}: {}", event_type, agent.name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_resolve_script() {
+        let dir = TempDir::new("runner");
+        std::fs::write(dir.path().join("bot.js"), "").unwrap();
+
+        assert_eq!(resolve_script("bot.js", dir.path()), dir.path().join("bot.js").display().to_string());
+        assert_eq!(resolve_script("#openclaw", dir.path()), "#openclaw");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_child_processes() {
+        let db = Arc::new(Mutex::new(Database::in_memory().unwrap()));
+        let mut agent = Agent::from_config("sleeper", "{}").unwrap();
+        agent.command = "sh".to_string();
+        // The background sleep holds stdout open after the shell is killed
+        agent.args = r#"["-c", "echo started; sleep 30 & sleep 30"]"#.to_string();
+        agent.timeout = 300;
+        agent.id = Some(db.lock().unwrap().create_agent(&agent).unwrap());
+
+        let started = Instant::now();
+        let run = run_agent(&db, &agent, &std::env::temp_dir()).unwrap();
+        assert!(run.timed_out);
+        assert!(!run.success);
+        assert_eq!(run.stdout.trim(), "started");
+        assert!(started.elapsed() < Duration::from_secs(10), "waited on the orphaned sleep");
+    }
+
+    #[test]
+    fn test_tail() {
+        let long = "é".repeat(MAX_LOGGED_OUTPUT);
+        let kept = tail(&long);
+        assert!(kept.len() <= MAX_LOGGED_OUTPUT);
+        assert!(kept.chars().all(|c| c == 'é'));
+        assert_eq!(tail("short"), "short");
+    }
+}
@@ -0,0 +1,267 @@
+//! Database backups in `~/.personaliz/backups`, named
+//! `personaliz-<kind>-<YYYYMMDD-HHMMSS>.db` (UTC). "manual" backups are made
+//! on request and kept until deleted by hand; "auto" backups are made every
+//! `backup_interval_hours` and rotated down to `backup_keep`; "pre-restore"
+//! backups hold the database as it was before a restore.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::database::{Database, SCHEMA_VERSION};
+use crate::settings;
+
+const PREFIX: &str = "personaliz-";
+// Automatic backups are hours apart, so checking this often is plenty
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupKind {
+    Manual,
+    Auto,
+    PreRestore,
+}
+
+impl BackupKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            BackupKind::Manual => "manual",
+            BackupKind::Auto => "auto",
+            BackupKind::PreRestore => "pre-restore",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupInfo {
+    pub path: String,
+    pub file_name: String,
+    pub kind: String, // "manual", "auto" or "pre-restore"
+    pub size_bytes: u64,
+    pub created_at: String,
+    #[serde(skip)]
+    modified: SystemTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreResult {
+    pub restored_from: String,
+    pub schema_version: i64, // of the backup; newer tables are added on restore
+    pub pre_restore_backup: String,
+}
+
+pub fn backup_dir() -> PathBuf {
+    settings::data_dir().join("backups")
+}
+
+/// Backs up the database into `dir` under a timestamped name.
+pub fn create_backup(db: &Database, dir: &Path, kind: BackupKind) -> Result<BackupInfo, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+
+    let stamp = Utc::now().format("%Y%m%d-%H%M%S");
+    let mut path = dir.join(format!("{}{}-{}.db", PREFIX, kind.as_str(), stamp));
+    // Two backups within the same second
+    for n in 2.. {
+        if !path.exists() {
+            break;
+        }
+        path = dir.join(format!("{}{}-{}-{}.db", PREFIX, kind.as_str(), stamp, n));
+    }
+
+    db.backup_to(&path).map_err(|e| format!("Backup failed: {}", e))?;
+    println!("[Backup] Wrote {}", path.display());
+    backup_info(&path).ok_or_else(|| format!("Backup {} disappeared", path.display()))
+}
+
+/// Backups in `dir`, newest first.
+pub fn list_backups(dir: &Path) -> Result<Vec<BackupInfo>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {}", dir.display(), e)),
+    };
+
+    let mut backups: Vec<BackupInfo> = entries.filter_map(|entry| backup_info(&entry.ok()?.path())).collect();
+    backups.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| b.file_name.cmp(&a.file_name)));
+    Ok(backups)
+}
+
+fn backup_info(path: &Path) -> Option<BackupInfo> {
+    let file_name = path.file_name()?.to_str()?;
+    let kind = file_name.strip_prefix(PREFIX)?.strip_suffix(".db")?;
+    let kind = [BackupKind::PreRestore, BackupKind::Manual, BackupKind::Auto]
+        .into_iter()
+        .find(|k| kind.starts_with(&format!("{}-", k.as_str())))?;
+    let metadata = fs::metadata(path).ok().filter(|m| m.is_file())?;
+    let modified = metadata.modified().ok()?;
+
+    Some(BackupInfo {
+        path: path.display().to_string(),
+        file_name: file_name.to_string(),
+        kind: kind.as_str().to_string(),
+        size_bytes: metadata.len(),
+        created_at: DateTime::<Utc>::from(modified).to_rfc3339(),
+        modified,
+    })
+}
+
+/// Deletes all but the newest `keep` automatic backups. Returns what was deleted.
+pub fn rotate(dir: &Path, keep: usize) -> Result<Vec<String>, String> {
+    let mut deleted = Vec::new();
+    let autos = list_backups(dir)?.into_iter().filter(|b| b.kind == BackupKind::Auto.as_str());
+    for backup in autos.skip(keep) {
+        fs::remove_file(&backup.path).map_err(|e| format!("Failed to delete {}: {}", backup.path, e))?;
+        deleted.push(backup.path);
+    }
+    Ok(deleted)
+}
+
+/// Makes an automatic backup if the newest one is older than
+/// `backup_interval_hours`, then rotates. Returns the new backup, if any.
+pub fn run_if_due(db: &Arc<Mutex<Database>>, dir: &Path) -> Result<Option<BackupInfo>, String> {
+    let settings = settings::load_shared(db);
+    if settings.backup_interval_hours == 0 {
+        return Ok(None);
+    }
+
+    let newest_auto = list_backups(dir)?.into_iter().find(|b| b.kind == BackupKind::Auto.as_str());
+    let interval = Duration::from_secs(settings.backup_interval_hours.saturating_mul(3600));
+    if newest_auto.is_some_and(|b| b.modified.elapsed().is_ok_and(|age| age < interval)) {
+        return Ok(None);
+    }
+
+    let backup = create_backup(&db.lock().unwrap(), dir, BackupKind::Auto)?;
+    for path in rotate(dir, settings.backup_keep.max(1))? {
+        println!("[Backup] Rotated out {}", path);
+    }
+    Ok(Some(backup))
+}
+
+/// Starts a thread that makes automatic backups for the life of the process.
+/// The newest backup on disk decides when the next is due, so the app and
+/// the daemon running at the same time don't double up.
+pub fn start_auto_backups(db: Arc<Mutex<Database>>) {
+    let spawned = thread::Builder::new().name("auto-backup".to_string()).spawn(move || loop {
+        if let Err(e) = run_if_due(&db, &backup_dir()) {
+            eprintln!("[Backup] Automatic backup failed: {}", e);
+        }
+        thread::sleep(CHECK_INTERVAL);
+    });
+    if let Err(e) = spawned {
+        eprintln!("[Backup] Failed to spawn backup thread: {}", e);
+    }
+}
+
+/// Replaces the database with `backup` after checking that it is an intact
+/// app database this version can open. The current database is backed up
+/// into `dir` first, so a restore can itself be undone.
+pub fn restore(db: &mut Database, backup: &Path, dir: &Path) -> Result<RestoreResult, String> {
+    let version = Database::schema_version_of(backup)?;
+    if version > SCHEMA_VERSION {
+        return Err(format!(
+            "{} is from a newer version of the app (schema {}, this version supports up to {})",
+            backup.display(),
+            version,
+            SCHEMA_VERSION
+        ));
+    }
+
+    let safety = create_backup(db, dir, BackupKind::PreRestore)?;
+    db.restore_from(backup).map_err(|e| format!("Restore failed: {}", e))?;
+    println!("[Backup] Restored {} (schema {})", backup.display(), version);
+
+    Ok(RestoreResult {
+        restored_from: backup.display().to_string(),
+        schema_version: version,
+        pre_restore_backup: safety.path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Agent;
+    use crate::test_support::TempDir;
+
+    fn agent(name: &str) -> Agent {
+        Agent::from_config(name, r#"{"command":"node","args":[]}"#).unwrap()
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let dir = TempDir::new("backup-restore");
+        let mut db = Database::in_memory().unwrap();
+        db.create_agent(&agent("before")).unwrap();
+
+        let backup = create_backup(&db, dir.path(), BackupKind::Manual).unwrap();
+        assert_eq!(backup.kind, "manual");
+        db.create_agent(&agent("after")).unwrap();
+
+        let result = restore(&mut db, Path::new(&backup.path), dir.path()).unwrap();
+        assert_eq!(result.schema_version, SCHEMA_VERSION);
+        assert!(db.get_agent_by_name("before").unwrap().is_some());
+        assert!(db.get_agent_by_name("after").unwrap().is_none());
+
+        // The pre-restore backup still has both
+        let safety = Database::open(Path::new(&result.pre_restore_backup)).unwrap();
+        assert!(safety.get_agent_by_name("after").unwrap().is_some());
+        assert_eq!(list_backups(dir.path()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_restore_rejects_newer_or_foreign_files() {
+        let dir = TempDir::new("backup-reject");
+        let mut db = Database::in_memory().unwrap();
+        db.create_agent(&agent("keep")).unwrap();
+
+        let newer = dir.path().join("newer.db");
+        Database::open(&newer).unwrap();
+        rusqlite::Connection::open(&newer)
+            .unwrap()
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+        let error = restore(&mut db, &newer, dir.path()).unwrap_err();
+        assert!(error.contains("newer version"), "{}", error);
+
+        let foreign = dir.path().join("foreign.db");
+        rusqlite::Connection::open(&foreign).unwrap().execute("CREATE TABLE notes (body TEXT)", []).unwrap();
+        assert!(restore(&mut db, &foreign, dir.path()).is_err());
+
+        let junk = dir.path().join("junk.db");
+        fs::write(&junk, "not a database at all, just some text that is long enough").unwrap();
+        assert!(restore(&mut db, &junk, dir.path()).is_err());
+
+        // Nothing was touched
+        assert!(db.get_agent_by_name("keep").unwrap().is_some());
+        assert!(list_backups(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_automatic_backups_rotate() {
+        let dir = TempDir::new("backup-rotate");
+        let db = Arc::new(Mutex::new(Database::in_memory().unwrap()));
+        settings::set(&db.lock().unwrap(), "backup_keep", serde_json::json!(2)).unwrap();
+
+        assert!(run_if_due(&db, dir.path()).unwrap().is_some());
+        // The one just made is recent enough
+        assert!(run_if_due(&db, dir.path()).unwrap().is_none());
+
+        create_backup(&db.lock().unwrap(), dir.path(), BackupKind::Manual).unwrap();
+        for _ in 0..3 {
+            create_backup(&db.lock().unwrap(), dir.path(), BackupKind::Auto).unwrap();
+        }
+        rotate(dir.path(), 2).unwrap();
+
+        let backups = list_backups(dir.path()).unwrap();
+        assert_eq!(backups.iter().filter(|b| b.kind == "auto").count(), 2);
+        assert_eq!(backups.iter().filter(|b| b.kind == "manual").count(), 1);
+
+        settings::set(&db.lock().unwrap(), "backup_interval_hours", serde_json::json!(0)).unwrap();
+        fs::remove_dir_all(dir.path()).unwrap();
+        assert!(run_if_due(&db, dir.path()).unwrap().is_none());
+    }
+}
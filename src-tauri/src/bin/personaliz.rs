@@ -0,0 +1,367 @@
+//! Headless command line for running agents without the desktop window,
+//! e.g. from cron, SSH sessions or CI. Works on the same personaliz.db.
+
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+use personaliz_desktop_lib::{agent_runner, backup, bundle, channels, daemon};
+use personaliz_desktop_lib::database::Database;
+use personaliz_desktop_lib::event_poller::EventPoller;
+use personaliz_desktop_lib::secrets::SecretStore;
+use personaliz_desktop_lib::settings;
+
+const USAGE: &str = "Usage: personaliz <command>
+
+Commands:
+  agents list                      List agents
+  agents run <name>                Run an agent now and wait for it to finish
+  agents logs [name] [--limit N]   Show recent agent log entries
+  agents export <file> <name>...   Bundle agents with their handlers, templates and scripts
+  agents import <file> [--preview | --skip | --overwrite | --rename]
+                                   Import a bundle; conflicts are skipped unless told otherwise
+  handlers list                    List event handlers and their health
+  handlers poll [--all]            Check handlers that are due (or all of them) once
+  notifications [--limit N]        Show recent agent run notifications
+  channels log [--limit N]         Show recent email and webhook deliveries
+  channels test <name>             Send a sample failure through a notification channel
+  db backup                        Back up the database to ~/.personaliz/backups
+  db backups                       List backups, newest first
+  db restore <file>                Replace the database with a backup (the current one is backed up first)
+  settings get [key]               Print one setting, or all of them
+  settings set <key> <value>       Change a setting; value is JSON, or a plain string
+  daemon                           Run the scheduler and event poller in the foreground
+  daemon status                    Show whether a daemon is running and what it is doing
+  daemon stop                      Ask the running daemon to exit";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let db = match Database::new() {
+        Ok(db) => Arc::new(Mutex::new(db)),
+        Err(e) => {
+            eprintln!("Failed to open database: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let result = match args.as_slice() {
+        ["agents", "list"] => agents_list(&db),
+        ["agents", "run", name] => agents_run(&db, name),
+        ["agents", "logs", rest @ ..] => agents_logs(&db, rest),
+        ["agents", "export", file, names @ ..] if !names.is_empty() => agents_export(&db, file, names),
+        ["agents", "import", file] => agents_import(&db, file, "--skip"),
+        ["agents", "import", file, flag] => agents_import(&db, file, flag),
+        ["handlers", "list"] => handlers_list(&db),
+        ["handlers", "poll"] => handlers_poll(&db, false),
+        ["handlers", "poll", "--all"] => handlers_poll(&db, true),
+        ["notifications"] => notifications_list(&db, 20),
+        ["notifications", "--limit", n] => parse_limit(n).and_then(|limit| notifications_list(&db, limit)),
+        ["channels", "log"] => channels_log(&db, 20),
+        ["channels", "log", "--limit", n] => parse_limit(n).and_then(|limit| channels_log(&db, limit)),
+        ["channels", "test", name] => channels_test(&db, name),
+        ["db", "backup"] => db_backup(&db),
+        ["db", "backups"] => db_backups(),
+        ["db", "restore", file] => db_restore(&db, file),
+        ["settings", "get"] => settings_get(&db, None),
+        ["settings", "get", key] => settings_get(&db, Some(key)),
+        ["settings", "set", key, value] => settings_set(&db, key, value),
+        ["daemon"] => daemon::run(Arc::clone(&db)).map(|_| true),
+        ["daemon", "status"] => daemon_status(),
+        ["daemon", "stop"] => daemon::request("shutdown", serde_json::Value::Null).map(|_| {
+            println!("Daemon stopping");
+            true
+        }),
+        ["help" | "--help" | "-h"] => {
+            println!("{}", USAGE);
+            Ok(true)
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Each command returns Ok(false) when it ran but something failed, so the
+// exit code tells cron or CI about it.
+
+fn agents_list(db: &Arc<Mutex<Database>>) -> Result<bool, String> {
+    let agents = db.lock().unwrap().get_all_agents().map_err(|e| e.to_string())?;
+    if agents.is_empty() {
+        println!("No agents");
+    }
+    for agent in agents {
+        let schedule = match agent.schedule_time {
+            Some(ref time) => format!("{} at {}", agent.schedule, time),
+            None => agent.schedule.clone(),
+        };
+        println!(
+            "{:<30} {:<20} {}",
+            agent.name,
+            schedule,
+            if agent.is_active { "active" } else { "disabled" }
+        );
+    }
+    Ok(true)
+}
+
+fn agents_run(db: &Arc<Mutex<Database>>, name: &str) -> Result<bool, String> {
+    let agent = db
+        .lock()
+        .unwrap()
+        .get_agent_by_name(name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No agent named {}", name))?;
+
+    let run = agent_runner::run_agent(db, &agent, &settings::project_dir())?;
+    agent_runner::run_finished(db, &run, &SecretStore::open_default());
+    print!("{}", run.stdout);
+    eprint!("{}", run.stderr);
+
+    if run.timed_out {
+        eprintln!("{} timed out after {} ms", agent.name, agent.timeout);
+    } else if !run.success {
+        eprintln!("{} failed with exit code {:?}", agent.name, run.exit_code);
+    }
+    Ok(run.success)
+}
+
+fn agents_logs(db: &Arc<Mutex<Database>>, args: &[&str]) -> Result<bool, String> {
+    let (name, limit) = match args {
+        [] => (None, 20),
+        [name] => (Some(*name), 20),
+        ["--limit", n] => (None, parse_limit(n)?),
+        [name, "--limit", n] => (Some(*name), parse_limit(n)?),
+        _ => return Err("Usage: personaliz agents logs [name] [--limit N]".to_string()),
+    };
+
+    let db = db.lock().unwrap();
+    let agent_id = match name {
+        Some(name) => Some(
+            db.get_agent_by_name(name)
+                .map_err(|e| e.to_string())?
+                .and_then(|a| a.id)
+                .ok_or_else(|| format!("No agent named {}", name))?,
+        ),
+        None => None,
+    };
+
+    for log in db.get_agent_logs(agent_id, limit).map_err(|e| e.to_string())? {
+        println!("{}  {:<20} {:<9} {}", log.timestamp, log.agent_name, log.event_type, log.message);
+    }
+    Ok(true)
+}
+
+fn parse_limit(value: &str) -> Result<i64, String> {
+    value.parse().map_err(|_| format!("Invalid limit: {}", value))
+}
+
+fn agents_export(db: &Arc<Mutex<Database>>, file: &str, names: &[&str]) -> Result<bool, String> {
+    let names: Vec<String> = names.iter().map(|n| n.to_string()).collect();
+    let manifest = bundle::export(&db.lock().unwrap(), &names, &settings::project_dir(), Path::new(file))?;
+    for entry in &manifest.files {
+        println!("{}", entry.path);
+    }
+    println!("Exported {} agent(s) to {}", manifest.agents.len(), file);
+    Ok(true)
+}
+
+fn agents_import(db: &Arc<Mutex<Database>>, file: &str, flag: &str) -> Result<bool, String> {
+    let db = db.lock().unwrap();
+    let project_dir = settings::project_dir();
+    let policy = match flag {
+        "--preview" => {
+            let preview = bundle::preview(&db, Path::new(file), &project_dir)?;
+            let kinds = [
+                ("agent", &preview.agents),
+                ("event_handler", &preview.event_handlers),
+                ("prompt_template", &preview.prompt_templates),
+                ("script", &preview.scripts),
+            ];
+            for (kind, items) in kinds {
+                for item in items {
+                    println!("{:<16} {:<30} {}", kind, item.name, if item.conflict { "conflict" } else { "ok" });
+                }
+            }
+            println!("Scripts go to {}", preview.script_dir);
+            return Ok(true);
+        }
+        flag => bundle::ConflictPolicy::parse(flag.trim_start_matches("--"))?,
+    };
+
+    for item in bundle::import(&db, Path::new(file), &project_dir, policy)? {
+        if item.name == item.original_name {
+            println!("{:<16} {:<30} {}", item.kind, item.name, item.action);
+        } else {
+            println!("{:<16} {:<30} {} as {}", item.kind, item.original_name, item.action, item.name);
+        }
+    }
+    // A running daemon polls the handlers, so it should pick up new ones
+    let _ = daemon::request("handlers_changed", serde_json::Value::Null);
+    Ok(true)
+}
+
+fn handlers_list(db: &Arc<Mutex<Database>>) -> Result<bool, String> {
+    let handlers = db.lock().unwrap().get_all_event_handlers().map_err(|e| e.to_string())?;
+    if handlers.is_empty() {
+        println!("No event handlers");
+    }
+    for handler in handlers {
+        let health = match (handler.is_active, handler.consecutive_failures) {
+            (false, _) => "disabled".to_string(),
+            (true, 0) => "ok".to_string(),
+            (true, n) => format!("{} failures", n),
+        };
+        println!(
+            "{:<30} {:<9} every {:>6}s  {:<12} last check {}",
+            handler.name,
+            handler.event_type,
+            handler.interval_seconds,
+            health,
+            handler.last_check.as_deref().unwrap_or("never")
+        );
+    }
+    Ok(true)
+}
+
+fn handlers_poll(db: &Arc<Mutex<Database>>, include_not_due: bool) -> Result<bool, String> {
+    let poller = EventPoller::new(Arc::clone(db));
+    poller.set_event_sink(Arc::new(|event| {
+        println!("{} from {}: {}", event.event_type, event.handler_name, event.payload);
+    }));
+
+    let results = poller.poll_once(include_not_due);
+    if results.is_empty() {
+        println!("No handlers due");
+    }
+    let mut all_ok = true;
+    for result in results {
+        match result.error {
+            None => println!("{}: ok, {} new events", result.handler_name, result.events),
+            Some(ref error) => {
+                all_ok = false;
+                println!("{}: failed: {}", result.handler_name, error);
+                if result.disabled {
+                    println!("{}: disabled after repeated failures", result.handler_name);
+                }
+            }
+        }
+    }
+    Ok(all_ok)
+}
+
+fn notifications_list(db: &Arc<Mutex<Database>>, limit: i64) -> Result<bool, String> {
+    let history = db.lock().unwrap().get_notifications(limit).map_err(|e| e.to_string())?;
+    if history.is_empty() {
+        println!("No notifications");
+    }
+    for n in history {
+        println!("{}  {:<10} {}: {}", n.created_at, n.status, n.title, n.body);
+    }
+    Ok(true)
+}
+
+fn channels_log(db: &Arc<Mutex<Database>>, limit: i64) -> Result<bool, String> {
+    let deliveries = db.lock().unwrap().get_deliveries(limit).map_err(|e| e.to_string())?;
+    if deliveries.is_empty() {
+        println!("No deliveries");
+    }
+    for d in deliveries {
+        let error = d.error.map(|e| format!(": {}", e)).unwrap_or_default();
+        println!("{}  {:<7} {} -> {} ({}){}", d.created_at, d.status, d.agent_name, d.channel, d.kind, error);
+    }
+    Ok(true)
+}
+
+fn channels_test(db: &Arc<Mutex<Database>>, name: &str) -> Result<bool, String> {
+    let delivery = channels::send_test(db, name, &SecretStore::open_default())?;
+    match delivery.error {
+        Some(e) => eprintln!("Sending to {} failed: {}", name, e),
+        None => println!("Sent a test message to {}", name),
+    }
+    Ok(delivery.status == "sent")
+}
+
+fn db_backup(db: &Arc<Mutex<Database>>) -> Result<bool, String> {
+    let info = backup::create_backup(&db.lock().unwrap(), &backup::backup_dir(), backup::BackupKind::Manual)?;
+    println!("Backed up to {}", info.path);
+    Ok(true)
+}
+
+fn db_backups() -> Result<bool, String> {
+    let backups = backup::list_backups(&backup::backup_dir())?;
+    if backups.is_empty() {
+        println!("No backups");
+    }
+    for info in backups {
+        println!("{}  {:<12} {:>10}  {}", info.created_at, info.kind, info.size_bytes, info.path);
+    }
+    Ok(true)
+}
+
+fn db_restore(db: &Arc<Mutex<Database>>, file: &str) -> Result<bool, String> {
+    let result = backup::restore(&mut db.lock().unwrap(), Path::new(file), &backup::backup_dir())?;
+    println!("Restored {} (schema {})", result.restored_from, result.schema_version);
+    println!("The previous database was saved to {}", result.pre_restore_backup);
+    let _ = daemon::request("handlers_changed", serde_json::Value::Null);
+    Ok(true)
+}
+
+fn settings_get(db: &Arc<Mutex<Database>>, key: Option<&str>) -> Result<bool, String> {
+    let entries = settings::entries(&db.lock().unwrap())?;
+    match key {
+        Some(key) => {
+            let entry = entries
+                .iter()
+                .find(|e| e.key == key)
+                .ok_or_else(|| format!("Unknown setting: {}", key))?;
+            println!("{}", serde_json::to_string_pretty(&entry.value).map_err(|e| e.to_string())?);
+        }
+        None => {
+            for entry in entries {
+                println!("{} = {}", entry.key, entry.value);
+            }
+        }
+    }
+    Ok(true)
+}
+
+fn settings_set(db: &Arc<Mutex<Database>>, key: &str, value: &str) -> Result<bool, String> {
+    // Bare words like `phi3` are taken as strings
+    let value = serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string()));
+
+    let mut patch = serde_json::Map::new();
+    patch.insert(key.to_string(), value);
+    let changed = settings::update(&db.lock().unwrap(), &serde_json::Value::Object(patch))?;
+
+    if changed.is_empty() {
+        println!("{} unchanged", key);
+    } else {
+        println!("{} updated", key);
+    }
+    Ok(true)
+}
+
+fn daemon_status() -> Result<bool, String> {
+    match daemon::request("status", serde_json::Value::Null) {
+        Ok(status) => {
+            println!("{}", serde_json::to_string_pretty(&status).map_err(|e| e.to_string())?);
+            Ok(true)
+        }
+        Err(e) => {
+            println!("{}", e);
+            Ok(false)
+        }
+    }
+}
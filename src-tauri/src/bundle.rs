@@ -0,0 +1,727 @@
+//! Agent bundles: one or more agents packed into a zip archive together with
+//! everything they need on another machine.
+//!
+//! ```text
+//! manifest.json        format, version, agent names and a sha256 per file
+//! agents/1-<name>.json     Agent rows
+//! handlers/1-<name>.json   event handlers bound to an exported agent
+//! prompts/1-<name>.json    prompt templates the agents reference
+//! scripts/<file>           scripts named in the agents' args
+//! ```
+//!
+//! An event handler is bound to an agent by an `"agent": "<name>"` entry in
+//! its config; an agent references templates with `"prompt_template"` (or a
+//! `"prompt_templates"` array) in its config. Script arguments are stored as
+//! bare file names, which the agent runner resolves against the project
+//! directory, so `C:\Users\...\linkedin_bot.js` works wherever it's imported.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::database::{Agent, Database, EventHandler, PromptTemplate};
+
+const FORMAT: &str = "personaliz-agent-bundle";
+const FORMAT_VERSION: i64 = 1;
+const MANIFEST: &str = "manifest.json";
+// Guards against archives that would expand into something enormous
+const MAX_FILE_BYTES: u64 = 20 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: String,
+    pub version: i64,
+    pub exported_at: String,
+    pub app_version: String,
+    pub agents: Vec<String>,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    pub sha256: String,
+}
+
+/// What to do when an imported item has the same name as an existing one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConflictPolicy {
+    Skip,      // keep the existing item
+    Overwrite, // replace it (agents and handlers keep their id and history)
+    Rename,    // import alongside it as "<name> (2)"
+}
+
+impl ConflictPolicy {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "rename" => Ok(ConflictPolicy::Rename),
+            _ => Err(format!("Unknown conflict policy {} (expected skip, overwrite or rename)", value)),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemPreview {
+    pub name: String,
+    pub conflict: bool, // for scripts: a different file with that name exists
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportPreview {
+    pub exported_at: String,
+    pub agents: Vec<ItemPreview>,
+    pub event_handlers: Vec<ItemPreview>,
+    pub prompt_templates: Vec<ItemPreview>,
+    pub scripts: Vec<ItemPreview>,
+    pub script_dir: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportedItem {
+    pub kind: String, // "agent", "event_handler", "prompt_template", "script"
+    pub name: String, // name it was imported as
+    pub original_name: String,
+    pub action: String, // "created", "overwritten", "renamed", "skipped", "unchanged"
+}
+
+// The parsed, checksum-verified contents of an archive
+struct Bundle {
+    manifest: Manifest,
+    agents: Vec<Agent>,
+    handlers: Vec<EventHandler>,
+    templates: Vec<PromptTemplate>,
+    scripts: Vec<(String, Vec<u8>)>,
+}
+
+/// Writes the named agents, their handlers, templates and scripts to `out`.
+pub fn export(db: &Database, agent_names: &[String], project_dir: &Path, out: &Path) -> Result<Manifest, String> {
+    if agent_names.is_empty() {
+        return Err("No agents to export".to_string());
+    }
+
+    let mut agents = Vec::new();
+    for name in agent_names {
+        let agent = db
+            .get_agent_by_name(name)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No agent named {}", name))?;
+        agents.push(agent);
+    }
+
+    let handlers: Vec<EventHandler> = db
+        .get_all_event_handlers()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|h| bound_agent(h).is_some_and(|agent| agent_names.contains(&agent)))
+        .collect();
+
+    let mut template_names: Vec<String> = Vec::new();
+    for agent in &agents {
+        for name in referenced_templates(&agent.config_json) {
+            if !template_names.contains(&name) {
+                template_names.push(name);
+            }
+        }
+    }
+    let mut templates = Vec::new();
+    for name in &template_names {
+        match db.get_prompt_template_by_name(name).map_err(|e| e.to_string())? {
+            Some(template) => templates.push(template),
+            None => eprintln!("[Bundle] Prompt template {} is referenced but doesn't exist", name),
+        }
+    }
+
+    let mut scripts: Vec<(String, PathBuf)> = Vec::new();
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    for (i, mut agent) in agents.into_iter().enumerate() {
+        let mut rename = |arg: &str| {
+            let (file_name, source) = script_for_arg(arg, project_dir)?;
+            if !scripts.iter().any(|(name, _)| *name == file_name) {
+                scripts.push((file_name.clone(), source));
+            }
+            Some(file_name)
+        };
+        agent.args = rewrite_args(&agent.args, &mut rename);
+        agent.config_json = map_config(&agent.config_json, |config| {
+            if let Some(args) = config.get("args").map(|a| a.to_string()) {
+                config["args"] = serde_json::from_str(&rewrite_args(&args, &mut rename)).unwrap_or_default();
+            }
+            if config.get("working_directory").is_some() {
+                config["working_directory"] = Value::from(".");
+            }
+        });
+        agent.id = None;
+        agent.created_at = String::new();
+        agent.updated_at = String::new();
+        files.push((format!("agents/{}-{}.json", i + 1, slug(&agent.name)), to_json(&agent)?));
+    }
+
+    for (i, mut handler) in handlers.into_iter().enumerate() {
+        // Health describes this machine's history, not the handler
+        handler.id = None;
+        handler.last_check = None;
+        handler.consecutive_failures = 0;
+        handler.last_success = None;
+        handler.last_error = None;
+        files.push((format!("handlers/{}-{}.json", i + 1, slug(&handler.name)), to_json(&handler)?));
+    }
+
+    for (i, mut template) in templates.into_iter().enumerate() {
+        template.id = None;
+        template.version = 1;
+        template.created_at = String::new();
+        template.updated_at = String::new();
+        files.push((format!("prompts/{}-{}.json", i + 1, slug(&template.name)), to_json(&template)?));
+    }
+
+    for (file_name, source) in scripts {
+        let contents = fs::read(&source).map_err(|e| format!("Failed to read {}: {}", source.display(), e))?;
+        files.push((format!("scripts/{}", file_name), contents));
+    }
+
+    let manifest = Manifest {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        agents: agent_names.to_vec(),
+        files: files
+            .iter()
+            .map(|(path, contents)| ManifestFile { path: path.clone(), sha256: sha256(contents) })
+            .collect(),
+    };
+
+    write_archive(out, &manifest, &files).map_err(|e| format!("Failed to write {}: {}", out.display(), e))?;
+    println!("[Bundle] Exported {} agent(s) to {}", manifest.agents.len(), out.display());
+    Ok(manifest)
+}
+
+fn write_archive(out: &Path, manifest: &Manifest, files: &[(String, Vec<u8>)]) -> Result<(), String> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(File::create(out).map_err(|e| e.to_string())?);
+
+    zip.start_file(MANIFEST, options).map_err(|e| e.to_string())?;
+    zip.write_all(&to_json(manifest)?).map_err(|e| e.to_string())?;
+    for (path, contents) in files {
+        zip.start_file(path.as_str(), options).map_err(|e| e.to_string())?;
+        zip.write_all(contents).map_err(|e| e.to_string())?;
+    }
+    zip.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Lists what importing `archive` would create and what already exists.
+pub fn preview(db: &Database, archive: &Path, project_dir: &Path) -> Result<ImportPreview, String> {
+    let bundle = read_bundle(archive)?;
+    let handlers = db.get_all_event_handlers().map_err(|e| e.to_string())?;
+
+    let mut agents = Vec::new();
+    for agent in &bundle.agents {
+        let conflict = db.get_agent_by_name(&agent.name).map_err(|e| e.to_string())?.is_some();
+        agents.push(ItemPreview { name: agent.name.clone(), conflict });
+    }
+    let mut templates = Vec::new();
+    for template in &bundle.templates {
+        let conflict = db.get_prompt_template_by_name(&template.name).map_err(|e| e.to_string())?.is_some();
+        templates.push(ItemPreview { name: template.name.clone(), conflict });
+    }
+
+    Ok(ImportPreview {
+        exported_at: bundle.manifest.exported_at,
+        agents,
+        event_handlers: bundle
+            .handlers
+            .iter()
+            .map(|h| ItemPreview { name: h.name.clone(), conflict: handlers.iter().any(|e| e.name == h.name) })
+            .collect(),
+        prompt_templates: templates,
+        scripts: bundle
+            .scripts
+            .iter()
+            .map(|(name, contents)| ItemPreview {
+                name: name.clone(),
+                conflict: script_differs(&project_dir.join(name), contents),
+            })
+            .collect(),
+        script_dir: project_dir.display().to_string(),
+    })
+}
+
+/// Imports everything in `archive`, writing scripts to `project_dir`.
+/// References between the items follow any renames.
+pub fn import(db: &Database, archive: &Path, project_dir: &Path, policy: ConflictPolicy) -> Result<Vec<ImportedItem>, String> {
+    let bundle = read_bundle(archive)?;
+    let mut report = Vec::new();
+
+    // Scripts are written once the database changes have committed, so a
+    // failed import leaves neither behind
+    let mut script_names: HashMap<String, String> = HashMap::new();
+    let mut script_writes: Vec<(String, &Vec<u8>)> = Vec::new();
+    for (name, contents) in &bundle.scripts {
+        let path = project_dir.join(name);
+        let (final_name, action) = if !path.exists() {
+            (name.clone(), "created")
+        } else if !script_differs(&path, contents) {
+            (name.clone(), "unchanged")
+        } else {
+            match policy {
+                ConflictPolicy::Skip => (name.clone(), "skipped"),
+                ConflictPolicy::Overwrite => (name.clone(), "overwritten"),
+                ConflictPolicy::Rename => (unique_file_name(project_dir, name), "renamed"),
+            }
+        };
+        if matches!(action, "created" | "overwritten" | "renamed") {
+            script_writes.push((final_name.clone(), contents));
+        }
+        report.push(imported("script", &final_name, name, action));
+        script_names.insert(name.clone(), final_name);
+    }
+
+    let tx = db.begin().map_err(|e| format!("Failed to start import: {}", e))?;
+
+    let mut template_names: HashMap<String, String> = HashMap::new();
+    for template in bundle.templates {
+        let original = template.name.clone();
+        let existing = db.get_prompt_template_by_name(&original).map_err(|e| e.to_string())?;
+        let (final_name, action) = match (existing, policy) {
+            (None, _) => {
+                db.create_prompt_template(&template).map_err(|e| e.to_string())?;
+                (original.clone(), "created")
+            }
+            (Some(_), ConflictPolicy::Skip) => (original.clone(), "skipped"),
+            (Some(existing), ConflictPolicy::Overwrite) => {
+                // Lands as a new version, so the local one can be rolled back to
+                db.update_prompt_template(existing.id.unwrap_or_default(), &template).map_err(|e| e.to_string())?;
+                (original.clone(), "overwritten")
+            }
+            (Some(_), ConflictPolicy::Rename) => {
+                let name = unique_name(&original, |n| Ok(db.get_prompt_template_by_name(n)?.is_some()))?;
+                db.create_prompt_template(&PromptTemplate { name: name.clone(), ..template })
+                    .map_err(|e| e.to_string())?;
+                (name, "renamed")
+            }
+        };
+        report.push(imported("prompt_template", &final_name, &original, action));
+        template_names.insert(original, final_name);
+    }
+
+    let mut agent_names: HashMap<String, String> = HashMap::new();
+    for mut agent in bundle.agents {
+        let original = agent.name.clone();
+        agent.args = rewrite_args(&agent.args, |arg| script_names.get(arg).cloned());
+        agent.config_json = map_config(&agent.config_json, |config| {
+            if let Some(args) = config.get("args").map(|a| a.to_string()) {
+                config["args"] =
+                    serde_json::from_str(&rewrite_args(&args, |arg| script_names.get(arg).cloned())).unwrap_or_default();
+            }
+            if config.get("working_directory").is_some() {
+                config["working_directory"] = Value::from(project_dir.display().to_string());
+            }
+            rename_template_refs(config, &template_names);
+        });
+
+        let existing = db.get_agent_by_name(&original).map_err(|e| e.to_string())?;
+        let (final_name, action) = match (existing, policy) {
+            (None, _) => {
+                db.create_agent(&agent).map_err(|e| e.to_string())?;
+                (original.clone(), "created")
+            }
+            (Some(_), ConflictPolicy::Skip) => (original.clone(), "skipped"),
+            (Some(existing), ConflictPolicy::Overwrite) => {
+                db.update_agent(existing.id.unwrap_or_default(), &agent).map_err(|e| e.to_string())?;
+                (original.clone(), "overwritten")
+            }
+            (Some(_), ConflictPolicy::Rename) => {
+                agent.name = unique_name(&original, |n| Ok(db.get_agent_by_name(n)?.is_some()))?;
+                db.create_agent(&agent).map_err(|e| e.to_string())?;
+                (agent.name.clone(), "renamed")
+            }
+        };
+        report.push(imported("agent", &final_name, &original, action));
+        agent_names.insert(original, final_name);
+    }
+
+    for mut handler in bundle.handlers {
+        let original = handler.name.clone();
+        handler.config_json = map_config(&handler.config_json, |config| {
+            if let Some(renamed) = config["agent"].as_str().and_then(|a| agent_names.get(a)) {
+                config["agent"] = Value::from(renamed.as_str());
+            }
+        });
+
+        // Looked up again for each handler, to see the ones this import already added
+        let handlers = db.get_all_event_handlers().map_err(|e| e.to_string())?;
+        let existing = handlers.iter().find(|h| h.name == original);
+        let (final_name, action) = match (existing, policy) {
+            (None, _) => {
+                db.create_event_handler(&handler).map_err(|e| e.to_string())?;
+                (original.clone(), "created")
+            }
+            (Some(_), ConflictPolicy::Skip) => (original.clone(), "skipped"),
+            (Some(existing), ConflictPolicy::Overwrite) => {
+                db.update_event_handler(existing.id.unwrap_or_default(), &handler).map_err(|e| e.to_string())?;
+                (original.clone(), "overwritten")
+            }
+            (Some(_), ConflictPolicy::Rename) => {
+                handler.name = unique_name(&original, |n| Ok(handlers.iter().any(|h| h.name == n)))?;
+                db.create_event_handler(&handler).map_err(|e| e.to_string())?;
+                (handler.name.clone(), "renamed")
+            }
+        };
+        report.push(imported("event_handler", &final_name, &original, action));
+    }
+
+    tx.commit().map_err(|e| format!("Failed to save import: {}", e))?;
+
+    if !script_writes.is_empty() {
+        fs::create_dir_all(project_dir).map_err(|e| format!("Failed to create {}: {}", project_dir.display(), e))?;
+    }
+    for (name, contents) in script_writes {
+        fs::write(project_dir.join(&name), contents).map_err(|e| format!("Failed to write {}: {}", name, e))?;
+    }
+
+    println!("[Bundle] Imported {} from {}", report.len(), archive.display());
+    Ok(report)
+}
+
+// Reads an archive, rejecting it unless every file matches the manifest
+fn read_bundle(path: &Path) -> Result<Bundle, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut zip = ZipArchive::new(file).map_err(|e| format!("Not an agent bundle: {}", e))?;
+
+    let manifest: Manifest =
+        serde_json::from_slice(&read_entry(&mut zip, MANIFEST)?).map_err(|e| format!("Invalid manifest: {}", e))?;
+    if manifest.format != FORMAT {
+        return Err("Not an agent bundle".to_string());
+    }
+    if manifest.version > FORMAT_VERSION {
+        return Err(format!("Bundle format {} is newer than this app supports", manifest.version));
+    }
+
+    let listed: Vec<&str> = manifest.files.iter().map(|f| f.path.as_str()).collect();
+    if let Some(extra) = zip.file_names().find(|name| *name != MANIFEST && !listed.contains(name)) {
+        return Err(format!("{} is not listed in the manifest", extra));
+    }
+
+    let mut bundle = Bundle { manifest, agents: Vec::new(), handlers: Vec::new(), templates: Vec::new(), scripts: Vec::new() };
+    for entry in &bundle.manifest.files {
+        let parts: Vec<&str> = Path::new(&entry.path)
+            .components()
+            .map(|c| match c {
+                Component::Normal(part) => part.to_str(),
+                _ => None,
+            })
+            .collect::<Option<_>>()
+            .filter(|parts: &Vec<&str>| parts.len() == 2)
+            .ok_or_else(|| format!("Unexpected path in bundle: {}", entry.path))?;
+
+        let contents = read_entry(&mut zip, &entry.path)?;
+        if sha256(&contents) != entry.sha256 {
+            return Err(format!("Checksum mismatch for {}", entry.path));
+        }
+
+        let invalid = |e: serde_json::Error| format!("Invalid {}: {}", entry.path, e);
+        match parts[0] {
+            "agents" => bundle.agents.push(serde_json::from_slice(&contents).map_err(invalid)?),
+            "handlers" => bundle.handlers.push(serde_json::from_slice(&contents).map_err(invalid)?),
+            "prompts" => bundle.templates.push(serde_json::from_slice(&contents).map_err(invalid)?),
+            "scripts" => bundle.scripts.push((parts[1].to_string(), contents)),
+            _ => return Err(format!("Unexpected path in bundle: {}", entry.path)),
+        }
+    }
+
+    Ok(bundle)
+}
+
+fn read_entry(zip: &mut ZipArchive<File>, name: &str) -> Result<Vec<u8>, String> {
+    let entry = zip.by_name(name).map_err(|_| format!("{} is missing from the bundle", name))?;
+    let mut contents = Vec::new();
+    entry
+        .take(MAX_FILE_BYTES + 1)
+        .read_to_end(&mut contents)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    if contents.len() as u64 > MAX_FILE_BYTES {
+        return Err(format!("{} is too large", name));
+    }
+    Ok(contents)
+}
+
+/// The agent a handler is bound to, from `"agent"` in its config.
+pub fn bound_agent(handler: &EventHandler) -> Option<String> {
+    let config: Value = serde_json::from_str(&handler.config_json).ok()?;
+    config["agent"].as_str().map(String::from)
+}
+
+fn referenced_templates(config_json: &str) -> Vec<String> {
+    let config: Value = serde_json::from_str(config_json).unwrap_or_default();
+    let mut names: Vec<String> = config["prompt_template"].as_str().map(String::from).into_iter().collect();
+    if let Some(list) = config["prompt_templates"].as_array() {
+        names.extend(list.iter().filter_map(|n| n.as_str()).map(String::from));
+    }
+    names
+}
+
+fn rename_template_refs(config: &mut Value, renamed: &HashMap<String, String>) {
+    if let Some(new) = config["prompt_template"].as_str().and_then(|n| renamed.get(n)) {
+        config["prompt_template"] = Value::from(new.as_str());
+    }
+    if let Some(list) = config.get_mut("prompt_templates").and_then(|l| l.as_array_mut()) {
+        for name in list.iter_mut() {
+            if let Some(new) = name.as_str().and_then(|n| renamed.get(n)) {
+                *name = Value::from(new.as_str());
+            }
+        }
+    }
+}
+
+// The file an argument names if it is a script: the path itself when it
+// exists here, otherwise a file of the same name in the project dir (which
+// catches absolute paths from another machine)
+fn script_for_arg(arg: &str, project_dir: &Path) -> Option<(String, PathBuf)> {
+    let file_name = arg.rsplit(['/', '\\']).next()?;
+    if file_name.is_empty() || file_name == "." || file_name == ".." {
+        return None;
+    }
+    let path = Path::new(arg);
+    if path.is_absolute() && path.is_file() {
+        return Some((file_name.to_string(), path.to_path_buf()));
+    }
+    let local = project_dir.join(file_name);
+    local.is_file().then(|| (file_name.to_string(), local))
+}
+
+// Applies `rename` to each argument of a JSON args array, keeping the ones it returns None for
+fn rewrite_args(args_json: &str, mut rename: impl FnMut(&str) -> Option<String>) -> String {
+    let Ok(args) = serde_json::from_str::<Vec<String>>(args_json) else {
+        return args_json.to_string();
+    };
+    let args: Vec<String> = args.into_iter().map(|arg| rename(&arg).unwrap_or(arg)).collect();
+    serde_json::to_string(&args).unwrap_or_else(|_| args_json.to_string())
+}
+
+// Edits a JSON config object; anything that isn't one is left alone
+fn map_config(config_json: &str, edit: impl FnOnce(&mut Value)) -> String {
+    match serde_json::from_str::<Value>(config_json) {
+        Ok(mut config) if config.is_object() => {
+            edit(&mut config);
+            config.to_string()
+        }
+        _ => config_json.to_string(),
+    }
+}
+
+fn script_differs(path: &Path, contents: &[u8]) -> bool {
+    fs::read(path).is_ok_and(|existing| existing != contents)
+}
+
+// "Name" -> "Name (2)", "Name (3)", ... whichever is free first
+fn unique_name(name: &str, exists: impl Fn(&str) -> rusqlite::Result<bool>) -> Result<String, String> {
+    (2..)
+        .map(|n| format!("{} ({})", name, n))
+        .find_map(|candidate| match exists(&candidate) {
+            Ok(true) => None,
+            Ok(false) => Some(Ok(candidate)),
+            Err(e) => Some(Err(e.to_string())),
+        })
+        .unwrap_or_else(|| Err("No free name".to_string()))
+}
+
+// "bot.js" -> "bot-2.js", "bot-3.js", ...
+fn unique_file_name(dir: &Path, file_name: &str) -> String {
+    let (stem, ext) = match file_name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (file_name, String::new()),
+    };
+    (2..)
+        .map(|n| format!("{}-{}{}", stem, n, ext))
+        .find(|candidate| !dir.join(candidate).exists())
+        .unwrap_or_else(|| file_name.to_string())
+}
+
+fn slug(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.split('-').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-");
+    if slug.is_empty() { "item".to_string() } else { slug }
+}
+
+fn sha256(contents: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(contents))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(value).map_err(|e| format!("Failed to serialize bundle: {}", e))
+}
+
+fn imported(kind: &str, name: &str, original_name: &str, action: &str) -> ImportedItem {
+    ImportedItem {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        original_name: original_name.to_string(),
+        action: action.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    // An agent configured on someone else's Windows machine, with a handler and a template
+    fn seed(db: &Database, project: &Path) {
+        fs::write(project.join("linkedin_hashtag_monitor.js"), "console.log('monitor')").unwrap();
+        let config = serde_json::json!({
+            "name": "Monitor",
+            "command": "node",
+            "args": ["C:\\Users\\manoh\\personaliz-desktop\\linkedin_hashtag_monitor.js", "#openclaw"],
+            "working_directory": "C:\\Users\\manoh\\personaliz-desktop",
+            "prompt_template": "summary",
+        })
+        .to_string();
+        db.create_agent(&Agent::from_config("Monitor", &config).unwrap()).unwrap();
+        db.create_event_handler(&EventHandler {
+            id: None,
+            name: "Openclaw feed".to_string(),
+            event_type: "rss".to_string(),
+            url: Some("https://example.com/feed".to_string()),
+            interval_seconds: 600,
+            last_check: None,
+            is_active: true,
+            config_json: r#"{"agent":"Monitor"}"#.to_string(),
+            consecutive_failures: 0,
+            last_success: None,
+            last_error: None,
+        })
+        .unwrap();
+        db.create_prompt_template(&PromptTemplate {
+            id: None,
+            name: "summary".to_string(),
+            description: None,
+            body: "Summarize {{event.payload.title}}".to_string(),
+            model: None,
+            version: 1,
+            created_at: String::new(),
+            updated_at: String::new(),
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_export_and_import_rewrites_paths() {
+        let dir = TempDir::new("bundle-roundtrip");
+        let (from, to) = (dir.path().join("from"), dir.path().join("to"));
+        fs::create_dir_all(&from).unwrap();
+        let archive = dir.path().join("monitor.zip");
+
+        let source = Database::in_memory().unwrap();
+        seed(&source, &from);
+        let manifest = export(&source, &["Monitor".to_string()], &from, &archive).unwrap();
+        assert_eq!(manifest.files.len(), 4);
+
+        let target = Database::in_memory().unwrap();
+        let preview = preview(&target, &archive, &to).unwrap();
+        assert_eq!(preview.agents[0].name, "Monitor");
+        assert!(!preview.agents[0].conflict);
+        assert_eq!(preview.scripts[0].name, "linkedin_hashtag_monitor.js");
+
+        let report = import(&target, &archive, &to, ConflictPolicy::Skip).unwrap();
+        assert!(report.iter().all(|item| item.action == "created"));
+
+        let agent = target.get_agent_by_name("Monitor").unwrap().unwrap();
+        assert_eq!(agent.args, r##"["linkedin_hashtag_monitor.js","#openclaw"]"##);
+        let config: Value = serde_json::from_str(&agent.config_json).unwrap();
+        assert_eq!(config["working_directory"], to.display().to_string());
+        assert_eq!(fs::read_to_string(to.join("linkedin_hashtag_monitor.js")).unwrap(), "console.log('monitor')");
+        assert_eq!(target.get_all_event_handlers().unwrap()[0].name, "Openclaw feed");
+        assert!(target.get_prompt_template_by_name("summary").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let dir = TempDir::new("bundle-conflicts");
+        let archive = dir.path().join("monitor.zip");
+        let db = Database::in_memory().unwrap();
+        seed(&db, dir.path());
+        export(&db, &["Monitor".to_string()], dir.path(), &archive).unwrap();
+
+        fs::write(dir.path().join("linkedin_hashtag_monitor.js"), "changed locally").unwrap();
+        let preview = preview(&db, &archive, dir.path()).unwrap();
+        assert!(preview.agents[0].conflict && preview.event_handlers[0].conflict && preview.scripts[0].conflict);
+
+        let report = import(&db, &archive, dir.path(), ConflictPolicy::Rename).unwrap();
+        assert!(report.iter().all(|item| item.action == "renamed"));
+
+        let renamed = db.get_agent_by_name("Monitor (2)").unwrap().unwrap();
+        assert_eq!(renamed.args, r##"["linkedin_hashtag_monitor-2.js","#openclaw"]"##);
+        let config: Value = serde_json::from_str(&renamed.config_json).unwrap();
+        assert_eq!(config["prompt_template"], "summary (2)");
+        let handler = db.get_all_event_handlers().unwrap().into_iter().find(|h| h.name == "Openclaw feed (2)").unwrap();
+        assert_eq!(bound_agent(&handler).as_deref(), Some("Monitor (2)"));
+        assert_eq!(fs::read_to_string(dir.path().join("linkedin_hashtag_monitor.js")).unwrap(), "changed locally");
+
+        let report = import(&db, &archive, dir.path(), ConflictPolicy::Skip).unwrap();
+        assert!(report.iter().all(|item| item.action == "skipped"));
+    }
+
+    #[test]
+    fn test_renamed_handlers_dont_collide() {
+        let dir = TempDir::new("bundle-handler-names");
+        let archive = dir.path().join("monitor.zip");
+        let source = Database::in_memory().unwrap();
+        seed(&source, dir.path());
+        let mut second = source.get_all_event_handlers().unwrap().remove(0);
+        second.name = "Openclaw feed (2)".to_string();
+        source.create_event_handler(&second).unwrap();
+        export(&source, &["Monitor".to_string()], dir.path(), &archive).unwrap();
+
+        // Renaming "Openclaw feed" takes "(2)" before the bundle's own "(2)" arrives
+        let target = Database::in_memory().unwrap();
+        seed(&target, dir.path());
+        import(&target, &archive, dir.path(), ConflictPolicy::Rename).unwrap();
+        let names: std::collections::HashSet<String> =
+            target.get_all_event_handlers().unwrap().into_iter().map(|h| h.name).collect();
+        assert_eq!(names.len(), 3);
+    }
+
+    #[test]
+    fn test_rejects_tampered_bundle() {
+        let dir = TempDir::new("bundle-tampered");
+        let archive = dir.path().join("monitor.zip");
+        let db = Database::in_memory().unwrap();
+        seed(&db, dir.path());
+        let manifest = export(&db, &["Monitor".to_string()], dir.path(), &archive).unwrap();
+
+        // Same layout, one script swapped out
+        let mut files = Vec::new();
+        let mut zip = ZipArchive::new(File::open(&archive).unwrap()).unwrap();
+        for entry in &manifest.files {
+            files.push((entry.path.clone(), read_entry(&mut zip, &entry.path).unwrap()));
+        }
+        files.last_mut().unwrap().1 = b"require('child_process')".to_vec();
+        write_archive(&archive, &manifest, &files).unwrap();
+
+        let error = import(&db, &archive, dir.path(), ConflictPolicy::Overwrite).unwrap_err();
+        assert!(error.starts_with("Checksum mismatch"), "{}", error);
+    }
+
+    #[test]
+    fn test_unique_file_name() {
+        let dir = TempDir::new("bundle-names");
+        fs::write(dir.path().join("bot.js"), "").unwrap();
+        fs::write(dir.path().join("bot-2.js"), "").unwrap();
+        assert_eq!(unique_file_name(dir.path(), "bot.js"), "bot-3.js");
+        assert_eq!(unique_file_name(dir.path(), "README"), "README-2");
+        assert_eq!(slug("LinkedIn Hashtag Monitor!"), "linkedin-hashtag-monitor");
+    }
+}
@@ -0,0 +1,463 @@
+//! Email (SMTP) and JSON webhook alerts for finished agent runs, configured
+//! in the `notification_channels` setting. Unlike desktop notifications they
+//! are not rate limited: every run a channel subscribes to is sent, and every
+//! attempt lands in the `notification_deliveries` table.
+//!
+//! Subjects and bodies are templates (see prompt_template.rs) with `agent.*`
+//! plus `run.status` ("succeeded", "failed", "timed out"), `run.success`,
+//! `run.exit_code`, `run.duration`, `run.duration_ms`, `run.finished_at` and
+//! `run.log_excerpt` (the last lines of stderr, or stdout if stderr is empty).
+
+use chrono::Utc;
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::agent_runner::AgentRun;
+use crate::database::{Agent, Database, DeliveryRecord};
+use crate::prompt_template;
+use crate::secrets::{self, SecretStore};
+use crate::settings::{self, ChannelTransport, NotificationChannel};
+
+pub const DEFAULT_SUBJECT: &str = "[Personaliz] {{agent.name}} {{run.status}}";
+pub const DEFAULT_BODY: &str =
+    "{{agent.name}} {{run.status}} after {{run.duration}} (exit code {{run.exit_code}}).\n\n{{run.log_excerpt}}";
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(15);
+const EXCERPT_LINES: usize = 20;
+const EXCERPT_MAX_CHARS: usize = 2000;
+
+/// Sends a finished run to every enabled channel that wants it and records
+/// each attempt. Returns the records, empty if no channel matched.
+pub fn dispatch(db: &Arc<Mutex<Database>>, run: &AgentRun, store: &SecretStore) -> Vec<DeliveryRecord> {
+    let settings = settings::load_shared(db);
+    let channels: Vec<&NotificationChannel> = settings
+        .notification_channels
+        .iter()
+        .filter(|channel| wants_run(channel, run))
+        .collect();
+    if channels.is_empty() {
+        return Vec::new();
+    }
+
+    let agent = db.lock().unwrap().get_agent_by_name(&run.agent_name).ok().flatten();
+    let context = run_context(agent.as_ref(), run);
+    channels
+        .into_iter()
+        .map(|channel| {
+            let result = deliver(channel, run, &context, store);
+            record(db, channel, Some(run.agent_id), &run.agent_name, result)
+        })
+        .collect()
+}
+
+/// Sends a sample failed run through the named channel, whether or not it is
+/// enabled, and records the attempt.
+pub fn send_test(db: &Arc<Mutex<Database>>, name: &str, store: &SecretStore) -> Result<DeliveryRecord, String> {
+    let settings = settings::load_shared(db);
+    let channel = settings
+        .notification_channels
+        .iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| format!("No notification channel named {}", name))?;
+
+    let run = sample_run();
+    let context = run_context(Some(&sample_agent()), &run);
+    let result = deliver(channel, &run, &context, store);
+    Ok(record(db, channel, None, &run.agent_name, result))
+}
+
+/// Checks a channel's templates for syntax errors and unknown variables.
+/// `agent.metadata.*` can't be checked up front and is allowed.
+pub fn check_templates(channel: &NotificationChannel) -> Result<(), String> {
+    let sample = run_context(Some(&sample_agent()), &sample_run());
+    for template in [&channel.subject, &channel.body] {
+        match prompt_template::render(template, &sample) {
+            Ok(_) => {}
+            Err(e) if !e.starts_with("Missing template variables") => return Err(e),
+            Err(_) => {
+                let unknown: Vec<String> = prompt_template::variables(template)
+                    .into_iter()
+                    .filter(|path| !path.starts_with("agent.metadata."))
+                    .filter(|path| prompt_template::render(&format!("{{{{{}}}}}", path), &sample).is_err())
+                    .collect();
+                if !unknown.is_empty() {
+                    return Err(format!("Unknown template variables: {}", unknown.join(", ")));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn wants_run(channel: &NotificationChannel, run: &AgentRun) -> bool {
+    let outcome = if run.success { channel.on_success } else { channel.on_failure };
+    channel.enabled && outcome && (channel.agents.is_empty() || channel.agents.contains(&run.agent_name))
+}
+
+// Has every agent column set, so templates can use any of them
+fn sample_agent() -> Agent {
+    Agent {
+        id: Some(0),
+        name: "example-agent".to_string(),
+        description: Some("Checks the example site".to_string()),
+        role: Some("Monitor".to_string()),
+        goal: Some("Catch outages early".to_string()),
+        tools: None,
+        schedule: "hourly".to_string(),
+        schedule_time: Some("09:00".to_string()),
+        command: "node".to_string(),
+        args: "[]".to_string(),
+        timeout: 300000,
+        config_json: "{}".to_string(),
+        created_at: String::new(),
+        updated_at: String::new(),
+        is_active: true,
+    }
+}
+
+fn sample_run() -> AgentRun {
+    AgentRun {
+        agent_id: 0,
+        agent_name: "example-agent".to_string(),
+        success: false,
+        exit_code: Some(1),
+        timed_out: false,
+        duration_ms: 1500,
+        stdout: String::new(),
+        stderr: "This is a test message from Personaliz.\n".to_string(),
+    }
+}
+
+fn run_context(agent: Option<&Agent>, run: &AgentRun) -> Value {
+    let status = if run.success {
+        "succeeded"
+    } else if run.timed_out {
+        "timed out"
+    } else {
+        "failed"
+    };
+    let mut extra = json!({
+        "run": {
+            "status": status,
+            "success": run.success,
+            "exit_code": run.exit_code.map_or(json!("none"), |code| json!(code)),
+            "duration": format!("{:.1} s", run.duration_ms as f64 / 1000.0),
+            "duration_ms": run.duration_ms,
+            "finished_at": Utc::now().to_rfc3339(),
+            "log_excerpt": log_excerpt(run),
+        }
+    });
+    // The agent may have been deleted while it ran
+    if agent.is_none() {
+        extra["agent"] = json!({"name": run.agent_name});
+    }
+    prompt_template::build_context(agent, None, Some(&extra))
+}
+
+fn log_excerpt(run: &AgentRun) -> String {
+    let output = if run.stderr.trim().is_empty() { &run.stdout } else { &run.stderr };
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    let excerpt = lines[lines.len().saturating_sub(EXCERPT_LINES)..].join("\n");
+    if excerpt.trim().is_empty() {
+        return "(no output)".to_string();
+    }
+    // Keep the end, where the error usually is
+    let skip = excerpt.chars().count().saturating_sub(EXCERPT_MAX_CHARS);
+    excerpt.chars().skip(skip).collect()
+}
+
+// A custom template can still miss at send time, e.g. `{{agent.goal}}` for
+// an agent without a goal; the alert then goes out with the default instead
+fn render_message(channel: &NotificationChannel, context: &Value) -> Result<(String, String), String> {
+    let render = |template: &str, default: &str| {
+        if template.is_empty() {
+            return prompt_template::render(default, context);
+        }
+        prompt_template::render(template, context).or_else(|e| {
+            eprintln!("[Channels] {}: {}, using the default template", channel.name, e);
+            prompt_template::render(default, context)
+        })
+    };
+    Ok((render(&channel.subject, DEFAULT_SUBJECT)?, render(&channel.body, DEFAULT_BODY)?))
+}
+
+fn deliver(channel: &NotificationChannel, run: &AgentRun, context: &Value, store: &SecretStore) -> Result<(), String> {
+    let (subject, body) = render_message(channel, context)?;
+    match &channel.transport {
+        ChannelTransport::Smtp { host, port, username, security, from, to } => {
+            let password = if username.is_empty() {
+                String::new()
+            } else {
+                store
+                    .get(&secrets::channel_password(&channel.name))?
+                    .ok_or_else(|| format!("No SMTP password saved for channel {}", channel.name))?
+            };
+            let server = SmtpServer { host, port: *port, security, username, password: &password };
+            send_email(&server, from, to, &subject, &body)
+        }
+        ChannelTransport::Webhook { url, headers, secret_headers } => {
+            let mut headers = headers.clone();
+            for name in secret_headers {
+                let value = store
+                    .get(&secrets::channel_header(&channel.name, name))?
+                    .ok_or_else(|| format!("No value saved for header {} of channel {}", name, channel.name))?;
+                headers.insert(name.clone(), value);
+            }
+            let run_vars = &context["run"];
+            let payload = json!({
+                "text": body,
+                "subject": subject,
+                "channel": channel.name,
+                "agent": run.agent_name,
+                "status": run_vars["status"],
+                "success": run.success,
+                "exit_code": run.exit_code,
+                "duration_ms": run.duration_ms,
+                "log_excerpt": run_vars["log_excerpt"],
+                "timestamp": run_vars["finished_at"],
+            });
+            post_webhook(url, &headers, &payload)
+        }
+    }
+}
+
+struct SmtpServer<'a> {
+    host: &'a str,
+    port: u16,
+    security: &'a str,
+    username: &'a str,
+    password: &'a str,
+}
+
+fn send_email(server: &SmtpServer, from: &str, to: &[String], subject: &str, body: &str) -> Result<(), String> {
+    let parse = |address: &str| {
+        address
+            .parse::<Mailbox>()
+            .map_err(|e| format!("Invalid email address {:?}: {}", address, e))
+    };
+    let mut message = Message::builder()
+        .from(parse(from)?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN);
+    for address in to {
+        message = message.to(parse(address)?);
+    }
+    let message = message
+        .body(body.to_string())
+        .map_err(|e| format!("Failed to build email: {}", e))?;
+
+    let builder = match server.security {
+        "tls" => SmtpTransport::relay(server.host),
+        "starttls" => SmtpTransport::starttls_relay(server.host),
+        _ => Ok(SmtpTransport::builder_dangerous(server.host)),
+    }
+    .map_err(|e| format!("Failed to set up TLS for {}: {}", server.host, e))?;
+    let mut builder = builder.port(server.port).timeout(Some(SEND_TIMEOUT));
+    if !server.username.is_empty() {
+        builder = builder.credentials(Credentials::new(server.username.to_string(), server.password.to_string()));
+    }
+
+    builder
+        .build()
+        .send(&message)
+        .map(|_| ())
+        .map_err(|e| format!("SMTP delivery to {}:{} failed: {}", server.host, server.port, e))
+}
+
+fn post_webhook(url: &str, headers: &BTreeMap<String, String>, payload: &Value) -> Result<(), String> {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(SEND_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let mut request = client.post(url).json(payload);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+
+    let response = request.send().map_err(|e| format!("Webhook request to {} failed: {}", url, e))?;
+    if !response.status().is_success() {
+        return Err(format!("Webhook {} answered HTTP {}", url, response.status()));
+    }
+    Ok(())
+}
+
+fn record(
+    db: &Arc<Mutex<Database>>,
+    channel: &NotificationChannel,
+    agent_id: Option<i64>,
+    agent_name: &str,
+    result: Result<(), String>,
+) -> DeliveryRecord {
+    let kind = match channel.transport {
+        ChannelTransport::Smtp { .. } => "smtp",
+        ChannelTransport::Webhook { .. } => "webhook",
+    };
+    match &result {
+        Ok(()) => println!("[Channels] Sent {} to {}", agent_name, channel.name),
+        Err(e) => eprintln!("[Channels] Failed to send {} to {}: {}", agent_name, channel.name, e),
+    }
+
+    let mut delivery = DeliveryRecord {
+        id: None,
+        channel: channel.name.clone(),
+        kind: kind.to_string(),
+        agent_id,
+        agent_name: agent_name.to_string(),
+        status: if result.is_ok() { "sent" } else { "failed" }.to_string(),
+        error: result.err(),
+        created_at: Utc::now().to_rfc3339(),
+    };
+    match db.lock().unwrap().add_delivery(&delivery) {
+        Ok(id) => delivery.id = Some(id),
+        Err(e) => eprintln!("[Channels] Failed to record delivery: {}", e),
+    }
+    delivery
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{MockServer, MockSmtpServer, TempDir};
+
+    fn setup(channels: Value) -> (Arc<Mutex<Database>>, i64) {
+        let db = Database::in_memory().unwrap();
+        settings::set(&db, "notification_channels", channels).unwrap();
+        let config = r#"{"command":"node","metadata":{"team":"growth"}}"#;
+        let id = db.create_agent(&Agent::from_config("monitor", config).unwrap()).unwrap();
+        (Arc::new(Mutex::new(db)), id)
+    }
+
+    // Channels without secret headers or passwords never read the store
+    fn no_secrets() -> SecretStore {
+        SecretStore::file_store(std::env::temp_dir().join("personaliz-channels-unused"), None)
+    }
+
+    fn run(agent_id: i64, success: bool) -> AgentRun {
+        AgentRun {
+            agent_id,
+            agent_name: "monitor".to_string(),
+            success,
+            exit_code: Some(if success { 0 } else { 2 }),
+            timed_out: false,
+            duration_ms: 2500,
+            stdout: "checked 3 pages\n".to_string(),
+            stderr: if success { String::new() } else { "Navigating...\nlogin page not found\n".to_string() },
+        }
+    }
+
+    #[test]
+    fn test_webhook_payload_and_templates() {
+        let server = MockServer::start(vec![(200, "text/plain", "ok".to_string())]);
+        let (db, id) = setup(json!([{
+            "name": "chat",
+            "kind": "webhook",
+            "url": format!("{}/hooks/alerts", server.url),
+            "headers": {"X-Source": "personaliz"},
+            "secret_headers": ["X-Token"],
+            "body": "{{agent.name}} ({{agent.metadata.team}}) {{run.status}}: {{run.log_excerpt}}",
+        }]));
+
+        let dir = TempDir::new("channels");
+        let store = SecretStore::file_store(dir.path().to_path_buf(), Some("test passphrase".to_string()));
+        let error = dispatch(&db, &run(id, false), &store)[0].error.clone().unwrap();
+        assert!(error.contains("No value saved for header X-Token"), "{}", error);
+
+        store.set(&secrets::channel_header("chat", "X-Token"), "abc").unwrap();
+        let deliveries = dispatch(&db, &run(id, false), &store);
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, "sent", "{:?}", deliveries[0].error);
+
+        let request = server.request();
+        assert_eq!(request.path, "/hooks/alerts");
+        assert_eq!(request.header("x-token"), Some("abc"));
+        assert_eq!(request.header("x-source"), Some("personaliz"));
+        let payload = request.json();
+        assert_eq!(payload["text"], "monitor (growth) failed: Navigating...\nlogin page not found");
+        assert_eq!(payload["subject"], "[Personaliz] monitor failed");
+        assert_eq!(payload["exit_code"], 2);
+        assert_eq!(payload["success"], false);
+
+        // Successes are off by default
+        assert!(dispatch(&db, &run(id, true), &store).is_empty());
+    }
+
+    #[test]
+    fn test_missing_agent_column_falls_back_to_default_body() {
+        let server = MockServer::start(vec![(200, "text/plain", "ok".to_string())]);
+        let (db, id) = setup(json!([{"name": "chat", "kind": "webhook", "url": server.url, "body": "Goal: {{agent.goal}}"}]));
+
+        assert_eq!(dispatch(&db, &run(id, false), &no_secrets())[0].status, "sent");
+        let text = server.request().json()["text"].as_str().unwrap().to_string();
+        assert!(text.starts_with("monitor failed after 2.5 s"), "{}", text);
+    }
+
+    #[test]
+    fn test_smtp_delivery() {
+        let smtp = MockSmtpServer::start();
+        let (db, id) = setup(json!([{
+            "name": "ops-email",
+            "kind": "smtp",
+            "host": "127.0.0.1",
+            "port": smtp.port,
+            "security": "none",
+            "from": "Personaliz <alerts@example.com>",
+            "to": ["ops@example.com", "oncall@example.com"],
+        }]));
+
+        let deliveries = dispatch(&db, &run(id, false), &no_secrets());
+        assert_eq!(deliveries[0].status, "sent", "{:?}", deliveries[0].error);
+
+        let mail = smtp.message();
+        assert_eq!(mail.from, "alerts@example.com");
+        assert_eq!(mail.to, ["ops@example.com", "oncall@example.com"]);
+        assert!(mail.data.contains("Subject: [Personaliz] monitor failed"), "{}", mail.data);
+        assert!(mail.data.contains("monitor failed after 2.5 s (exit code 2)"), "{}", mail.data);
+        assert!(mail.data.contains("login page not found"), "{}", mail.data);
+    }
+
+    #[test]
+    fn test_filters_and_failure_logging() {
+        let server = MockServer::start(vec![(500, "text/plain", "boom".to_string())]);
+        let (db, id) = setup(json!([
+            {"name": "other-agents", "kind": "webhook", "url": server.url, "agents": ["backup-checker"]},
+            {"name": "disabled", "kind": "webhook", "url": server.url, "enabled": false},
+            {"name": "broken", "kind": "webhook", "url": server.url, "on_success": true},
+        ]));
+
+        let deliveries = dispatch(&db, &run(id, true), &no_secrets());
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].channel, "broken");
+        assert_eq!(deliveries[0].status, "failed");
+        assert!(deliveries[0].error.as_deref().unwrap().contains("HTTP 500"));
+
+        let log = db.lock().unwrap().get_deliveries(10).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].agent_id, Some(id));
+
+        // Test messages go out even through disabled channels, and nowhere is listening now
+        let test = send_test(&db, "disabled", &no_secrets()).unwrap();
+        assert_eq!((test.status.as_str(), test.agent_id), ("failed", None));
+        assert!(send_test(&db, "missing", &no_secrets()).is_err());
+    }
+
+    #[test]
+    fn test_check_templates() {
+        let channel = |body: &str| {
+            serde_json::from_value::<NotificationChannel>(json!({
+                "name": "chat", "kind": "webhook", "url": "http://localhost", "body": body,
+            }))
+            .unwrap()
+        };
+        assert!(check_templates(&channel("")).is_ok());
+        assert!(check_templates(&channel("{{agent.name}} {{run.exit_code}} {{agent.metadata.owner}}")).is_ok());
+        assert!(check_templates(&channel("{{agent.description}} {{agent.role}} {{agent.goal}} {{agent.schedule}}")).is_ok());
+        assert!(check_templates(&channel("{{run.stdout}}")).unwrap_err().contains("run.stdout"));
+        assert!(check_templates(&channel("{{run.status")).is_err());
+    }
+}
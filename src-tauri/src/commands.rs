@@ -0,0 +1,1601 @@
+//! Tauri commands. Registered by `crate::configure`; each returns a JSON string
+//! or an error message for the frontend.
+
+use std::process::Command;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Runtime};
+
+use crate::{
+    agent_builder, backup, bundle, channels, dependencies, installer, llm, memory, prompt_template, secrets, settings, AppEnv,
+};
+use crate::daemon::DaemonClient;
+use crate::database::{Database, Agent, AgentLog, EventHandler, PromptTemplate};
+use crate::event_poller::EventPoller;
+use crate::llm::LlmProvider;
+use crate::secrets::SecretStore;
+
+#[tauri::command]
+pub fn run_command(cmd: String) -> Result<String, String> {
+
+    let output = Command::new("cmd")
+        .args(["/C", &cmd])
+        .output()
+        .map_err(|e| format!("Failed to execute command: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Command failed: {}", stderr));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    Ok(stdout)
+}
+
+#[tauri::command]
+pub fn create_agent_file(name: String, content: String, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+
+    // Create .agents directory if it doesn't exist
+    let agents_dir = "C:\\Users\\manoh\\openclaw\\.agents";
+    fs::create_dir_all(agents_dir)
+        .map_err(|e| format!("Failed to create .agents directory: {}", e))?;
+
+    // Generate filename from agent name
+    let filename = name.replace(" ", "_").to_lowercase();
+    let path = format!("{}\\{}.json", agents_dir, filename);
+
+    fs::write(&path, &content)
+        .map_err(|e| format!("Failed to write agent file: {}", e))?;
+
+    // Also store in database
+    if let Ok(agent) = Agent::from_config(&name, &content) {
+        let db_lock = db.lock().unwrap();
+        let _ = db_lock.create_agent(&agent); // Ignore errors if agent already exists
+    }
+
+    Ok(format!("Agent file created: {}", path))
+}
+
+/// Applies a partial settings update (see settings::update) and emits
+/// `settings_changed` with the keys that actually changed. An `llm_api_key`
+/// in the patch goes to secure storage instead, once the rest of the patch
+/// is accepted; empty leaves it unchanged.
+fn update_and_notify<R: Runtime>(
+    app: &tauri::AppHandle<R>,
+    db: &Arc<Mutex<Database>>,
+    secrets: &SecretStore,
+    mut patch: serde_json::Value,
+) -> Result<Vec<String>, String> {
+    let mut api_key = None;
+    if let Some(fields) = patch.as_object_mut() {
+        if let Some(key) = fields.remove("llm_api_key") {
+            let key = key.as_str().ok_or("llm_api_key must be a string")?;
+            if !key.is_empty() {
+                api_key = Some(key.to_string());
+            }
+        }
+    }
+
+    let db_lock = db.lock().unwrap();
+    let changed = settings::update(&db_lock, &patch)?;
+    if let Some(key) = api_key {
+        secrets.set(secrets::LLM_API_KEY, &key)?;
+    }
+    if !changed.is_empty() {
+        let payload = serde_json::json!({"keys": changed, "settings": settings::load(&db_lock)});
+        if let Err(e) = app.emit("settings_changed", payload) {
+            eprintln!("Failed to emit settings_changed: {}", e);
+        }
+    }
+    Ok(changed)
+}
+
+/// Updates only the settings present in `patch`, a JSON object such as
+/// {"llm_model": "llama3"}. Returns the keys that changed. A running event
+/// poller keeps its concurrency, timeout, backoff and failure threshold
+/// until it is next started.
+#[tauri::command]
+pub fn update_settings<R: Runtime>(
+    patch: serde_json::Value,
+    app: tauri::AppHandle<R>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let changed = update_and_notify(&app, &db, &env.secrets, patch)?;
+    Ok(serde_json::json!({"changed": changed, "message": "Settings saved successfully"}).to_string())
+}
+
+/// Saves the LLM settings from the settings form. Kept for older callers;
+/// new code should use update_settings.
+#[tauri::command]
+pub fn save_settings<R: Runtime>(
+    llm_provider: String,
+    llm_api_key: String,
+    llm_model: String,
+    llm_endpoint: String,
+    app: tauri::AppHandle<R>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let patch = serde_json::json!({
+        "llm_provider": llm_provider,
+        "llm_api_key": llm_api_key,
+        "llm_model": llm_model,
+        "llm_endpoint": llm_endpoint,
+    });
+    update_and_notify(&app, &db, &env.secrets, patch)?;
+
+    Ok("Settings saved successfully".to_string())
+}
+
+/// Returns the current settings. The API key is never included; use
+/// has_api_key to find out whether one is stored.
+#[tauri::command]
+pub fn load_settings(db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    serde_json::to_string(&settings::load(&db.lock().unwrap()))
+        .map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+/// Every setting with its value, default and when it was last changed.
+#[tauri::command]
+pub fn get_setting_entries(db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let entries = settings::entries(&db.lock().unwrap())?;
+    serde_json::to_string(&entries)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))
+}
+
+/// Sets a single setting by key, e.g. ("poller_max_concurrency", 8), which
+/// like the other poller settings applies from the poller's next start.
+#[tauri::command]
+pub fn set_setting<R: Runtime>(
+    key: String,
+    value: serde_json::Value,
+    app: tauri::AppHandle<R>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let mut patch = serde_json::Map::new();
+    patch.insert(key.clone(), value);
+    update_and_notify(&app, &db, &env.secrets, serde_json::Value::Object(patch))?;
+
+    Ok(format!("Setting {} saved", key))
+}
+
+#[tauri::command]
+pub fn has_api_key(env: tauri::State<AppEnv>) -> Result<bool, String> {
+    env.secrets.has(secrets::LLM_API_KEY)
+}
+
+#[tauri::command]
+pub fn clear_api_key(env: tauri::State<AppEnv>) -> Result<String, String> {
+    env.secrets.delete(secrets::LLM_API_KEY)?;
+    Ok("API key removed".to_string())
+}
+
+/// Stores the API key for one provider of the fallback chain, e.g. "openai".
+#[tauri::command]
+pub fn save_provider_api_key(provider: String, api_key: String, env: tauri::State<AppEnv>) -> Result<String, String> {
+    let name = secrets::provider_api_key(&provider);
+    if api_key.is_empty() {
+        env.secrets.delete(&name)?;
+        return Ok(format!("API key for {} removed", provider));
+    }
+    env.secrets.set(&name, &api_key)?;
+    Ok(format!("API key for {} saved", provider))
+}
+
+/// Replaces the providers tried, in order, when the primary one is unreachable.
+#[tauri::command]
+pub fn save_llm_fallback_providers<R: Runtime>(
+    providers: Vec<settings::LlmProviderConfig>,
+    app: tauri::AppHandle<R>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    update_and_notify(&app, &db, &env.secrets, serde_json::json!({"llm_fallback_providers": providers}))?;
+    Ok("Fallback providers saved".to_string())
+}
+
+/// Checks every provider in the chain and returns a ProviderHealth list as JSON.
+#[tauri::command]
+pub async fn llm_probe_providers(
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let settings = settings::load_shared(&db);
+    let secrets = env.secrets.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let health = llm::probe_all(&settings, &secrets);
+        serde_json::to_string(&health)
+            .map_err(|e| format!("Failed to serialize provider health: {}", e))
+    })
+    .await
+    .map_err(|e| format!("LLM task failed: {}", e))?
+}
+
+/// Sends a single prompt to the provider configured in settings and returns
+/// the ChatResponse as JSON. Runs off the main thread, since local models can
+/// take a while.
+#[tauri::command]
+pub async fn llm_complete(
+    prompt: String,
+    system: Option<String>,
+    agent_id: Option<i64>,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let db = Arc::clone(&db);
+    let secrets = env.secrets.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let provider = llm::metered_from_settings(&settings::load_shared(&db), db, agent_id, &secrets).map_err(|e| e.to_string())?;
+        let response = provider.complete(&prompt, system).map_err(|e| e.to_string())?;
+
+        serde_json::to_string(&response)
+            .map_err(|e| format!("Failed to serialize LLM response: {}", e))
+    })
+    .await
+    .map_err(|e| format!("LLM task failed: {}", e))?
+}
+
+#[tauri::command]
+pub async fn llm_chat(
+    request: llm::ChatRequest,
+    agent_id: Option<i64>,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let db = Arc::clone(&db);
+    let secrets = env.secrets.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let provider = llm::metered_from_settings(&settings::load_shared(&db), db, agent_id, &secrets).map_err(|e| e.to_string())?;
+        let response = provider.chat(&request).map_err(|e| e.to_string())?;
+
+        serde_json::to_string(&response)
+            .map_err(|e| format!("Failed to serialize LLM response: {}", e))
+    })
+    .await
+    .map_err(|e| format!("LLM task failed: {}", e))?
+}
+
+/// Drafts an agent config from a plain-English description using the
+/// configured model. Nothing is saved: the returned AgentDraft is shown to the
+/// user, who confirms it by passing its config_json to create_agent_file.
+#[tauri::command]
+pub async fn draft_agent(
+    description: String,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let db = Arc::clone(&db);
+    let secrets = env.secrets.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let provider = llm::metered_from_settings(&settings::load_shared(&db), db, None, &secrets).map_err(|e| e.to_string())?;
+        let draft = agent_builder::draft_agent(provider.as_ref(), &description)?;
+
+        serde_json::to_string(&draft)
+            .map_err(|e| format!("Failed to serialize agent draft: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Agent builder task failed: {}", e))?
+}
+
+#[derive(Clone, serde::Serialize)]
+struct LlmStreamDelta {
+    request_id: String,
+    delta: String,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct LlmStreamEnd {
+    request_id: String,
+    response: Option<llm::ChatResponse>,
+    error: Option<String>,
+    cancelled: bool,
+}
+
+/// Streams a chat completion to the frontend. Text arrives as `llm-stream`
+/// events and the outcome as one `llm-stream-end` event, both tagged with the
+/// caller's request_id. Cancel with llm_cancel.
+#[tauri::command]
+pub async fn llm_stream<R: Runtime>(
+    request_id: String,
+    request: llm::ChatRequest,
+    agent_id: Option<i64>,
+    app: tauri::AppHandle<R>,
+    streams: tauri::State<'_, Arc<llm::StreamRegistry>>,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let streams = Arc::clone(&streams);
+    let db = Arc::clone(&db);
+    let secrets = env.secrets.clone();
+    let cancel = streams.register(&request_id)?;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = llm::metered_from_settings(&settings::load_shared(&db), db, agent_id, &secrets).and_then(|provider| {
+            let mut on_delta = |delta: &str| {
+                let _ = app.emit("llm-stream", LlmStreamDelta {
+                    request_id: request_id.clone(),
+                    delta: delta.to_string(),
+                });
+            };
+            provider.chat_stream(&request, &mut on_delta, &cancel)
+        });
+        streams.finish(&request_id);
+
+        let end = match result {
+            Ok(ref response) => LlmStreamEnd {
+                request_id: request_id.clone(),
+                response: Some(response.clone()),
+                error: None,
+                cancelled: false,
+            },
+            Err(ref e) => LlmStreamEnd {
+                request_id: request_id.clone(),
+                response: None,
+                error: Some(e.to_string()),
+                cancelled: matches!(e, llm::LlmError::Cancelled),
+            },
+        };
+        let _ = app.emit("llm-stream-end", end);
+
+        let response = result.map_err(|e| e.to_string())?;
+        serde_json::to_string(&response)
+            .map_err(|e| format!("Failed to serialize LLM response: {}", e))
+    })
+    .await
+    .map_err(|e| format!("LLM task failed: {}", e))?
+}
+
+/// LLM spend for the last `days` days (default 30): totals per day and per
+/// agent, plus this month's spend against the budget.
+#[tauri::command]
+pub fn get_llm_usage(days: Option<i64>, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let since = (chrono::Utc::now() - chrono::Duration::days(days.unwrap_or(30))).to_rfc3339();
+    let db = db.lock().unwrap();
+
+    let daily = db.get_llm_usage_by_day(&since).map_err(|e| format!("Failed to get LLM usage: {}", e))?;
+    let by_agent = db.get_llm_usage_by_agent(&since).map_err(|e| format!("Failed to get LLM usage: {}", e))?;
+    let month_cost_usd = db
+        .get_llm_cost_since(&llm::month_start())
+        .map_err(|e| format!("Failed to get LLM usage: {}", e))?;
+
+    Ok(serde_json::json!({
+        "daily": daily,
+        "by_agent": by_agent,
+        "month_cost_usd": month_cost_usd,
+        "monthly_budget_usd": settings::load(&db).llm_monthly_budget_usd,
+    })
+    .to_string())
+}
+
+/// Sets the monthly LLM budget in USD; 0 removes the cap.
+#[tauri::command]
+pub fn save_llm_budget<R: Runtime>(
+    monthly_budget_usd: f64,
+    app: tauri::AppHandle<R>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    update_and_notify(&app, &db, &env.secrets, serde_json::json!({"llm_monthly_budget_usd": monthly_budget_usd}))?;
+    Ok("LLM budget saved".to_string())
+}
+
+#[tauri::command]
+pub fn save_llm_prices<R: Runtime>(
+    prices: Vec<settings::ModelPrice>,
+    app: tauri::AppHandle<R>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    update_and_notify(&app, &db, &env.secrets, serde_json::json!({"llm_prices": prices}))?;
+    Ok("LLM prices saved".to_string())
+}
+
+#[tauri::command]
+pub fn llm_cancel(request_id: String, streams: tauri::State<Arc<llm::StreamRegistry>>) -> Result<String, String> {
+    if streams.cancel(&request_id) {
+        Ok(format!("Cancelling {}", request_id))
+    } else {
+        Err(format!("No streaming request with id {}", request_id))
+    }
+}
+
+/// Returns a DependencyReport: each dependency's version, whether it meets
+/// the minimum, and what to do about it if not.
+#[tauri::command]
+pub fn check_dependencies() -> Result<String, String> {
+    serde_json::to_string(&dependencies::check_all())
+        .map_err(|e| format!("Failed to serialize dependency checks: {}", e))
+}
+
+// Installer commands
+/// The steps start_install would run to fix what check_dependencies reports.
+#[tauri::command]
+pub async fn get_install_plan(db: tauri::State<'_, Arc<Mutex<Database>>>) -> Result<String, String> {
+    let settings = settings::load_shared(&db);
+    tauri::async_runtime::spawn_blocking(move || {
+        let steps = installer::plan(&dependencies::check_all(), &settings);
+        serde_json::to_string(&steps)
+            .map_err(|e| format!("Failed to serialize install plan: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Installer task failed: {}", e))?
+}
+
+/// Runs the install plan (or just the steps in `step_ids`) in the background.
+/// Progress arrives as `install-progress` events; returns the run id.
+#[tauri::command]
+pub async fn start_install<R: Runtime>(
+    step_ids: Option<Vec<String>>,
+    app: tauri::AppHandle<R>,
+    installer: tauri::State<'_, Arc<installer::Installer>>,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let settings = settings::load_shared(&db);
+    let mut steps = tauri::async_runtime::spawn_blocking(move || installer::plan(&dependencies::check_all(), &settings))
+        .await
+        .map_err(|e| format!("Installer task failed: {}", e))?;
+    if let Some(ids) = step_ids {
+        steps.retain(|step| ids.contains(&step.id));
+    }
+
+    let run_id = installer.start(steps, Arc::new(move |event: installer::InstallEvent| {
+        if let Err(e) = app.emit("install-progress", &event) {
+            eprintln!("Failed to emit install progress: {}", e);
+        }
+    }))?;
+
+    Ok(serde_json::json!({"run_id": run_id, "message": "Installation started"}).to_string())
+}
+
+#[tauri::command]
+pub fn cancel_install(installer: tauri::State<Arc<installer::Installer>>) -> Result<String, String> {
+    if installer.cancel() {
+        Ok("Cancelling installation".to_string())
+    } else {
+        Err("No installation is running".to_string())
+    }
+}
+
+#[tauri::command]
+pub fn get_install_log(limit: Option<i64>, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let entries = db_lock.get_install_log(limit.unwrap_or(50))
+        .map_err(|e| format!("Failed to get install log: {}", e))?;
+
+    serde_json::to_string(&entries)
+        .map_err(|e| format!("Failed to serialize install log: {}", e))
+}
+
+// Database commands
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // one argument per field sent by the frontend
+pub fn db_create_agent(
+    name: String,
+    description: Option<String>,
+    role: Option<String>,
+    goal: Option<String>,
+    tools: Option<String>,
+    schedule: String,
+    schedule_time: Option<String>,
+    command: String,
+    args: String,
+    timeout: i64,
+    config_json: String,
+    db: tauri::State<Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let agent = Agent {
+        id: None,
+        name,
+        description,
+        role,
+        goal,
+        tools,
+        schedule,
+        schedule_time,
+        command,
+        args,
+        timeout,
+        config_json,
+        created_at: String::new(),
+        updated_at: String::new(),
+        is_active: true,
+    };
+
+    let db_lock = db.lock().unwrap();
+    let id = db_lock.create_agent(&agent)
+        .map_err(|e| format!("Failed to create agent: {}", e))?;
+
+    Ok(serde_json::json!({"id": id, "message": "Agent created successfully"}).to_string())
+}
+
+#[tauri::command]
+pub fn db_get_all_agents(db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let agents = db_lock.get_all_agents()
+        .map_err(|e| format!("Failed to get agents: {}", e))?;
+
+    serde_json::to_string(&agents)
+        .map_err(|e| format!("Failed to serialize agents: {}", e))
+}
+
+#[tauri::command]
+pub fn db_get_agent_by_name(name: String, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let agent = db_lock.get_agent_by_name(&name)
+        .map_err(|e| format!("Failed to get agent: {}", e))?;
+
+    serde_json::to_string(&agent)
+        .map_err(|e| format!("Failed to serialize agent: {}", e))
+}
+
+/// Packs agents with their handlers, prompt templates and scripts into `path`.
+#[tauri::command]
+pub fn export_agents(
+    names: Vec<String>,
+    path: String,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let manifest = bundle::export(&db_lock, &names, &env.project_dir, Path::new(&path))?;
+
+    serde_json::to_string(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))
+}
+
+#[tauri::command]
+pub fn preview_agent_bundle(
+    path: String,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let preview = bundle::preview(&db_lock, Path::new(&path), &env.project_dir)?;
+
+    serde_json::to_string(&preview)
+        .map_err(|e| format!("Failed to serialize preview: {}", e))
+}
+
+/// `on_conflict` is "skip" (the default), "overwrite" or "rename".
+#[tauri::command]
+pub fn import_agent_bundle(
+    path: String,
+    on_conflict: Option<String>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    poller: tauri::State<Arc<EventPoller>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let policy = bundle::ConflictPolicy::parse(on_conflict.as_deref().unwrap_or("skip"))?;
+    let report = {
+        let db_lock = db.lock().unwrap();
+        bundle::import(&db_lock, Path::new(&path), &env.project_dir, policy)?
+    };
+    notify_handlers_changed(&poller, &env.daemon);
+
+    serde_json::to_string(&report)
+        .map_err(|e| format!("Failed to serialize import report: {}", e))
+}
+
+#[tauri::command]
+pub fn db_log_agent_event(
+    agent_id: i64,
+    agent_name: String,
+    event_type: String,
+    message: String,
+    details: Option<String>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let log = AgentLog {
+        id: None,
+        agent_id,
+        agent_name,
+        event_type,
+        message,
+        details,
+        timestamp: String::new(),
+    };
+
+    let db_lock = db.lock().unwrap();
+    let id = db_lock.log_agent_event(&log)
+        .map_err(|e| format!("Failed to log event: {}", e))?;
+
+    Ok(serde_json::json!({"id": id, "message": "Event logged"}).to_string())
+}
+
+#[tauri::command]
+pub fn db_get_agent_logs(
+    agent_id: Option<i64>,
+    limit: i64,
+    db: tauri::State<Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let logs = db_lock.get_agent_logs(agent_id, limit)
+        .map_err(|e| format!("Failed to get logs: {}", e))?;
+
+    serde_json::to_string(&logs)
+        .map_err(|e| format!("Failed to serialize logs: {}", e))
+}
+
+/// Backs up the whole database to a timestamped file in ~/.personaliz/backups.
+#[tauri::command]
+pub fn backup_database(db: tauri::State<Arc<Mutex<Database>>>, env: tauri::State<AppEnv>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let info = backup::create_backup(&db_lock, &env.backup_dir, backup::BackupKind::Manual)?;
+
+    serde_json::to_string(&info)
+        .map_err(|e| format!("Failed to serialize backup: {}", e))
+}
+
+/// Recent agent run notifications, including ones held back by rate limiting.
+#[tauri::command]
+pub fn get_notification_history(limit: Option<i64>, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let history = db_lock.get_notifications(limit.unwrap_or(100))
+        .map_err(|e| format!("Failed to get notifications: {}", e))?;
+
+    serde_json::to_string(&history)
+        .map_err(|e| format!("Failed to serialize notifications: {}", e))
+}
+
+#[tauri::command]
+pub fn clear_notification_history(db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let deleted = db_lock.clear_notifications()
+        .map_err(|e| format!("Failed to clear notifications: {}", e))?;
+    Ok(format!("Cleared {} notifications", deleted))
+}
+
+/// Recent email and webhook deliveries, newest first.
+#[tauri::command]
+pub fn get_notification_deliveries(limit: Option<i64>, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let deliveries = db_lock.get_deliveries(limit.unwrap_or(100))
+        .map_err(|e| format!("Failed to get deliveries: {}", e))?;
+
+    serde_json::to_string(&deliveries)
+        .map_err(|e| format!("Failed to serialize deliveries: {}", e))
+}
+
+/// Sends a sample failure through a notification channel. The delivery is
+/// returned (and logged) whether or not it went through.
+#[tauri::command]
+pub fn test_notification_channel(
+    name: String,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let delivery = channels::send_test(&db, &name, &env.secrets)?;
+    serde_json::to_string(&delivery)
+        .map_err(|e| format!("Failed to serialize delivery: {}", e))
+}
+
+/// Stores the SMTP password of a notification channel; empty removes it.
+#[tauri::command]
+pub fn save_channel_password(channel: String, password: String, env: tauri::State<AppEnv>) -> Result<String, String> {
+    let name = secrets::channel_password(&channel);
+    if password.is_empty() {
+        env.secrets.delete(&name)?;
+        return Ok(format!("Password for {} removed", channel));
+    }
+    env.secrets.set(&name, &password)?;
+    Ok(format!("Password for {} saved", channel))
+}
+
+/// Stores the value of one of a webhook channel's `secret_headers`; empty removes it.
+#[tauri::command]
+pub fn save_channel_header(
+    channel: String,
+    header: String,
+    value: String,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let name = secrets::channel_header(&channel, &header);
+    if value.is_empty() {
+        env.secrets.delete(&name)?;
+        return Ok(format!("{} header for {} removed", header, channel));
+    }
+    env.secrets.set(&name, &value)?;
+    Ok(format!("{} header for {} saved", header, channel))
+}
+
+#[tauri::command]
+pub fn list_backups(env: tauri::State<AppEnv>) -> Result<String, String> {
+    let backups = backup::list_backups(&env.backup_dir)?;
+    serde_json::to_string(&backups)
+        .map_err(|e| format!("Failed to serialize backups: {}", e))
+}
+
+/// Restores a backup made by backup_database (or any copy of personaliz.db)
+/// after checking its schema version; the current state is backed up first.
+#[tauri::command]
+pub async fn restore_database(
+    path: String,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+    poller: tauri::State<'_, Arc<EventPoller>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let db = Arc::clone(&db);
+    let poller = Arc::clone(&poller);
+    let env = env.inner().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = {
+            let mut db_lock = db.lock().unwrap();
+            backup::restore(&mut db_lock, Path::new(&path), &env.backup_dir)?
+        };
+        notify_handlers_changed(&poller, &env.daemon);
+
+        serde_json::to_string(&result)
+            .map_err(|e| format!("Failed to serialize restore result: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Restore task failed: {}", e))?
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // one argument per field sent by the frontend
+pub fn db_create_event_handler(
+    name: String,
+    event_type: String,
+    url: Option<String>,
+    interval_seconds: i64,
+    config_json: String,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    poller: tauri::State<Arc<EventPoller>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let handler = EventHandler {
+        id: None,
+        name,
+        event_type,
+        url,
+        interval_seconds,
+        last_check: None,
+        is_active: true,
+        config_json,
+        consecutive_failures: 0,
+        last_success: None,
+        last_error: None,
+    };
+
+    let db_lock = db.lock().unwrap();
+    let id = db_lock.create_event_handler(&handler)
+        .map_err(|e| format!("Failed to create event handler: {}", e))?;
+    drop(db_lock);
+    notify_handlers_changed(&poller, &env.daemon);
+
+    Ok(serde_json::json!({"id": id, "message": "Event handler created"}).to_string())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)] // one argument per field sent by the frontend
+pub fn db_update_event_handler(
+    id: i64,
+    name: String,
+    event_type: String,
+    url: Option<String>,
+    interval_seconds: i64,
+    is_active: bool,
+    config_json: String,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    poller: tauri::State<Arc<EventPoller>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let handler = EventHandler {
+        id: Some(id),
+        name,
+        event_type,
+        url,
+        interval_seconds,
+        last_check: None,
+        is_active,
+        config_json,
+        consecutive_failures: 0,
+        last_success: None,
+        last_error: None,
+    };
+
+    let db_lock = db.lock().unwrap();
+    db_lock.update_event_handler(id, &handler)
+        .map_err(|e| format!("Failed to update event handler: {}", e))?;
+    drop(db_lock);
+    notify_handlers_changed(&poller, &env.daemon);
+
+    Ok(serde_json::json!({"id": id, "message": "Event handler updated"}).to_string())
+}
+
+#[tauri::command]
+pub fn db_delete_event_handler(
+    id: i64,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    poller: tauri::State<Arc<EventPoller>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    db_lock.delete_event_handler(id)
+        .map_err(|e| format!("Failed to delete event handler: {}", e))?;
+    drop(db_lock);
+    notify_handlers_changed(&poller, &env.daemon);
+
+    Ok(serde_json::json!({"id": id, "message": "Event handler deleted"}).to_string())
+}
+
+#[tauri::command]
+pub fn db_get_all_event_handlers(db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let handlers = db_lock.get_all_event_handlers()
+        .map_err(|e| format!("Failed to get event handlers: {}", e))?;
+
+    serde_json::to_string(&handlers)
+        .map_err(|e| format!("Failed to serialize event handlers: {}", e))
+}
+
+#[tauri::command]
+pub fn db_create_prompt_template(
+    name: String,
+    description: Option<String>,
+    body: String,
+    model: Option<String>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let template = PromptTemplate {
+        id: None,
+        name,
+        description,
+        body,
+        model,
+        version: 1,
+        created_at: String::new(),
+        updated_at: String::new(),
+    };
+
+    let db_lock = db.lock().unwrap();
+    let id = db_lock.create_prompt_template(&template)
+        .map_err(|e| format!("Failed to create prompt template: {}", e))?;
+
+    Ok(serde_json::json!({"id": id, "message": "Prompt template created"}).to_string())
+}
+
+#[tauri::command]
+pub fn db_get_all_prompt_templates(db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let templates = db_lock.get_all_prompt_templates()
+        .map_err(|e| format!("Failed to get prompt templates: {}", e))?;
+
+    serde_json::to_string(&templates)
+        .map_err(|e| format!("Failed to serialize prompt templates: {}", e))
+}
+
+/// Saves a new version of a template; the previous one stays in its history.
+#[tauri::command]
+pub fn db_update_prompt_template(
+    id: i64,
+    description: Option<String>,
+    body: String,
+    model: Option<String>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let template = PromptTemplate {
+        id: Some(id),
+        name: String::new(),
+        description,
+        body,
+        model,
+        version: 0,
+        created_at: String::new(),
+        updated_at: String::new(),
+    };
+
+    let db_lock = db.lock().unwrap();
+    let version = db_lock.update_prompt_template(id, &template)
+        .map_err(|e| format!("Failed to update prompt template: {}", e))?;
+
+    Ok(serde_json::json!({"id": id, "version": version, "message": "Prompt template updated"}).to_string())
+}
+
+#[tauri::command]
+pub fn db_get_prompt_template_versions(id: i64, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let versions = db_lock.get_prompt_template_versions(id)
+        .map_err(|e| format!("Failed to get prompt template versions: {}", e))?;
+
+    serde_json::to_string(&versions)
+        .map_err(|e| format!("Failed to serialize prompt template versions: {}", e))
+}
+
+#[tauri::command]
+pub fn db_rollback_prompt_template(id: i64, version: i64, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let new_version = db_lock.rollback_prompt_template(id, version)
+        .map_err(|e| format!("Failed to roll back prompt template: {}", e))?;
+
+    Ok(serde_json::json!({
+        "id": id,
+        "version": new_version,
+        "message": format!("Restored version {}", version)
+    })
+    .to_string())
+}
+
+#[tauri::command]
+pub fn db_delete_prompt_template(id: i64, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    db_lock.delete_prompt_template(id)
+        .map_err(|e| format!("Failed to delete prompt template: {}", e))?;
+
+    Ok(serde_json::json!({"id": id, "message": "Prompt template deleted"}).to_string())
+}
+
+/// Remembers `content` for an agent, embedded with the local embedding model.
+/// Near-duplicates of an existing memory are not stored again.
+#[tauri::command]
+pub async fn memory_add(agent_id: i64, content: String, db: tauri::State<'_, Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db = Arc::clone(&db);
+    tauri::async_runtime::spawn_blocking(move || {
+        let embedder = llm::embedder_from_settings(&settings::load_shared(&db));
+        let embedding = memory::embed(&embedder, &content)?;
+
+        let db_lock = db.lock().unwrap();
+        let remembered = memory::remember(&db_lock, agent_id, &content, embedding, embedder.model())?;
+        serde_json::to_string(&remembered)
+            .map_err(|e| format!("Failed to serialize memory: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Memory task failed: {}", e))?
+}
+
+/// The agent's memories most similar to `query`, best first.
+#[tauri::command]
+pub async fn memory_query(
+    agent_id: i64,
+    query: String,
+    limit: Option<usize>,
+    min_score: Option<f32>,
+    db: tauri::State<'_, Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let db = Arc::clone(&db);
+    tauri::async_runtime::spawn_blocking(move || {
+        let embedder = llm::embedder_from_settings(&settings::load_shared(&db));
+        let embedding = memory::embed(&embedder, &query)?;
+
+        let db_lock = db.lock().unwrap();
+        let matches = memory::recall(
+            &db_lock,
+            agent_id,
+            &embedding,
+            embedder.model(),
+            limit.unwrap_or(5),
+            min_score.unwrap_or(0.0),
+        )?;
+        serde_json::to_string(&matches)
+            .map_err(|e| format!("Failed to serialize memories: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Memory task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn memory_list(agent_id: i64, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let memories = db_lock.get_agent_memories(agent_id)
+        .map_err(|e| format!("Failed to get agent memories: {}", e))?;
+
+    serde_json::to_string(&memories)
+        .map_err(|e| format!("Failed to serialize memories: {}", e))
+}
+
+#[tauri::command]
+pub fn memory_forget(id: i64, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    db_lock.delete_agent_memory(id)
+        .map_err(|e| format!("Failed to forget memory: {}", e))?;
+
+    Ok(serde_json::json!({"id": id, "message": "Memory forgotten"}).to_string())
+}
+
+/// Forgets everything an agent remembered.
+#[tauri::command]
+pub fn memory_clear(agent_id: i64, db: tauri::State<Arc<Mutex<Database>>>) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let removed = db_lock.clear_agent_memories(agent_id)
+        .map_err(|e| format!("Failed to clear memories: {}", e))?;
+
+    Ok(serde_json::json!({"agent_id": agent_id, "removed": removed, "message": "Memories cleared"}).to_string())
+}
+
+/// Renders a template with variables from an agent (`agent.*`), an event
+/// payload (`event.*`) and any extra top-level `variables`.
+#[tauri::command]
+pub fn render_prompt_template(
+    name: String,
+    agent_name: Option<String>,
+    event: Option<serde_json::Value>,
+    variables: Option<serde_json::Value>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+) -> Result<String, String> {
+    let db_lock = db.lock().unwrap();
+    let template = db_lock.get_prompt_template_by_name(&name)
+        .map_err(|e| format!("Failed to get prompt template: {}", e))?
+        .ok_or_else(|| format!("No prompt template named {}", name))?;
+
+    let agent = match agent_name {
+        Some(agent_name) => Some(
+            db_lock.get_agent_by_name(&agent_name)
+                .map_err(|e| format!("Failed to get agent: {}", e))?
+                .ok_or_else(|| format!("No agent named {}", agent_name))?,
+        ),
+        None => None,
+    };
+    drop(db_lock);
+
+    let context = prompt_template::build_context(agent.as_ref(), event.as_ref(), variables.as_ref());
+    let prompt = prompt_template::render(&template.body, &context)?;
+
+    Ok(serde_json::json!({
+        "prompt": prompt,
+        "model": template.model,
+        "version": template.version
+    })
+    .to_string())
+}
+
+// Handlers are polled by the daemon when one is running, so it has to hear
+// about changes too
+fn notify_handlers_changed(poller: &EventPoller, daemon: &DaemonClient) {
+    poller.notify_handlers_changed();
+    let _ = daemon.request("handlers_changed", serde_json::Value::Null);
+}
+
+#[tauri::command]
+pub fn start_event_poller(poller: tauri::State<Arc<EventPoller>>, env: tauri::State<AppEnv>) -> Result<String, String> {
+    let started = if env.daemon.is_running() {
+        env.daemon.request("start_poller", serde_json::Value::Null)?.as_bool() == Some(true)
+    } else {
+        poller.start()
+    };
+    if started {
+        Ok("Event poller started".to_string())
+    } else {
+        Ok("Event poller already running".to_string())
+    }
+}
+
+#[tauri::command]
+pub async fn stop_event_poller(
+    poller: tauri::State<'_, Arc<EventPoller>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let poller = Arc::clone(&poller);
+    let daemon = env.daemon;
+    // Stopping waits for checks in flight
+    tauri::async_runtime::spawn_blocking(move || {
+        let stopped = if daemon.is_running() {
+            daemon.request("stop_poller", serde_json::Value::Null)?.as_bool() == Some(true)
+        } else {
+            poller.stop()
+        };
+        if stopped {
+            Ok("Event poller stopped".to_string())
+        } else {
+            Ok("Event poller was not running".to_string())
+        }
+    })
+    .await
+    .map_err(|e| format!("Event poller task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn get_event_poller_status(poller: tauri::State<Arc<EventPoller>>, env: tauri::State<AppEnv>) -> Result<String, String> {
+    if let Ok(status) = env.daemon.request("poller_status", serde_json::Value::Null) {
+        return Ok(status.to_string());
+    }
+    serde_json::to_string(&poller.get_status())
+        .map_err(|e| format!("Failed to serialize poller status: {}", e))
+}
+
+#[tauri::command]
+pub fn get_daemon_status(env: tauri::State<AppEnv>) -> Result<String, String> {
+    match env.daemon.request("status", serde_json::Value::Null) {
+        Ok(status) => Ok(serde_json::json!({"running": true, "status": status}).to_string()),
+        Err(_) => Ok(serde_json::json!({"running": false}).to_string()),
+    }
+}
+
+/// Starts `personaliz daemon` in the background and hands polling over to it.
+#[tauri::command]
+pub async fn start_daemon<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    poller: tauri::State<'_, Arc<EventPoller>>,
+    env: tauri::State<'_, AppEnv>,
+) -> Result<String, String> {
+    let poller = Arc::clone(&poller);
+    let daemon = env.daemon;
+    // Waits for the local poller to stop and for the daemon to come up
+    tauri::async_runtime::spawn_blocking(move || {
+        if daemon.is_running() {
+            return Ok("Daemon already running".to_string());
+        }
+
+        // The CLI is installed next to the app binary
+        let exe = std::env::current_exe().map_err(|e| format!("Failed to find app binary: {}", e))?;
+        let cli = exe.with_file_name(format!("personaliz{}", std::env::consts::EXE_SUFFIX));
+        if !cli.exists() {
+            return Err(format!("Daemon binary not found: {}", cli.display()));
+        }
+
+        poller.stop();
+        Command::new(&cli)
+            .arg("daemon")
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Failed to start daemon: {}", e))?;
+
+        for _ in 0..30 {
+            if daemon.is_running() {
+                forward_daemon_events(app, daemon);
+                return Ok("Daemon started".to_string());
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        Err("Daemon did not come up; run `personaliz daemon` in a terminal to see why".to_string())
+    })
+    .await
+    .map_err(|e| format!("Daemon task failed: {}", e))?
+}
+
+#[tauri::command]
+pub fn stop_daemon(
+    poller: tauri::State<Arc<EventPoller>>,
+    db: tauri::State<Arc<Mutex<Database>>>,
+    env: tauri::State<AppEnv>,
+) -> Result<String, String> {
+    env.daemon.request("shutdown", serde_json::Value::Null)?;
+    // Polling falls back to the app, as if the daemon had never run
+    if settings::load_shared(&db).poller_auto_start {
+        poller.start();
+    }
+    Ok("Daemon stopped".to_string())
+}
+
+// Relays daemon events to the frontend under the same names the in-app
+// poller uses, until the daemon goes away
+pub(crate) fn forward_daemon_events<R: Runtime>(app: tauri::AppHandle<R>, daemon: DaemonClient) {
+    std::thread::spawn(move || {
+        let result = daemon.subscribe(|event, payload| {
+            if let Err(e) = app.emit(event, &payload) {
+                eprintln!("[Daemon] Failed to emit {}: {}", event, e);
+            }
+        });
+        if let Err(e) = result {
+            eprintln!("[Daemon] Lost connection to daemon: {}", e);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn run_browser_script(script_name: String, args: Vec<String>) -> Result<String, String> {
+    // Get project root (parent of src-tauri)
+    let project_dir = std::env::current_dir()
+        .map_err(|e| format!("Failed to get current directory: {}", e))?
+        .parent()
+        .ok_or("Failed to get project root")?
+        .to_path_buf();
+    
+    let script_path = project_dir.join(&script_name);
+    
+    if !script_path.exists() {
+        return Err(format!("Script not found: {}", script_name));
+    }
+
+    // Build command arguments
+    let mut cmd_args = vec![script_path.to_str().unwrap().to_string()];
+    cmd_args.extend(args);
+
+    // Run in detached mode so it doesn't block
+    let _ = Command::new("node")
+        .args(&cmd_args)
+        .spawn()
+        .map_err(|e| format!("Failed to start script: {}", e))?;
+
+    Ok(format!("Started {} in background", script_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use tauri::ipc::{CallbackFn, InvokeBody};
+    use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime, INVOKE_KEY};
+    use tauri::webview::InvokeRequest;
+    use tauri::{Manager, WebviewWindow, WebviewWindowBuilder};
+
+    use crate::database::{AgentMemory, NotificationRecord};
+    use crate::test_support::{MockServer, TempDir};
+
+    // The full app from crate::configure on the mock runtime, backed by an
+    // in-memory database, with no daemon and its directories and secrets in
+    // a temp dir
+    struct TestApp {
+        app: tauri::App<MockRuntime>,
+        webview: WebviewWindow<MockRuntime>,
+        dir: TempDir,
+    }
+
+    impl TestApp {
+        fn new() -> Self {
+            let dir = TempDir::new("commands");
+            let env = AppEnv {
+                daemon: DaemonClient::disabled(),
+                project_dir: dir.path().join("project"),
+                backup_dir: dir.path().join("backups"),
+                secrets: SecretStore::file_store(dir.path().join("secrets"), Some("test passphrase".to_string())),
+            };
+            fs::create_dir_all(&env.project_dir).unwrap();
+
+            let app = crate::configure(mock_builder(), Database::in_memory().unwrap(), env)
+                .build(mock_context(noop_assets()))
+                .unwrap();
+            let webview = WebviewWindowBuilder::new(&app, "main", Default::default()).build().unwrap();
+            TestApp { app, webview, dir }
+        }
+
+        /// Calls a command the way the frontend does. Arguments use the
+        /// frontend's camelCase names; JSON string results are parsed.
+        fn invoke(&self, cmd: &str, args: Value) -> Result<Value, String> {
+            let request = InvokeRequest {
+                cmd: cmd.into(),
+                callback: CallbackFn(0),
+                error: CallbackFn(1),
+                url: "http://tauri.localhost".parse().unwrap(),
+                body: InvokeBody::Json(args),
+                headers: Default::default(),
+                invoke_key: INVOKE_KEY.to_string(),
+            };
+
+            match tauri::test::get_ipc_response(&self.webview, request) {
+                Ok(body) => match body.deserialize::<Value>().unwrap() {
+                    Value::String(text) => Ok(serde_json::from_str(&text).unwrap_or(Value::String(text))),
+                    other => Ok(other),
+                },
+                Err(error) => Err(error.as_str().map(String::from).unwrap_or_else(|| error.to_string())),
+            }
+        }
+
+        fn db(&self) -> tauri::State<'_, Arc<Mutex<Database>>> {
+            self.app.state::<Arc<Mutex<Database>>>()
+        }
+
+        fn secret(&self, name: &str) -> Option<String> {
+            self.app.state::<AppEnv>().secrets.get(name).unwrap()
+        }
+    }
+
+    fn create_agent(app: &TestApp, name: &str) -> i64 {
+        let created = app
+            .invoke(
+                "db_create_agent",
+                json!({
+                    "name": name,
+                    "description": "Posts about AI",
+                    "role": "Writer",
+                    "goal": null,
+                    "tools": null,
+                    "schedule": "daily",
+                    "scheduleTime": "09:00",
+                    "command": "node",
+                    "args": r#"["linkedin_bot.js"]"#,
+                    "timeout": 300000,
+                    "configJson": "{}",
+                }),
+            )
+            .unwrap();
+        created["id"].as_i64().unwrap()
+    }
+
+    #[test]
+    fn test_agent_commands() {
+        let app = TestApp::new();
+        let id = create_agent(&app, "writer");
+        assert!(app.invoke("db_create_agent", json!({"name": "incomplete"})).is_err());
+
+        let agents = app.invoke("db_get_all_agents", json!({})).unwrap();
+        assert_eq!(agents.as_array().unwrap().len(), 1);
+        let agent = app.invoke("db_get_agent_by_name", json!({"name": "writer"})).unwrap();
+        assert_eq!(agent["id"], id);
+        assert_eq!(agent["schedule_time"], "09:00");
+        assert_eq!(app.invoke("db_get_agent_by_name", json!({"name": "nobody"})).unwrap(), Value::Null);
+
+        app.invoke(
+            "db_log_agent_event",
+            json!({"agentId": id, "agentName": "writer", "eventType": "executed", "message": "Ran", "details": null}),
+        )
+        .unwrap();
+        let logs = app.invoke("db_get_agent_logs", json!({"agentId": id, "limit": 10})).unwrap();
+        assert_eq!(logs[0]["event_type"], "executed");
+        let logs = app.invoke("db_get_agent_logs", json!({"agentId": null, "limit": 10})).unwrap();
+        assert_eq!(logs.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_event_handler_commands() {
+        let app = TestApp::new();
+        let created = app
+            .invoke(
+                "db_create_event_handler",
+                json!({
+                    "name": "openclaw feed",
+                    "eventType": "rss",
+                    "url": "https://example.com/feed.xml",
+                    "intervalSeconds": 600,
+                    "configJson": "{}",
+                }),
+            )
+            .unwrap();
+        let id = created["id"].as_i64().unwrap();
+
+        app.invoke(
+            "db_update_event_handler",
+            json!({
+                "id": id,
+                "name": "openclaw feed",
+                "eventType": "rss",
+                "url": "https://example.com/atom.xml",
+                "intervalSeconds": 900,
+                "isActive": false,
+                "configJson": "{}",
+            }),
+        )
+        .unwrap();
+        let handlers = app.invoke("db_get_all_event_handlers", json!({})).unwrap();
+        assert_eq!(handlers[0]["url"], "https://example.com/atom.xml");
+        assert_eq!(handlers[0]["interval_seconds"], 900);
+        assert_eq!(handlers[0]["is_active"], false);
+
+        app.invoke("db_delete_event_handler", json!({"id": id})).unwrap();
+        assert_eq!(app.invoke("db_get_all_event_handlers", json!({})).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn test_prompt_template_commands() {
+        let app = TestApp::new();
+        create_agent(&app, "writer");
+        let created = app
+            .invoke(
+                "db_create_prompt_template",
+                json!({"name": "post", "description": null, "body": "{{agent.name}}: {{topic}}", "model": null}),
+            )
+            .unwrap();
+        let id = created["id"].as_i64().unwrap();
+
+        let updated = app
+            .invoke(
+                "db_update_prompt_template",
+                json!({"id": id, "description": null, "body": "{{agent.name}} on {{topic}}", "model": "phi3"}),
+            )
+            .unwrap();
+        assert_eq!(updated["version"], 2);
+        let versions = app.invoke("db_get_prompt_template_versions", json!({"id": id})).unwrap();
+        assert_eq!(versions.as_array().unwrap().len(), 2);
+
+        let rendered = app
+            .invoke(
+                "render_prompt_template",
+                json!({"name": "post", "agentName": "writer", "event": null, "variables": {"topic": "AI"}}),
+            )
+            .unwrap();
+        assert_eq!(rendered["prompt"], "writer on AI");
+        assert_eq!(rendered["model"], "phi3");
+        let missing = app.invoke("render_prompt_template", json!({"name": "post", "variables": {}}));
+        assert!(missing.is_err());
+
+        let rolled_back = app.invoke("db_rollback_prompt_template", json!({"id": id, "version": 1})).unwrap();
+        assert_eq!(rolled_back["version"], 3);
+        let templates = app.invoke("db_get_all_prompt_templates", json!({})).unwrap();
+        assert_eq!(templates[0]["body"], "{{agent.name}}: {{topic}}");
+
+        app.invoke("db_delete_prompt_template", json!({"id": id})).unwrap();
+        assert_eq!(app.invoke("db_get_all_prompt_templates", json!({})).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn test_settings_commands() {
+        let app = TestApp::new();
+        let settings = app.invoke("load_settings", json!({})).unwrap();
+        assert_eq!(settings["llm_model"], "phi3");
+
+        let updated = app.invoke("update_settings", json!({"patch": {"llm_model": "llama3", "poller_max_concurrency": 2}})).unwrap();
+        assert_eq!(updated["changed"].as_array().unwrap().len(), 2);
+        assert_eq!(app.invoke("load_settings", json!({})).unwrap()["llm_model"], "llama3");
+
+        // Invalid patches change nothing
+        assert!(app.invoke("update_settings", json!({"patch": {"llm_model": "x", "llm_provider": "nope"}})).is_err());
+        assert_eq!(app.invoke("load_settings", json!({})).unwrap()["llm_model"], "llama3");
+
+        app.invoke("set_setting", json!({"key": "poller_auto_start", "value": true})).unwrap();
+        let entries = app.invoke("get_setting_entries", json!({})).unwrap();
+        let entry = entries.as_array().unwrap().iter().find(|e| e["key"] == "poller_auto_start").unwrap();
+        assert_eq!(entry["value"], true);
+        assert!(entry["updated_at"].is_string());
+    }
+
+    #[test]
+    fn test_api_key_commands() {
+        let app = TestApp::new();
+        assert_eq!(app.invoke("has_api_key", json!({})).unwrap(), false);
+
+        let form = |provider: &str, key: &str| {
+            json!({"llmProvider": provider, "llmApiKey": key, "llmModel": "gpt-4o", "llmEndpoint": ""})
+        };
+        app.invoke("save_settings", form("openai", "sk-new")).unwrap();
+        assert_eq!(app.secret(secrets::LLM_API_KEY).as_deref(), Some("sk-new"));
+        assert_eq!(app.invoke("has_api_key", json!({})).unwrap(), true);
+
+        // A rejected form leaves the saved key alone, as does an empty one
+        assert!(app.invoke("save_settings", form("nope", "sk-other")).is_err());
+        app.invoke("save_settings", form("openai", "")).unwrap();
+        assert_eq!(app.secret(secrets::LLM_API_KEY).as_deref(), Some("sk-new"));
+
+        app.invoke("save_provider_api_key", json!({"provider": "claude", "apiKey": "sk-ant"})).unwrap();
+        assert_eq!(app.secret(&secrets::provider_api_key("claude")).as_deref(), Some("sk-ant"));
+        app.invoke("save_provider_api_key", json!({"provider": "claude", "apiKey": ""})).unwrap();
+        assert_eq!(app.secret(&secrets::provider_api_key("claude")), None);
+
+        app.invoke("clear_api_key", json!({})).unwrap();
+        assert_eq!(app.invoke("has_api_key", json!({})).unwrap(), false);
+    }
+
+    #[test]
+    fn test_memory_commands() {
+        let app = TestApp::new();
+        let id = create_agent(&app, "writer");
+        for content in ["likes short posts", "posts at 9am"] {
+            app.db()
+                .lock()
+                .unwrap()
+                .add_agent_memory(&AgentMemory {
+                    id: None,
+                    agent_id: id,
+                    content: content.to_string(),
+                    embedding: vec![1.0, 0.0],
+                    model: "nomic-embed-text".to_string(),
+                    created_at: String::new(),
+                })
+                .unwrap();
+        }
+
+        let memories = app.invoke("memory_list", json!({"agentId": id})).unwrap();
+        assert_eq!(memories.as_array().unwrap().len(), 2);
+        assert!(memories[0].get("embedding").is_none(), "embeddings stay in the backend");
+
+        app.invoke("memory_forget", json!({"id": memories[0]["id"]})).unwrap();
+        let cleared = app.invoke("memory_clear", json!({"agentId": id})).unwrap();
+        assert_eq!(cleared["removed"], 1);
+        assert_eq!(app.invoke("memory_list", json!({"agentId": id})).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn test_usage_and_install_log_commands() {
+        let app = TestApp::new();
+        let usage = app.invoke("get_llm_usage", json!({"days": 7})).unwrap();
+        assert_eq!(usage["daily"], json!([]));
+        assert_eq!(usage["month_cost_usd"], 0.0);
+
+        app.invoke("save_llm_budget", json!({"monthlyBudgetUsd": 5.0})).unwrap();
+        assert_eq!(app.invoke("get_llm_usage", json!({})).unwrap()["monthly_budget_usd"], 5.0);
+
+        assert_eq!(app.invoke("get_install_log", json!({"limit": 5})).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn test_bundle_commands() {
+        let app = TestApp::new();
+        create_agent(&app, "writer");
+        fs::write(app.dir.path().join("project/linkedin_bot.js"), "console.log('post')").unwrap();
+        let archive = app.dir.path().join("writer.zip").display().to_string();
+
+        let manifest = app.invoke("export_agents", json!({"names": ["writer"], "path": archive})).unwrap();
+        assert_eq!(manifest["files"].as_array().unwrap().len(), 2);
+        assert!(app.invoke("export_agents", json!({"names": ["nobody"], "path": archive})).is_err());
+
+        let preview = app.invoke("preview_agent_bundle", json!({"path": archive})).unwrap();
+        assert_eq!(preview["agents"][0]["conflict"], true);
+        assert_eq!(preview["scripts"][0]["conflict"], false, "identical script");
+
+        assert!(app.invoke("import_agent_bundle", json!({"path": archive, "onConflict": "merge"})).is_err());
+        let report = app.invoke("import_agent_bundle", json!({"path": archive, "onConflict": "rename"})).unwrap();
+        let agent = report.as_array().unwrap().iter().find(|item| item["kind"] == "agent").unwrap();
+        assert_eq!(agent["name"], "writer (2)");
+        assert_eq!(app.invoke("db_get_all_agents", json!({})).unwrap().as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_backup_commands() {
+        let app = TestApp::new();
+        create_agent(&app, "writer");
+        let backup = app.invoke("backup_database", json!({})).unwrap();
+        assert_eq!(backup["kind"], "manual");
+        assert!(backup["path"].as_str().unwrap().starts_with(app.dir.path().to_str().unwrap()));
+
+        create_agent(&app, "monitor");
+        let restored = app.invoke("restore_database", json!({"path": backup["path"]})).unwrap();
+        assert_eq!(restored["restored_from"], backup["path"]);
+        let agents = app.invoke("db_get_all_agents", json!({})).unwrap();
+        assert_eq!(agents.as_array().unwrap().len(), 1);
+
+        let backups = app.invoke("list_backups", json!({})).unwrap();
+        let kinds: Vec<&str> = backups.as_array().unwrap().iter().map(|b| b["kind"].as_str().unwrap()).collect();
+        assert_eq!(kinds.len(), 2);
+        assert!(kinds.contains(&"manual") && kinds.contains(&"pre-restore"));
+        assert!(app.invoke("restore_database", json!({"path": app.dir.path().join("missing.db")})).is_err());
+    }
+
+    #[test]
+    fn test_notification_commands() {
+        let app = TestApp::new();
+        let id = create_agent(&app, "writer");
+        app.db()
+            .lock()
+            .unwrap()
+            .add_notification(&NotificationRecord {
+                id: None,
+                agent_id: id,
+                agent_name: "writer".to_string(),
+                outcome: "failure".to_string(),
+                title: "writer failed".to_string(),
+                body: "Exited with code 1".to_string(),
+                status: "shown".to_string(),
+                grouped_count: 1,
+                created_at: String::new(),
+            })
+            .unwrap();
+
+        let history = app.invoke("get_notification_history", json!({"limit": 10})).unwrap();
+        assert_eq!(history[0]["title"], "writer failed");
+        assert_eq!(app.invoke("clear_notification_history", json!({})).unwrap(), "Cleared 1 notifications");
+        assert_eq!(app.invoke("get_notification_history", json!({})).unwrap(), json!([]));
+    }
+
+    #[test]
+    fn test_notification_channel_commands() {
+        let app = TestApp::new();
+        let server = MockServer::start(vec![(200, "text/plain", "ok".to_string())]);
+        app.invoke(
+            "update_settings",
+            json!({"patch": {"notification_channels": [
+                {"name": "chat", "kind": "webhook", "url": server.url, "secret_headers": ["X-Token"]},
+            ]}}),
+        )
+        .unwrap();
+        assert!(app.invoke("test_notification_channel", json!({"name": "nowhere"})).is_err());
+
+        app.invoke("save_channel_header", json!({"channel": "chat", "header": "X-Token", "value": "abc"})).unwrap();
+        let delivery = app.invoke("test_notification_channel", json!({"name": "chat"})).unwrap();
+        assert_eq!(delivery["status"], "sent", "{}", delivery);
+        let request = server.request();
+        assert_eq!(request.header("x-token"), Some("abc"));
+        assert!(request.json()["text"].is_string());
+        app.invoke("save_channel_header", json!({"channel": "chat", "header": "X-Token", "value": ""})).unwrap();
+        assert_eq!(app.secret(&secrets::channel_header("chat", "X-Token")), None);
+
+        app.invoke("save_channel_password", json!({"channel": "ops-email", "password": "hunter2"})).unwrap();
+        assert_eq!(app.secret(&secrets::channel_password("ops-email")).as_deref(), Some("hunter2"));
+        app.invoke("save_channel_password", json!({"channel": "ops-email", "password": ""})).unwrap();
+        assert_eq!(app.secret(&secrets::channel_password("ops-email")), None);
+
+        let deliveries = app.invoke("get_notification_deliveries", json!({"limit": 10})).unwrap();
+        assert_eq!(deliveries[0]["channel"], "chat");
+    }
+
+    #[test]
+    fn test_event_poller_commands() {
+        let app = TestApp::new();
+        assert_eq!(app.invoke("start_event_poller", json!({})).unwrap(), "Event poller started");
+        assert_eq!(app.invoke("get_event_poller_status", json!({})).unwrap()["running"], true);
+        assert_eq!(app.invoke("stop_event_poller", json!({})).unwrap(), "Event poller stopped");
+        assert_eq!(app.invoke("stop_event_poller", json!({})).unwrap(), "Event poller was not running");
+    }
+}
@@ -0,0 +1,341 @@
+//! Headless daemon that owns the event poller and agent scheduler, so
+//! scheduled agents keep running after the window closes. The desktop app and
+//! the CLI talk to it over a local socket (a Unix socket in the data dir, or a
+//! named pipe on Windows) using one JSON object per line:
+//!
+//! request  `{"method": "run_agent", "params": {"name": "..."}}`
+//! response `{"ok": true, "result": ...}` or `{"ok": false, "error": "..."}`
+//!
+//! After a `subscribe` request the connection instead receives
+//! `{"event": "handler-event" | "agent-run", "payload": ...}` lines.
+
+use interprocess::local_socket::prelude::*;
+use interprocess::local_socket::{ListenerOptions, Name, Stream};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::agent_runner;
+use crate::backup;
+use crate::database::Database;
+use crate::event_poller::{EventPoller, HandlerEvent};
+use crate::scheduler::AgentScheduler;
+use crate::secrets::SecretStore;
+use crate::settings;
+
+// Requests are answered right away (runs happen in the background), so a
+// daemon that takes longer than this is treated as hung
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Deserialize)]
+struct Request {
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+struct Daemon {
+    poller: EventPoller,
+    scheduler: AgentScheduler,
+    started_at: String,
+    subscribers: Mutex<Vec<Stream>>,
+    shutting_down: AtomicBool,
+}
+
+/// Socket the daemon listens on: `~/.personaliz/daemon.sock`, or the
+/// `\\.\pipe\personaliz-daemon-<user>` named pipe on Windows.
+pub fn socket_name() -> io::Result<Name<'static>> {
+    #[cfg(windows)]
+    {
+        use interprocess::local_socket::GenericNamespaced;
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        format!("personaliz-daemon-{}", user).to_ns_name::<GenericNamespaced>()
+    }
+    #[cfg(not(windows))]
+    {
+        use interprocess::local_socket::GenericFilePath;
+        settings::data_dir().join("daemon.sock").to_fs_name::<GenericFilePath>()
+    }
+}
+
+/// Runs the daemon on the calling thread until a `shutdown` request.
+/// Fails if another daemon is already listening.
+pub fn run(db: Arc<Mutex<Database>>) -> Result<(), String> {
+    if is_running() {
+        return Err("A daemon is already running".to_string());
+    }
+
+    let name = socket_name().map_err(|e| format!("Invalid socket name: {}", e))?;
+    // try_overwrite replaces a socket file left behind by a daemon that crashed
+    let listener = ListenerOptions::new()
+        .name(name)
+        .try_overwrite(true)
+        .create_sync()
+        .map_err(|e| format!("Failed to listen on daemon socket: {}", e))?;
+    restrict_socket_permissions();
+
+    let daemon = Arc::new(Daemon {
+        poller: EventPoller::new(Arc::clone(&db)),
+        scheduler: AgentScheduler::new(Arc::clone(&db), settings::project_dir()),
+        started_at: chrono::Utc::now().to_rfc3339(),
+        subscribers: Mutex::new(Vec::new()),
+        shutting_down: AtomicBool::new(false),
+    });
+
+    let for_events = Arc::clone(&daemon);
+    daemon.poller.set_event_sink(Arc::new(move |event: HandlerEvent| {
+        println!("[Daemon] {} from {}", event.event_type, event.handler_name);
+        for_events.broadcast("handler-event", json!(event));
+    }));
+    let for_runs = Arc::clone(&daemon);
+    let db_for_runs = Arc::clone(&db);
+    let secrets = SecretStore::open_default();
+    daemon.scheduler.set_run_sink(Arc::new(move |run| {
+        println!("[Daemon] {} finished, success: {}", run.agent_name, run.success);
+        agent_runner::run_finished(&db_for_runs, &run, &secrets);
+        for_runs.broadcast("agent-run", json!(run));
+    }));
+
+    daemon.poller.start();
+    daemon.scheduler.start();
+    backup::start_auto_backups(Arc::clone(&db));
+    println!("[Daemon] Listening (pid {})", std::process::id());
+
+    for conn in listener.incoming() {
+        if daemon.shutting_down.load(Ordering::SeqCst) {
+            break;
+        }
+        match conn {
+            Ok(stream) => {
+                let daemon = Arc::clone(&daemon);
+                thread::spawn(move || daemon.handle_connection(stream));
+            }
+            Err(e) => eprintln!("[Daemon] Failed to accept connection: {}", e),
+        }
+    }
+
+    daemon.scheduler.stop();
+    daemon.poller.stop();
+    println!("[Daemon] Stopped");
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict_socket_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+    let path = settings::data_dir().join("daemon.sock");
+    if let Err(e) = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)) {
+        eprintln!("[Daemon] Failed to restrict socket permissions: {}", e);
+    }
+}
+
+#[cfg(not(unix))]
+fn restrict_socket_permissions() {}
+
+impl Daemon {
+    fn handle_connection(&self, stream: Stream) {
+        let mut reader = BufReader::new(&stream);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+
+            let request: Request = match serde_json::from_str(&line) {
+                Ok(request) => request,
+                Err(e) => {
+                    let _ = write_line(&stream, &json!({"ok": false, "error": format!("Invalid request: {}", e)}));
+                    continue;
+                }
+            };
+
+            if request.method == "subscribe" {
+                if write_line(&stream, &json!({"ok": true, "result": null})).is_ok() {
+                    drop(reader);
+                    // A subscriber that stops reading must not stall the broadcasts
+                    let _ = stream.set_send_timeout(Some(REQUEST_TIMEOUT));
+                    self.subscribers.lock().unwrap().push(stream);
+                }
+                return;
+            }
+
+            let response = match self.dispatch(&request.method, &request.params) {
+                Ok(result) => json!({"ok": true, "result": result}),
+                Err(error) => json!({"ok": false, "error": error}),
+            };
+            if write_line(&stream, &response).is_err() {
+                return;
+            }
+
+            if request.method == "shutdown" {
+                self.shutting_down.store(true, Ordering::SeqCst);
+                // Wake the accept loop so it sees the flag
+                let _ = socket_name().and_then(Stream::connect);
+                return;
+            }
+        }
+    }
+
+    fn dispatch(&self, method: &str, params: &Value) -> Result<Value, String> {
+        match method {
+            "ping" | "status" => Ok(json!({
+                "pid": std::process::id(),
+                "started_at": self.started_at,
+                "poller": self.poller.get_status(),
+                "scheduler": self.scheduler.get_status(),
+            })),
+            "start_poller" => Ok(json!(self.poller.start())),
+            "stop_poller" => Ok(json!(self.poller.stop())),
+            "poller_status" => Ok(json!(self.poller.get_status())),
+            "handlers_changed" => {
+                self.poller.notify_handlers_changed();
+                Ok(Value::Null)
+            }
+            "run_agent" => {
+                let name = params["name"].as_str().ok_or("run_agent needs a name")?;
+                self.scheduler.run_now(name)?;
+                Ok(Value::Null)
+            }
+            "shutdown" => Ok(Value::Null),
+            _ => Err(format!("Unknown method: {}", method)),
+        }
+    }
+
+    // Sends an event to every subscriber, dropping the ones that went away
+    fn broadcast(&self, event: &str, payload: Value) {
+        let message = json!({"event": event, "payload": payload});
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|stream| write_line(stream, &message).is_ok());
+    }
+}
+
+fn write_line(mut stream: &Stream, value: &Value) -> io::Result<()> {
+    let mut line = value.to_string();
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    stream.flush()
+}
+
+fn connect() -> Result<Stream, String> {
+    let name = socket_name().map_err(|e| format!("Invalid socket name: {}", e))?;
+    let stream = Stream::connect(name).map_err(|_| "Daemon is not running".to_string())?;
+    let _ = stream.set_recv_timeout(Some(REQUEST_TIMEOUT));
+    let _ = stream.set_send_timeout(Some(REQUEST_TIMEOUT));
+    Ok(stream)
+}
+
+fn read_response(reader: &mut impl BufRead) -> Result<Value, String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => return Err("Daemon closed the connection".to_string()),
+        Ok(_) => {}
+        Err(e) => return Err(format!("Failed to read from daemon: {}", e)),
+    }
+
+    let response: Value = serde_json::from_str(&line).map_err(|e| format!("Invalid daemon response: {}", e))?;
+    if response["ok"].as_bool() == Some(true) {
+        Ok(response["result"].clone())
+    } else {
+        Err(response["error"].as_str().unwrap_or("Unknown daemon error").to_string())
+    }
+}
+
+/// Sends one request to the running daemon and returns its result.
+pub fn request(method: &str, params: Value) -> Result<Value, String> {
+    let stream = connect()?;
+    write_line(&stream, &json!({"method": method, "params": params}))
+        .map_err(|e| format!("Failed to send to daemon: {}", e))?;
+    read_response(&mut BufReader::new(&stream))
+}
+
+pub fn is_running() -> bool {
+    request("ping", Value::Null).is_ok()
+}
+
+/// The desktop app's way to the daemon, held as app state. `disabled()`
+/// behaves as if no daemon were running, so tests never reach a developer's
+/// real one.
+#[derive(Debug, Clone, Copy)]
+pub struct DaemonClient {
+    enabled: bool,
+}
+
+impl DaemonClient {
+    pub fn socket() -> Self {
+        DaemonClient { enabled: true }
+    }
+
+    pub fn disabled() -> Self {
+        DaemonClient { enabled: false }
+    }
+
+    pub fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        if !self.enabled {
+            return Err("Daemon is not running".to_string());
+        }
+        request(method, params)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.enabled && is_running()
+    }
+
+    pub fn subscribe(&self, on_event: impl Fn(&str, Value)) -> Result<(), String> {
+        if !self.enabled {
+            return Err("Daemon is not running".to_string());
+        }
+        subscribe(on_event)
+    }
+}
+
+/// Calls `on_event(event, payload)` for each event the daemon broadcasts.
+/// Blocks until the daemon goes away.
+pub fn subscribe(on_event: impl Fn(&str, Value)) -> Result<(), String> {
+    let stream = connect()?;
+    write_line(&stream, &json!({"method": "subscribe"})).map_err(|e| format!("Failed to send to daemon: {}", e))?;
+
+    let mut reader = BufReader::new(&stream);
+    read_response(&mut reader)?;
+    // Events arrive whenever something happens, so no read timeout from here on
+    let _ = stream.set_recv_timeout(None);
+
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(e) => return Err(format!("Failed to read from daemon: {}", e)),
+        }
+        if let Ok(message) = serde_json::from_str::<Value>(&line) {
+            if let Some(event) = message["event"].as_str() {
+                on_event(event, message["payload"].clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_response() {
+        let mut ok = BufReader::new(&b"{\"ok\":true,\"result\":{\"pid\":1}}\n"[..]);
+        assert_eq!(read_response(&mut ok).unwrap(), json!({"pid": 1}));
+
+        let mut failed = BufReader::new(&b"{\"ok\":false,\"error\":\"No agent named x\"}\n"[..]);
+        assert_eq!(read_response(&mut failed).unwrap_err(), "No agent named x");
+
+        let mut closed = BufReader::new(&b""[..]);
+        assert!(read_response(&mut closed).is_err());
+    }
+}
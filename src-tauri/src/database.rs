@@ -1,6 +1,6 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, DatabaseName, OpenFlags, Result};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Agent {
@@ -21,6 +21,35 @@ pub struct Agent {
     pub is_active: bool,
 }
 
+impl Agent {
+    /// Builds an agent row from an agent config file (see AGENT_CONFIG_GUIDE.md).
+    /// `description` may sit at the top level or under `metadata`.
+    pub fn from_config(name: &str, config_json: &str) -> std::result::Result<Agent, String> {
+        let config: serde_json::Value = serde_json::from_str(config_json)
+            .map_err(|e| format!("Agent config is not valid JSON: {}", e))?;
+        let metadata = config.get("metadata");
+        let text = |value: Option<&serde_json::Value>| value.and_then(|v| v.as_str()).map(String::from);
+
+        Ok(Agent {
+            id: None,
+            name: name.to_string(),
+            description: text(config.get("description")).or_else(|| text(metadata.and_then(|m| m.get("description")))),
+            role: text(metadata.and_then(|m| m.get("role"))),
+            goal: text(metadata.and_then(|m| m.get("goal"))),
+            tools: config.get("tools").map(|t| t.to_string()),
+            schedule: text(config.get("schedule")).unwrap_or_else(|| "daily".to_string()),
+            schedule_time: text(config.get("schedule_time")),
+            command: text(config.get("command")).unwrap_or_else(|| "node".to_string()),
+            args: config.get("args").map(|a| a.to_string()).unwrap_or_else(|| "[]".to_string()),
+            timeout: config.get("timeout").and_then(|t| t.as_i64()).unwrap_or(300000),
+            config_json: config_json.to_string(),
+            created_at: String::new(),
+            updated_at: String::new(),
+            is_active: config.get("enabled").and_then(|e| e.as_bool()).unwrap_or(true),
+        })
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AgentLog {
     pub id: Option<i64>,
@@ -32,45 +61,355 @@ pub struct AgentLog {
     pub timestamp: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EventHandler {
     pub id: Option<i64>,
     pub name: String,
-    pub event_type: String, // "polling", "web", "periodic"
+    pub event_type: String, // "polling", "web", "rss", "periodic"
     pub url: Option<String>,
     pub interval_seconds: i64,
     pub last_check: Option<String>,
     pub is_active: bool,
     pub config_json: String,
+    #[serde(default)]
+    pub consecutive_failures: i64,
+    #[serde(default)]
+    pub last_success: Option<String>,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+/// One LLM call, as recorded for cost accounting.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LlmUsage {
+    pub id: Option<i64>,
+    pub agent_id: Option<i64>,
+    pub provider: String,
+    pub model: String,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub latency_ms: i64,
+    pub cost_usd: f64,
+    pub success: bool,
+    pub created_at: String,
+}
+
+/// LLM usage totals for one day or one agent.
+#[derive(Debug, Serialize)]
+pub struct LlmUsageSummary {
+    pub key: String, // "YYYY-MM-DD" or the agent name ("" for calls made outside an agent)
+    pub agent_id: Option<i64>,
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub cost_usd: f64,
+}
+
+/// A reusable prompt. `body` may reference `{{variables}}`, see prompt_template.rs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub id: Option<i64>,
+    pub name: String,
+    pub description: Option<String>,
+    pub body: String,
+    pub model: Option<String>, // model the prompt was written for, None = any
+    pub version: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A past (or the current) revision of a prompt template.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PromptTemplateVersion {
+    pub template_id: i64,
+    pub version: i64,
+    pub description: Option<String>,
+    pub body: String,
+    pub model: Option<String>,
+    pub created_at: String,
+}
+
+/// A row of the `settings` table; `value` is JSON. See settings.rs.
+#[derive(Debug)]
+pub struct StoredSetting {
+    pub key: String,
+    pub value: String,
+    pub updated_at: String,
+}
+
+/// Something an agent remembered, with the embedding used to search it.
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentMemory {
+    pub id: Option<i64>,
+    pub agent_id: i64,
+    pub content: String,
+    #[serde(skip)]
+    pub embedding: Vec<f32>,
+    pub model: String, // embedding model; vectors from different models aren't comparable
+    pub created_at: String,
+}
+
+/// One step of an installer run, see installer.rs.
+#[derive(Debug, Serialize)]
+pub struct InstallLogEntry {
+    pub id: Option<i64>,
+    pub run_id: String,
+    pub step_id: String,
+    pub command: String,
+    pub status: String, // "succeeded", "failed", "cancelled"
+    pub exit_code: Option<i32>,
+    pub output: String, // last lines of output
+    pub started_at: String,
+    pub finished_at: String,
+}
+
+/// A desktop notification about an agent run, or one that was held back by
+/// rate limiting; see notifications.rs.
+#[derive(Debug, Serialize)]
+pub struct NotificationRecord {
+    pub id: Option<i64>,
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub outcome: String, // "success", "failure"
+    pub title: String,
+    pub body: String,
+    pub status: String,      // "shown", "suppressed", "failed"
+    pub grouped_count: i64,  // runs a shown notification covers, including suppressed ones
+    pub created_at: String,
+}
+
+/// One attempt to send a run result (or a test message) to an email or
+/// webhook channel; see channels.rs.
+#[derive(Debug, Serialize)]
+pub struct DeliveryRecord {
+    pub id: Option<i64>,
+    pub channel: String,
+    pub kind: String,            // "smtp", "webhook"
+    pub agent_id: Option<i64>,   // None for test messages
+    pub agent_name: String,
+    pub status: String,          // "sent", "failed"
+    pub error: Option<String>,
+    pub created_at: String,
 }
 
+/// Current schema version, stored in SQLite's `user_version` pragma.
+pub const SCHEMA_VERSION: i64 = 8;
+
+/// Schema changes, in order: each brings the database to the version next
+/// to it. See `Database::migrate`.
+const MIGRATIONS: &[(i64, &str)] = &[
+    // Event handler health tracking
+    (
+        1,
+        "ALTER TABLE event_handlers ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;
+         ALTER TABLE event_handlers ADD COLUMN last_success TEXT;
+         ALTER TABLE event_handlers ADD COLUMN last_error TEXT;",
+    ),
+    // Items already seen by RSS/Atom handlers, so each one only fires once
+    (
+        2,
+        "CREATE TABLE IF NOT EXISTS feed_items_seen (
+            handler_id INTEGER NOT NULL,
+            guid TEXT NOT NULL,
+            seen_at TEXT NOT NULL,
+            PRIMARY KEY (handler_id, guid),
+            FOREIGN KEY (handler_id) REFERENCES event_handlers(id)
+        )",
+    ),
+    // LLM usage and cost accounting
+    (
+        3,
+        "CREATE TABLE IF NOT EXISTS llm_usage (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER,
+            provider TEXT NOT NULL,
+            model TEXT NOT NULL,
+            prompt_tokens INTEGER NOT NULL DEFAULT 0,
+            completion_tokens INTEGER NOT NULL DEFAULT 0,
+            latency_ms INTEGER NOT NULL DEFAULT 0,
+            cost_usd REAL NOT NULL DEFAULT 0,
+            success INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (agent_id) REFERENCES agents(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_llm_usage_created_at ON llm_usage(created_at);",
+    ),
+    // Prompt template library, every revision kept for rollback
+    (
+        4,
+        "CREATE TABLE IF NOT EXISTS prompt_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            description TEXT,
+            body TEXT NOT NULL,
+            model TEXT,
+            version INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS prompt_template_versions (
+            template_id INTEGER NOT NULL,
+            version INTEGER NOT NULL,
+            description TEXT,
+            body TEXT NOT NULL,
+            model TEXT,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (template_id, version),
+            FOREIGN KEY (template_id) REFERENCES prompt_templates(id)
+        );",
+    ),
+    // Agent memory; embeddings are little-endian f32 blobs
+    (
+        5,
+        "CREATE TABLE IF NOT EXISTS agent_memories (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            embedding BLOB NOT NULL,
+            model TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (agent_id) REFERENCES agents(id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_agent_memories_agent ON agent_memories(agent_id);",
+    ),
+    // What the dependency installer ran
+    (
+        6,
+        "CREATE TABLE IF NOT EXISTS install_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            run_id TEXT NOT NULL,
+            step_id TEXT NOT NULL,
+            command TEXT NOT NULL,
+            status TEXT NOT NULL,
+            exit_code INTEGER,
+            output TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL
+        )",
+    ),
+    // Desktop notification history, also used for rate limiting
+    (
+        7,
+        "CREATE TABLE IF NOT EXISTS notifications (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            agent_id INTEGER NOT NULL,
+            agent_name TEXT NOT NULL,
+            outcome TEXT NOT NULL,
+            title TEXT NOT NULL,
+            body TEXT NOT NULL,
+            status TEXT NOT NULL,
+            grouped_count INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS idx_notifications_agent
+            ON notifications(agent_id, outcome, created_at);",
+    ),
+    // Email and webhook delivery log
+    (
+        8,
+        "CREATE TABLE IF NOT EXISTS notification_deliveries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            channel TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            agent_id INTEGER,
+            agent_name TEXT NOT NULL,
+            status TEXT NOT NULL,
+            error TEXT,
+            created_at TEXT NOT NULL
+        )",
+    ),
+];
+
+const EVENT_HANDLER_COLUMNS: &str = "id, name, event_type, url, interval_seconds, last_check, is_active, \
+     config_json, consecutive_failures, last_success, last_error";
+
+const NOTIFICATION_COLUMNS: &str = "id, agent_id, agent_name, outcome, title, body, status, grouped_count, created_at";
+
 pub struct Database {
     conn: Connection,
 }
 
 impl Database {
+    /// Opens the app database, ~/.personaliz/personaliz.db.
     pub fn new() -> Result<Self> {
-        let db_path = Self::get_db_path();
-        
+        Self::open(&Self::get_db_path())
+    }
+
+    /// Opens (or creates) a database file and brings its schema up to date.
+    pub fn open(path: &Path) -> Result<Self> {
         // Create directory if it doesn't exist
-        if let Some(parent) = db_path.parent() {
+        if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)
                 .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
         }
 
-        let conn = Connection::open(&db_path)?;
-        
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// A private database that disappears when dropped, for tests.
+    pub fn in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
         let db = Database { conn };
         db.init_tables()?;
+        db.migrate()?;
         Ok(db)
     }
 
+    /// Starts a transaction spanning several of the methods below, such as a
+    /// bundle import. It rolls back when dropped unless committed.
+    pub fn begin(&self) -> Result<rusqlite::Transaction<'_>> {
+        self.conn.unchecked_transaction()
+    }
+
+    // Runs a multi-statement write in its own transaction, or as part of the
+    // caller's if `begin` already opened one
+    fn atomically<T>(&self, write: impl FnOnce() -> Result<T>) -> Result<T> {
+        if !self.conn.is_autocommit() {
+            return write();
+        }
+        let tx = self.conn.unchecked_transaction()?;
+        let value = write()?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    /// Copies the whole database to `path` with SQLite's online backup API,
+    /// so it is consistent even while other processes write to it.
+    pub fn backup_to(&self, path: &Path) -> Result<()> {
+        self.conn.backup(DatabaseName::Main, path, None)
+    }
+
+    /// Replaces the contents of this database with the database at `path`,
+    /// then brings its schema up to date. Check it with `schema_version_of` first.
+    pub fn restore_from(&mut self, path: &Path) -> Result<()> {
+        self.conn.restore(DatabaseName::Main, path, None::<fn(rusqlite::backup::Progress)>)?;
+        self.init_tables()?;
+        self.migrate()
+    }
+
+    /// Opens a database file read-only and returns its schema version.
+    /// Fails if it is damaged or isn't an app database.
+    pub fn schema_version_of(path: &Path) -> std::result::Result<i64, String> {
+        let invalid = |e: rusqlite::Error| format!("{} is not a readable database: {}", path.display(), e);
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(invalid)?;
+        let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0)).map_err(invalid)?;
+        if check != "ok" {
+            return Err(format!("{} is damaged: {}", path.display(), check));
+        }
+        let has_agents: bool = conn
+            .query_row("SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'agents'", [], |row| row.get(0))
+            .map_err(invalid)?;
+        if !has_agents {
+            return Err(format!("{} is not a Personaliz database", path.display()));
+        }
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(invalid)
+    }
+
     fn get_db_path() -> PathBuf {
-        let home = std::env::var("USERPROFILE")
-            .unwrap_or_else(|_| ".".to_string());
-        PathBuf::from(home)
-            .join(".personaliz")
-            .join("personaliz.db")
+        crate::settings::data_dir().join("personaliz.db")
     }
 
     fn init_tables(&self) -> Result<()> {
@@ -126,7 +465,7 @@ impl Database {
             [],
         )?;
 
-        // Settings table (for future use)
+        // Settings table, one JSON value per AppSettings field (see settings.rs)
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS settings (
                 key TEXT PRIMARY KEY,
@@ -139,6 +478,11 @@ impl Database {
         Ok(())
     }
 
+    /// Brings tables created by older versions up to `SCHEMA_VERSION`.
+    fn migrate(&self) -> Result<()> {
+        run_migrations(&self.conn, MIGRATIONS)
+    }
+
     // Agent operations
     pub fn create_agent(&self, agent: &Agent) -> Result<i64> {
         let now = chrono::Utc::now().to_rfc3339();
@@ -228,7 +572,6 @@ impl Database {
         agents.next().transpose()
     }
 
-    #[allow(dead_code)]
     pub fn update_agent(&self, id: i64, agent: &Agent) -> Result<()> {
         let now = chrono::Utc::now().to_rfc3339();
         
@@ -259,6 +602,7 @@ impl Database {
 
     #[allow(dead_code)]
     pub fn delete_agent(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM agent_memories WHERE agent_id = ?1", [id])?;
         self.conn.execute("DELETE FROM agents WHERE id = ?1", [id])?;
         Ok(())
     }
@@ -313,6 +657,15 @@ impl Database {
         logs.collect()
     }
 
+    /// Timestamp of the agent's most recent "executed" log entry, if it ever ran.
+    pub fn get_last_agent_run(&self, agent_id: i64) -> Result<Option<String>> {
+        self.conn.query_row(
+            "SELECT MAX(timestamp) FROM agent_logs WHERE agent_id = ?1 AND event_type = 'executed'",
+            [agent_id],
+            |row| row.get(0),
+        )
+    }
+
     // Event handler operations
     pub fn create_event_handler(&self, handler: &EventHandler) -> Result<i64> {
         self.conn.execute(
@@ -333,11 +686,24 @@ impl Database {
         Ok(self.conn.last_insert_rowid())
     }
 
+    /// All handlers, including ones that were stopped or auto-disabled, so
+    /// their health is still visible.
     pub fn get_all_event_handlers(&self) -> Result<Vec<EventHandler>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, name, event_type, url, interval_seconds, last_check, is_active, config_json 
-             FROM event_handlers WHERE is_active = 1"
-        )?;
+        self.query_event_handlers(&format!(
+            "SELECT {} FROM event_handlers ORDER BY name",
+            EVENT_HANDLER_COLUMNS
+        ))
+    }
+
+    pub fn get_active_event_handlers(&self) -> Result<Vec<EventHandler>> {
+        self.query_event_handlers(&format!(
+            "SELECT {} FROM event_handlers WHERE is_active = 1",
+            EVENT_HANDLER_COLUMNS
+        ))
+    }
+
+    fn query_event_handlers(&self, query: &str) -> Result<Vec<EventHandler>> {
+        let mut stmt = self.conn.prepare(query)?;
 
         let handlers = stmt.query_map([], |row| {
             Ok(EventHandler {
@@ -349,35 +715,920 @@ impl Database {
                 last_check: row.get(5)?,
                 is_active: row.get(6)?,
                 config_json: row.get(7)?,
+                consecutive_failures: row.get(8)?,
+                last_success: row.get(9)?,
+                last_error: row.get(10)?,
             })
         })?;
 
         handlers.collect()
     }
 
-    pub fn update_event_handler_last_check(&self, id: i64) -> Result<()> {
+    /// Editing a handler (e.g. fixing its URL or re-enabling it) clears its failure streak.
+    pub fn update_event_handler(&self, id: i64, handler: &EventHandler) -> Result<()> {
+        self.conn.execute(
+            "UPDATE event_handlers SET name=?1, event_type=?2, url=?3, interval_seconds=?4, 
+             is_active=?5, config_json=?6, consecutive_failures=0 WHERE id=?7",
+            rusqlite::params![
+                &handler.name,
+                &handler.event_type,
+                &handler.url,
+                &handler.interval_seconds,
+                handler.is_active as i64,
+                &handler.config_json,
+                &id,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_event_handler_success(&self, id: i64) -> Result<()> {
         let now = chrono::Utc::now().to_rfc3339();
         self.conn.execute(
-            "UPDATE event_handlers SET last_check = ?1 WHERE id = ?2",
+            "UPDATE event_handlers SET last_check = ?1, last_success = ?1, 
+             consecutive_failures = 0 WHERE id = ?2",
             rusqlite::params![&now, &id],
         )?;
         Ok(())
     }
 
-    #[allow(dead_code)]
+    /// Records a failed check and returns the new failure streak and whether the
+    /// handler was disabled for reaching `disable_after` (0 never disables).
+    pub fn record_event_handler_failure(&self, id: i64, error: &str, disable_after: i64) -> Result<(i64, bool)> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.conn.execute(
+            "UPDATE event_handlers SET last_check = ?1, last_error = ?2, 
+             consecutive_failures = consecutive_failures + 1 WHERE id = ?3",
+            rusqlite::params![&now, error, &id],
+        )?;
+
+        let failures: i64 = self.conn.query_row(
+            "SELECT consecutive_failures FROM event_handlers WHERE id = ?1",
+            [id],
+            |row| row.get(0),
+        )?;
+
+        let disabled = disable_after > 0 && failures >= disable_after;
+        if disabled {
+            self.conn.execute("UPDATE event_handlers SET is_active = 0 WHERE id = ?1", [id])?;
+        }
+
+        Ok((failures, disabled))
+    }
+
     pub fn delete_event_handler(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM feed_items_seen WHERE handler_id = ?1", [id])?;
         self.conn.execute("DELETE FROM event_handlers WHERE id = ?1", [id])?;
         Ok(())
     }
+
+    // LLM usage operations
+    pub fn record_llm_usage(&self, usage: &LlmUsage) -> Result<i64> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.conn.execute(
+            "INSERT INTO llm_usage (agent_id, provider, model, prompt_tokens, completion_tokens, latency_ms, cost_usd, success, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            rusqlite::params![
+                &usage.agent_id,
+                &usage.provider,
+                &usage.model,
+                &usage.prompt_tokens,
+                &usage.completion_tokens,
+                &usage.latency_ms,
+                &usage.cost_usd,
+                &usage.success,
+                &now,
+            ],
+        )?;
+
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Total estimated cost of LLM calls made at or after `since` (RFC 3339).
+    pub fn get_llm_cost_since(&self, since: &str) -> Result<f64> {
+        self.conn.query_row(
+            "SELECT COALESCE(SUM(cost_usd), 0) FROM llm_usage WHERE created_at >= ?1",
+            [since],
+            |row| row.get(0),
+        )
+    }
+
+    /// Usage per UTC day since `since`, newest day first.
+    pub fn get_llm_usage_by_day(&self, since: &str) -> Result<Vec<LlmUsageSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT substr(created_at, 1, 10) AS day, COUNT(*), SUM(prompt_tokens), SUM(completion_tokens), SUM(cost_usd)
+             FROM llm_usage WHERE created_at >= ?1 GROUP BY day ORDER BY day DESC",
+        )?;
+
+        let days = stmt.query_map([since], |row| {
+            Ok(LlmUsageSummary {
+                key: row.get(0)?,
+                agent_id: None,
+                calls: row.get(1)?,
+                prompt_tokens: row.get(2)?,
+                completion_tokens: row.get(3)?,
+                cost_usd: row.get(4)?,
+            })
+        })?;
+
+        days.collect()
+    }
+
+    /// Usage per agent since `since`, most expensive first.
+    pub fn get_llm_usage_by_agent(&self, since: &str) -> Result<Vec<LlmUsageSummary>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT u.agent_id, COALESCE(a.name, ''), COUNT(*), SUM(u.prompt_tokens), SUM(u.completion_tokens), SUM(u.cost_usd) AS cost
+             FROM llm_usage u LEFT JOIN agents a ON a.id = u.agent_id
+             WHERE u.created_at >= ?1 GROUP BY u.agent_id ORDER BY cost DESC",
+        )?;
+
+        let agents = stmt.query_map([since], |row| {
+            Ok(LlmUsageSummary {
+                agent_id: row.get(0)?,
+                key: row.get(1)?,
+                calls: row.get(2)?,
+                prompt_tokens: row.get(3)?,
+                completion_tokens: row.get(4)?,
+                cost_usd: row.get(5)?,
+            })
+        })?;
+
+        agents.collect()
+    }
+
+    pub fn create_prompt_template(&self, template: &PromptTemplate) -> Result<i64> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.atomically(|| {
+            self.conn.execute(
+                "INSERT INTO prompt_templates (name, description, body, model, version, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, 1, ?5, ?5)",
+                rusqlite::params![&template.name, &template.description, &template.body, &template.model, &now],
+            )?;
+            let id = self.conn.last_insert_rowid();
+            self.insert_prompt_template_version(id, 1, template, &now)?;
+            Ok(id)
+        })
+    }
+
+    pub fn get_all_prompt_templates(&self) -> Result<Vec<PromptTemplate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, description, body, model, version, created_at, updated_at
+             FROM prompt_templates ORDER BY name",
+        )?;
+        let templates = stmt.query_map([], Self::prompt_template_from_row)?;
+        templates.collect()
+    }
+
+    pub fn get_prompt_template_by_name(&self, name: &str) -> Result<Option<PromptTemplate>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, name, description, body, model, version, created_at, updated_at
+             FROM prompt_templates WHERE name = ?1",
+        )?;
+        let mut templates = stmt.query_map([name], Self::prompt_template_from_row)?;
+        templates.next().transpose()
+    }
+
+    fn prompt_template_from_row(row: &rusqlite::Row) -> Result<PromptTemplate> {
+        Ok(PromptTemplate {
+            id: Some(row.get(0)?),
+            name: row.get(1)?,
+            description: row.get(2)?,
+            body: row.get(3)?,
+            model: row.get(4)?,
+            version: row.get(5)?,
+            created_at: row.get(6)?,
+            updated_at: row.get(7)?,
+        })
+    }
+
+    /// Saves a new revision of a template and returns its version number.
+    /// Saving identical content doesn't create a revision.
+    pub fn update_prompt_template(&self, id: i64, template: &PromptTemplate) -> Result<i64> {
+        self.atomically(|| {
+            let (description, body, model, version): (Option<String>, String, Option<String>, i64) = self.conn.query_row(
+                "SELECT description, body, model, version FROM prompt_templates WHERE id = ?1",
+                [id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )?;
+            if description == template.description && body == template.body && model == template.model {
+                return Ok(version);
+            }
+
+            let now = chrono::Utc::now().to_rfc3339();
+            let new_version = version + 1;
+            self.conn.execute(
+                "UPDATE prompt_templates SET description=?1, body=?2, model=?3, version=?4, updated_at=?5 WHERE id=?6",
+                rusqlite::params![&template.description, &template.body, &template.model, &new_version, &now, &id],
+            )?;
+            self.insert_prompt_template_version(id, new_version, template, &now)?;
+
+            Ok(new_version)
+        })
+    }
+
+    /// Version history, newest first.
+    pub fn get_prompt_template_versions(&self, id: i64) -> Result<Vec<PromptTemplateVersion>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT template_id, version, description, body, model, created_at
+             FROM prompt_template_versions WHERE template_id = ?1 ORDER BY version DESC",
+        )?;
+        let versions = stmt.query_map([id], |row| {
+            Ok(PromptTemplateVersion {
+                template_id: row.get(0)?,
+                version: row.get(1)?,
+                description: row.get(2)?,
+                body: row.get(3)?,
+                model: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?;
+        versions.collect()
+    }
+
+    /// Restores the content of an earlier version. This is saved as a new
+    /// version, so the rollback itself can be undone.
+    pub fn rollback_prompt_template(&self, id: i64, version: i64) -> Result<i64> {
+        let old = self.conn.query_row(
+            "SELECT description, body, model FROM prompt_template_versions WHERE template_id = ?1 AND version = ?2",
+            rusqlite::params![&id, &version],
+            |row| {
+                Ok(PromptTemplate {
+                    id: Some(id),
+                    name: String::new(),
+                    description: row.get(0)?,
+                    body: row.get(1)?,
+                    model: row.get(2)?,
+                    version,
+                    created_at: String::new(),
+                    updated_at: String::new(),
+                })
+            },
+        )?;
+        self.update_prompt_template(id, &old)
+    }
+
+    pub fn delete_prompt_template(&self, id: i64) -> Result<()> {
+        self.atomically(|| {
+            self.conn.execute("DELETE FROM prompt_template_versions WHERE template_id = ?1", [id])?;
+            self.conn.execute("DELETE FROM prompt_templates WHERE id = ?1", [id])?;
+            Ok(())
+        })
+    }
+
+    fn insert_prompt_template_version(&self, id: i64, version: i64, template: &PromptTemplate, now: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO prompt_template_versions (template_id, version, description, body, model, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![&id, &version, &template.description, &template.body, &template.model, now],
+        )?;
+        Ok(())
+    }
+
+    pub fn add_agent_memory(&self, memory: &AgentMemory) -> Result<i64> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let blob: Vec<u8> = memory.embedding.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.conn.execute(
+            "INSERT INTO agent_memories (agent_id, content, embedding, model, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![&memory.agent_id, &memory.content, &blob, &memory.model, &now],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// All memories of an agent, newest first.
+    pub fn get_agent_memories(&self, agent_id: i64) -> Result<Vec<AgentMemory>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, agent_id, content, embedding, model, created_at FROM agent_memories
+             WHERE agent_id = ?1 ORDER BY created_at DESC, id DESC",
+        )?;
+        let memories = stmt.query_map([agent_id], |row| {
+            let blob: Vec<u8> = row.get(3)?;
+            Ok(AgentMemory {
+                id: Some(row.get(0)?),
+                agent_id: row.get(1)?,
+                content: row.get(2)?,
+                embedding: blob
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+                model: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?;
+        memories.collect()
+    }
+
+    pub fn delete_agent_memory(&self, id: i64) -> Result<()> {
+        self.conn.execute("DELETE FROM agent_memories WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Forgets everything an agent remembered; returns how many memories were removed.
+    pub fn clear_agent_memories(&self, agent_id: i64) -> Result<usize> {
+        self.conn.execute("DELETE FROM agent_memories WHERE agent_id = ?1", [agent_id])
+    }
+
+    pub fn add_install_log_entry(&self, entry: &InstallLogEntry) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO install_log (run_id, step_id, command, status, exit_code, output, started_at, finished_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                &entry.run_id,
+                &entry.step_id,
+                &entry.command,
+                &entry.status,
+                &entry.exit_code,
+                &entry.output,
+                &entry.started_at,
+                &entry.finished_at,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Most recent install steps first.
+    pub fn get_install_log(&self, limit: i64) -> Result<Vec<InstallLogEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, run_id, step_id, command, status, exit_code, output, started_at, finished_at
+             FROM install_log ORDER BY id DESC LIMIT ?1",
+        )?;
+        let entries = stmt.query_map([limit], |row| {
+            Ok(InstallLogEntry {
+                id: Some(row.get(0)?),
+                run_id: row.get(1)?,
+                step_id: row.get(2)?,
+                command: row.get(3)?,
+                status: row.get(4)?,
+                exit_code: row.get(5)?,
+                output: row.get(6)?,
+                started_at: row.get(7)?,
+                finished_at: row.get(8)?,
+            })
+        })?;
+        entries.collect()
+    }
+
+    pub fn add_notification(&self, notification: &NotificationRecord) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO notifications (agent_id, agent_name, outcome, title, body, status, grouped_count, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                &notification.agent_id,
+                &notification.agent_name,
+                &notification.outcome,
+                &notification.title,
+                &notification.body,
+                &notification.status,
+                &notification.grouped_count,
+                &notification.created_at,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Most recent notifications first, including suppressed ones.
+    pub fn get_notifications(&self, limit: i64) -> Result<Vec<NotificationRecord>> {
+        self.query_notifications(
+            &format!("SELECT {} FROM notifications ORDER BY id DESC LIMIT ?1", NOTIFICATION_COLUMNS),
+            rusqlite::params![limit],
+        )
+    }
+
+    /// The last notification actually shown for an agent's successes or failures.
+    pub fn get_last_shown_notification(&self, agent_id: i64, outcome: &str) -> Result<Option<NotificationRecord>> {
+        let mut shown = self.query_notifications(
+            &format!(
+                "SELECT {} FROM notifications WHERE agent_id = ?1 AND outcome = ?2 AND status = 'shown'
+                 ORDER BY id DESC LIMIT 1",
+                NOTIFICATION_COLUMNS
+            ),
+            rusqlite::params![agent_id, outcome],
+        )?;
+        Ok(shown.pop())
+    }
+
+    /// Notifications suppressed after notification `after_id` (0 = ever).
+    pub fn count_suppressed_notifications(&self, agent_id: i64, outcome: &str, after_id: i64) -> Result<i64> {
+        self.conn.query_row(
+            "SELECT COUNT(*) FROM notifications
+             WHERE agent_id = ?1 AND outcome = ?2 AND status = 'suppressed' AND id > ?3",
+            rusqlite::params![agent_id, outcome, after_id],
+            |row| row.get(0),
+        )
+    }
+
+    pub fn clear_notifications(&self) -> Result<usize> {
+        self.conn.execute("DELETE FROM notifications", [])
+    }
+
+    pub fn add_delivery(&self, delivery: &DeliveryRecord) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO notification_deliveries (channel, kind, agent_id, agent_name, status, error, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            (
+                &delivery.channel,
+                &delivery.kind,
+                &delivery.agent_id,
+                &delivery.agent_name,
+                &delivery.status,
+                &delivery.error,
+                &delivery.created_at,
+            ),
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Most recent deliveries first.
+    pub fn get_deliveries(&self, limit: i64) -> Result<Vec<DeliveryRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, channel, kind, agent_id, agent_name, status, error, created_at
+             FROM notification_deliveries ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], |row| {
+            Ok(DeliveryRecord {
+                id: Some(row.get(0)?),
+                channel: row.get(1)?,
+                kind: row.get(2)?,
+                agent_id: row.get(3)?,
+                agent_name: row.get(4)?,
+                status: row.get(5)?,
+                error: row.get(6)?,
+                created_at: row.get(7)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    fn query_notifications(&self, query: &str, params: impl rusqlite::Params) -> Result<Vec<NotificationRecord>> {
+        let mut stmt = self.conn.prepare(query)?;
+        let rows = stmt.query_map(params, |row| {
+            Ok(NotificationRecord {
+                id: Some(row.get(0)?),
+                agent_id: row.get(1)?,
+                agent_name: row.get(2)?,
+                outcome: row.get(3)?,
+                title: row.get(4)?,
+                body: row.get(5)?,
+                status: row.get(6)?,
+                grouped_count: row.get(7)?,
+                created_at: row.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get_settings(&self) -> Result<Vec<StoredSetting>> {
+        let mut stmt = self.conn.prepare("SELECT key, value, updated_at FROM settings ORDER BY key")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(StoredSetting {
+                key: row.get(0)?,
+                value: row.get(1)?,
+                updated_at: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.conn.execute(
+            "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            rusqlite::params![key, value, &now],
+        )?;
+        Ok(())
+    }
+
+    // Feed item operations
+    pub fn has_seen_feed_items(&self, handler_id: i64) -> Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM feed_items_seen WHERE handler_id = ?1)",
+            [handler_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Remembers a feed item, returning true if it had not been seen before.
+    pub fn mark_feed_item_seen(&self, handler_id: i64, guid: &str) -> Result<bool> {
+        let now = chrono::Utc::now().to_rfc3339();
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO feed_items_seen (handler_id, guid, seen_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![&handler_id, guid, &now],
+        )?;
+        Ok(inserted > 0)
+    }
+}
+
+/// Applies each step newer than the database's `user_version` in its own
+/// transaction, together with the bump to that step's version, so a failed
+/// step leaves no half-applied change behind and is retried on the next open.
+fn run_migrations(conn: &Connection, steps: &[(i64, &str)]) -> Result<()> {
+    let current: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (version, sql) in steps.iter().filter(|(version, _)| *version > current) {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", version)?;
+        tx.commit()?;
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::TempDir;
+
+    fn db() -> Database {
+        Database::in_memory().unwrap()
+    }
+
+    fn agent(name: &str) -> Agent {
+        Agent::from_config(
+            name,
+            r#"{"schedule":"daily","schedule_time":"09:00","command":"node","args":["linkedin_bot.js"],
+                "metadata":{"role":"Writer","goal":"Post daily"}}"#,
+        )
+        .unwrap()
+    }
+
+    fn log(agent_id: i64, event_type: &str) -> AgentLog {
+        AgentLog {
+            id: None,
+            agent_id,
+            agent_name: "writer".to_string(),
+            event_type: event_type.to_string(),
+            message: format!("{} happened", event_type),
+            details: None,
+            timestamp: String::new(),
+        }
+    }
+
+    fn handler(name: &str) -> EventHandler {
+        EventHandler {
+            id: None,
+            name: name.to_string(),
+            event_type: "rss".to_string(),
+            url: Some("https://example.com/feed.xml".to_string()),
+            interval_seconds: 300,
+            last_check: None,
+            is_active: true,
+            config_json: "{}".to_string(),
+            consecutive_failures: 0,
+            last_success: None,
+            last_error: None,
+        }
+    }
+
+    fn usage(agent_id: Option<i64>, cost_usd: f64) -> LlmUsage {
+        LlmUsage {
+            id: None,
+            agent_id,
+            provider: "openai".to_string(),
+            model: "gpt-4o-mini".to_string(),
+            prompt_tokens: 100,
+            completion_tokens: 20,
+            latency_ms: 250,
+            cost_usd,
+            success: true,
+            created_at: String::new(),
+        }
+    }
+
+    fn template(name: &str, body: &str) -> PromptTemplate {
+        PromptTemplate {
+            id: None,
+            name: name.to_string(),
+            description: None,
+            body: body.to_string(),
+            model: None,
+            version: 1,
+            created_at: String::new(),
+            updated_at: String::new(),
+        }
+    }
+
+    fn memory(agent_id: i64, content: &str) -> AgentMemory {
+        AgentMemory {
+            id: None,
+            agent_id,
+            content: content.to_string(),
+            embedding: vec![0.5, -1.25, 3.0],
+            model: "nomic-embed-text".to_string(),
+            created_at: String::new(),
+        }
+    }
 
     #[test]
     fn test_database_creation() {
-        let db = Database::new().unwrap();
-        assert!(db.get_all_agents().is_ok());
+        let db = db();
+        assert!(db.get_all_agents().unwrap().is_empty());
+        let version: i64 = db.conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn test_failed_migration_step_rolls_back() {
+        assert_eq!(MIGRATIONS.last().unwrap().0, SCHEMA_VERSION);
+
+        let conn = Connection::open_in_memory().unwrap();
+        let version = |conn: &Connection| conn.query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0)).unwrap();
+        let broken = [
+            (1, "CREATE TABLE a (x TEXT)"),
+            (2, "ALTER TABLE a ADD COLUMN y TEXT; INSERT INTO missing VALUES (1)"),
+        ];
+        assert!(run_migrations(&conn, &broken).is_err());
+        assert_eq!(version(&conn), 1);
+
+        // Step 2 left nothing behind, so it applies cleanly once fixed
+        let fixed = [broken[0], (2, "ALTER TABLE a ADD COLUMN y TEXT")];
+        run_migrations(&conn, &fixed).unwrap();
+        assert_eq!(version(&conn), 2);
+    }
+
+    #[test]
+    fn test_open_file_keeps_data_across_opens() {
+        let dir = TempDir::new("db");
+        let path = dir.path().join("nested").join("test.db");
+
+        Database::open(&path).unwrap().create_agent(&agent("writer")).unwrap();
+        // Reopening runs the migrations again; they must be no-ops
+        let db = Database::open(&path).unwrap();
+        assert_eq!(db.get_all_agents().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_agent_crud() {
+        let db = db();
+        let id = db.create_agent(&agent("writer")).unwrap();
+        db.create_agent(&agent("monitor")).unwrap();
+        assert!(db.create_agent(&agent("writer")).is_err(), "names are unique");
+
+        let mut writer = db.get_agent_by_name("writer").unwrap().unwrap();
+        assert_eq!(writer.id, Some(id));
+        assert_eq!(writer.role.as_deref(), Some("Writer"));
+        assert_eq!(writer.schedule_time.as_deref(), Some("09:00"));
+        assert!(writer.is_active);
+        assert!(db.get_agent_by_name("nobody").unwrap().is_none());
+
+        writer.schedule = "hourly".to_string();
+        writer.is_active = false;
+        db.update_agent(id, &writer).unwrap();
+        let writer = db.get_agent_by_name("writer").unwrap().unwrap();
+        assert_eq!(writer.schedule, "hourly");
+        assert!(!writer.is_active);
+
+        db.add_agent_memory(&memory(id, "likes short posts")).unwrap();
+        db.delete_agent(id).unwrap();
+        assert!(db.get_agent_by_name("writer").unwrap().is_none());
+        assert!(db.get_agent_memories(id).unwrap().is_empty());
+        assert_eq!(db.get_all_agents().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_agent_logs() {
+        let db = db();
+        let writer = db.create_agent(&agent("writer")).unwrap();
+        let monitor = db.create_agent(&agent("monitor")).unwrap();
+        assert_eq!(db.get_last_agent_run(writer).unwrap(), None);
+
+        db.log_agent_event(&log(writer, "executed")).unwrap();
+        db.log_agent_event(&log(writer, "success")).unwrap();
+        db.log_agent_event(&log(monitor, "error")).unwrap();
+
+        assert_eq!(db.get_agent_logs(None, 10).unwrap().len(), 3);
+        assert_eq!(db.get_agent_logs(None, 2).unwrap().len(), 2);
+        let writer_logs = db.get_agent_logs(Some(writer), 10).unwrap();
+        assert_eq!(writer_logs.len(), 2);
+        assert_eq!(writer_logs[0].event_type, "success", "newest first");
+
+        let last_run = db.get_last_agent_run(writer).unwrap().unwrap();
+        assert_eq!(last_run, writer_logs[1].timestamp);
+        assert_eq!(db.get_last_agent_run(monitor).unwrap(), None);
+    }
+
+    #[test]
+    fn test_event_handler_lifecycle() {
+        let db = db();
+        let id = db.create_event_handler(&handler("feed")).unwrap();
+        let mut paused = handler("paused");
+        paused.is_active = false;
+        db.create_event_handler(&paused).unwrap();
+
+        assert_eq!(db.get_all_event_handlers().unwrap().len(), 2);
+        assert_eq!(db.get_active_event_handlers().unwrap().len(), 1);
+
+        db.record_event_handler_success(id).unwrap();
+        assert_eq!(db.record_event_handler_failure(id, "timeout", 2).unwrap(), (1, false));
+        assert_eq!(db.record_event_handler_failure(id, "timeout", 2).unwrap(), (2, true));
+        let feed = db.get_all_event_handlers().unwrap().into_iter().find(|h| h.id == Some(id)).unwrap();
+        assert!(!feed.is_active);
+        assert!(feed.last_success.is_some());
+        assert_eq!(feed.last_error.as_deref(), Some("timeout"));
+
+        // Fixing and re-enabling the handler clears its failure streak
+        db.update_event_handler(id, &handler("feed")).unwrap();
+        let feed = db.get_active_event_handlers().unwrap().into_iter().find(|h| h.id == Some(id)).unwrap();
+        assert_eq!(feed.consecutive_failures, 0);
+
+        // 0 never disables
+        assert_eq!(db.record_event_handler_failure(id, "500", 0).unwrap(), (1, false));
+
+        db.mark_feed_item_seen(id, "item-1").unwrap();
+        db.delete_event_handler(id).unwrap();
+        assert_eq!(db.get_all_event_handlers().unwrap().len(), 1);
+        assert!(!db.has_seen_feed_items(id).unwrap());
+    }
+
+    #[test]
+    fn test_feed_items_seen() {
+        let db = db();
+        let id = db.create_event_handler(&handler("feed")).unwrap();
+        assert!(!db.has_seen_feed_items(id).unwrap());
+
+        assert!(db.mark_feed_item_seen(id, "item-1").unwrap());
+        assert!(!db.mark_feed_item_seen(id, "item-1").unwrap());
+        assert!(db.mark_feed_item_seen(id, "item-2").unwrap());
+        assert!(db.has_seen_feed_items(id).unwrap());
+    }
+
+    #[test]
+    fn test_llm_usage() {
+        let db = db();
+        let writer = db.create_agent(&agent("writer")).unwrap();
+        let since = (chrono::Utc::now() - chrono::Duration::days(1)).to_rfc3339();
+
+        db.record_llm_usage(&usage(Some(writer), 0.02)).unwrap();
+        db.record_llm_usage(&usage(Some(writer), 0.03)).unwrap();
+        db.record_llm_usage(&usage(None, 0.01)).unwrap();
+
+        assert!((db.get_llm_cost_since(&since).unwrap() - 0.06).abs() < 1e-9);
+        assert_eq!(db.get_llm_cost_since("9999-01-01").unwrap(), 0.0);
+
+        let days = db.get_llm_usage_by_day(&since).unwrap();
+        assert_eq!(days.len(), 1);
+        assert_eq!(days[0].calls, 3);
+        assert_eq!(days[0].prompt_tokens, 300);
+
+        let agents = db.get_llm_usage_by_agent(&since).unwrap();
+        assert_eq!(agents.len(), 2);
+        assert_eq!(agents[0].key, "writer", "most expensive first");
+        assert_eq!(agents[0].agent_id, Some(writer));
+        assert_eq!(agents[0].calls, 2);
+        assert_eq!(agents[1].key, "");
+    }
+
+    #[test]
+    fn test_prompt_templates() {
+        let db = db();
+        let id = db.create_prompt_template(&template("post", "Write about {{topic}}")).unwrap();
+        db.create_prompt_template(&template("comment", "Reply to {{event.title}}")).unwrap();
+        assert!(db.create_prompt_template(&template("post", "again")).is_err(), "names are unique");
+
+        let names: Vec<String> = db.get_all_prompt_templates().unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["comment", "post"]);
+
+        assert_eq!(db.update_prompt_template(id, &template("post", "Write about {{topic}}")).unwrap(), 1);
+        assert_eq!(db.update_prompt_template(id, &template("post", "Write briefly about {{topic}}")).unwrap(), 2);
+        let post = db.get_prompt_template_by_name("post").unwrap().unwrap();
+        assert_eq!(post.version, 2);
+        assert_eq!(post.body, "Write briefly about {{topic}}");
+
+        assert_eq!(db.rollback_prompt_template(id, 1).unwrap(), 3);
+        let post = db.get_prompt_template_by_name("post").unwrap().unwrap();
+        assert_eq!(post.body, "Write about {{topic}}");
+        let versions: Vec<i64> = db.get_prompt_template_versions(id).unwrap().iter().map(|v| v.version).collect();
+        assert_eq!(versions, [3, 2, 1]);
+        assert!(db.rollback_prompt_template(id, 9).is_err());
+
+        db.delete_prompt_template(id).unwrap();
+        assert!(db.get_prompt_template_by_name("post").unwrap().is_none());
+        assert!(db.get_prompt_template_versions(id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_prompt_template_writes_join_open_transaction() {
+        let db = db();
+        let id = db.create_prompt_template(&template("post", "Write about {{topic}}")).unwrap();
+
+        let tx = db.begin().unwrap();
+        assert_eq!(db.update_prompt_template(id, &template("post", "Write briefly")).unwrap(), 2);
+        db.delete_prompt_template(id).unwrap();
+        drop(tx);
+
+        let post = db.get_prompt_template_by_name("post").unwrap().unwrap();
+        assert_eq!(post.version, 1);
+        assert_eq!(db.get_prompt_template_versions(id).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_agent_memories() {
+        let db = db();
+        let writer = db.create_agent(&agent("writer")).unwrap();
+        let monitor = db.create_agent(&agent("monitor")).unwrap();
+        let first = db.add_agent_memory(&memory(writer, "likes short posts")).unwrap();
+        db.add_agent_memory(&memory(writer, "posts at 9am")).unwrap();
+        db.add_agent_memory(&memory(monitor, "watches #openclaw")).unwrap();
+
+        let memories = db.get_agent_memories(writer).unwrap();
+        assert_eq!(memories.len(), 2);
+        assert_eq!(memories[0].content, "posts at 9am", "newest first");
+        assert_eq!(memories[0].embedding, vec![0.5, -1.25, 3.0]);
+
+        db.delete_agent_memory(first).unwrap();
+        assert_eq!(db.get_agent_memories(writer).unwrap().len(), 1);
+        assert_eq!(db.clear_agent_memories(writer).unwrap(), 1);
+        assert!(db.get_agent_memories(writer).unwrap().is_empty());
+        assert_eq!(db.get_agent_memories(monitor).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_install_log() {
+        let db = db();
+        for step in ["npm-install", "playwright", "ollama-pull:phi3"] {
+            db.add_install_log_entry(&InstallLogEntry {
+                id: None,
+                run_id: "run-1".to_string(),
+                step_id: step.to_string(),
+                command: format!("run {}", step),
+                status: "succeeded".to_string(),
+                exit_code: Some(0),
+                output: String::new(),
+                started_at: chrono::Utc::now().to_rfc3339(),
+                finished_at: chrono::Utc::now().to_rfc3339(),
+            })
+            .unwrap();
+        }
+
+        let entries = db.get_install_log(2).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].step_id, "ollama-pull:phi3");
+        assert_eq!(entries[0].exit_code, Some(0));
+    }
+
+    #[test]
+    fn test_notifications() {
+        let db = db();
+        let notification = |status: &str| NotificationRecord {
+            id: None,
+            agent_id: 1,
+            agent_name: "monitor".to_string(),
+            outcome: "failure".to_string(),
+            title: "monitor failed".to_string(),
+            body: "Exited with code 1".to_string(),
+            status: status.to_string(),
+            grouped_count: 1,
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        assert!(db.get_last_shown_notification(1, "failure").unwrap().is_none());
+        let shown = db.add_notification(&notification("shown")).unwrap();
+        db.add_notification(&notification("suppressed")).unwrap();
+        db.add_notification(&notification("suppressed")).unwrap();
+
+        assert_eq!(db.get_last_shown_notification(1, "failure").unwrap().unwrap().id, Some(shown));
+        assert!(db.get_last_shown_notification(1, "success").unwrap().is_none());
+        assert_eq!(db.count_suppressed_notifications(1, "failure", shown).unwrap(), 2);
+        assert_eq!(db.count_suppressed_notifications(1, "failure", shown + 2).unwrap(), 0);
+        assert_eq!(db.get_notifications(10).unwrap().len(), 3);
+
+        assert_eq!(db.clear_notifications().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_deliveries() {
+        let db = db();
+        let delivery = |status: &str, error: Option<&str>| DeliveryRecord {
+            id: None,
+            channel: "ops-email".to_string(),
+            kind: "smtp".to_string(),
+            agent_id: Some(1),
+            agent_name: "monitor".to_string(),
+            status: status.to_string(),
+            error: error.map(str::to_string),
+            created_at: chrono::Utc::now().to_rfc3339(),
+        };
+
+        db.add_delivery(&delivery("sent", None)).unwrap();
+        db.add_delivery(&delivery("failed", Some("connection refused"))).unwrap();
+
+        let log = db.get_deliveries(1).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].status, "failed");
+        assert_eq!(log[0].error.as_deref(), Some("connection refused"));
+        assert_eq!(db.get_deliveries(10).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_settings_rows() {
+        let db = db();
+        assert!(db.get_settings().unwrap().is_empty());
+
+        db.set_setting("llm_model", r#""phi3""#).unwrap();
+        db.set_setting("poller_max_concurrency", "4").unwrap();
+        db.set_setting("llm_model", r#""llama3""#).unwrap();
+
+        let rows = db.get_settings().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].key, "llm_model");
+        assert_eq!(rows[0].value, r#""llama3""#);
     }
 }
@@ -0,0 +1,253 @@
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::process;
+
+const MIN_NODE: &str = "18.0.0";
+const MIN_NPM: &str = "9.0.0";
+// /api/embed, used for agent memory, arrived in Ollama 0.3.4
+const MIN_OLLAMA: &str = "0.3.4";
+
+#[derive(Debug, Serialize)]
+pub struct DependencyStatus {
+    pub name: String,
+    pub installed: bool,
+    pub version: Option<String>,
+    pub min_version: Option<String>,
+    pub meets_minimum: bool,
+    pub path: Option<String>,
+    /// What to do about it; None when the dependency is ready
+    pub remediation: Option<String>,
+}
+
+impl DependencyStatus {
+    pub fn ok(&self) -> bool {
+        self.installed && self.meets_minimum
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DependencyReport {
+    pub os: String,
+    pub ready: bool,
+    pub dependencies: Vec<DependencyStatus>,
+}
+
+/// Probes everything the agents need on this machine.
+pub fn check_all() -> DependencyReport {
+    let os = std::env::consts::OS;
+    let dependencies = vec![
+        check_command("node", "node", MIN_NODE, os),
+        check_command("npm", "npm", MIN_NPM, os),
+        check_playwright(os),
+        check_command("ollama", "ollama", MIN_OLLAMA, os),
+        check_ollama_server(os),
+        check_openclaw(),
+    ];
+
+    DependencyReport {
+        os: os.to_string(),
+        ready: dependencies.iter().all(DependencyStatus::ok),
+        dependencies,
+    }
+}
+
+/// Runs `<program> --version` and checks the reported version.
+fn check_command(name: &str, program: &str, min_version: &str, os: &str) -> DependencyStatus {
+    let version = run(program, &["--version"]).and_then(|output| parse_version(&output).map(format_version));
+    let installed = version.is_some();
+    let meets_minimum = version
+        .as_deref()
+        .and_then(parse_version)
+        .is_some_and(|v| parse_version(min_version).is_some_and(|min| v >= min));
+
+    let remediation = if !installed {
+        Some(install_hint(name, os))
+    } else if !meets_minimum {
+        Some(format!(
+            "{} {} is older than the required {}. {}",
+            name,
+            version.as_deref().unwrap_or_default(),
+            min_version,
+            install_hint(name, os)
+        ))
+    } else {
+        None
+    };
+
+    DependencyStatus {
+        name: name.to_string(),
+        installed,
+        version,
+        min_version: Some(min_version.to_string()),
+        meets_minimum,
+        path: None,
+        remediation,
+    }
+}
+
+fn check_playwright(os: &str) -> DependencyStatus {
+    let found = playwright_paths(os).into_iter().find(|path| {
+        // An empty cache directory means no browser was downloaded
+        std::fs::read_dir(path).is_ok_and(|mut entries| entries.any(|e| {
+            e.is_ok_and(|e| e.file_name().to_string_lossy().starts_with("chromium"))
+        }))
+    });
+
+    DependencyStatus {
+        name: "playwright".to_string(),
+        installed: found.is_some(),
+        version: None,
+        min_version: None,
+        meets_minimum: true,
+        remediation: found.is_none().then(|| install_hint("playwright", os)),
+        path: found.map(|p| p.display().to_string()),
+    }
+}
+
+fn check_ollama_server(os: &str) -> DependencyStatus {
+    // `ollama list` talks to the server, so it fails when the server isn't running
+    let running = run("ollama", &["list"]).is_some();
+    DependencyStatus {
+        name: "ollama-server".to_string(),
+        installed: running,
+        version: None,
+        min_version: None,
+        meets_minimum: true,
+        path: None,
+        remediation: (!running).then(|| install_hint("ollama-server", os)),
+    }
+}
+
+fn check_openclaw() -> DependencyStatus {
+    let path = openclaw_path();
+    let installed = path.join("package.json").exists();
+    DependencyStatus {
+        name: "openclaw".to_string(),
+        installed,
+        version: None,
+        min_version: None,
+        meets_minimum: true,
+        remediation: (!installed).then(|| {
+            format!(
+                "Clone OpenClaw to {} or set OPENCLAW_PATH to where it lives, then run: setup openclaw",
+                path.display()
+            )
+        }),
+        path: Some(path.display().to_string()),
+    }
+}
+
+/// Where Playwright keeps its browsers on each platform, with the
+/// `PLAYWRIGHT_BROWSERS_PATH` override checked first.
+pub fn playwright_paths(os: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(custom) = std::env::var_os("PLAYWRIGHT_BROWSERS_PATH").filter(|p| !p.is_empty()) {
+        paths.push(PathBuf::from(custom));
+    }
+
+    let home = home_dir();
+    match os {
+        "windows" => {
+            let local = std::env::var_os("LOCALAPPDATA")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join("AppData").join("Local"));
+            paths.push(local.join("ms-playwright"));
+        }
+        "macos" => paths.push(home.join("Library").join("Caches").join("ms-playwright")),
+        _ => {
+            let cache = std::env::var_os("XDG_CACHE_HOME")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".cache"));
+            paths.push(cache.join("ms-playwright"));
+        }
+    }
+    paths
+}
+
+/// The OpenClaw checkout: `OPENCLAW_PATH`, or `openclaw` in the home directory.
+pub fn openclaw_path() -> PathBuf {
+    std::env::var_os("OPENCLAW_PATH")
+        .filter(|p| !p.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_dir().join("openclaw"))
+}
+
+fn home_dir() -> PathBuf {
+    std::env::var_os("USERPROFILE")
+        .or_else(|| std::env::var_os("HOME"))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+fn install_hint(name: &str, os: &str) -> String {
+    match (name, os) {
+        ("node" | "npm", "windows") => "Install Node.js LTS: winget install OpenJS.NodeJS.LTS (npm is included)".to_string(),
+        ("node" | "npm", "macos") => "Install Node.js LTS: brew install node (npm is included)".to_string(),
+        ("node" | "npm", _) => "Install Node.js LTS from https://nodejs.org or your package manager (npm is included)".to_string(),
+        ("playwright", _) => "Run: npx playwright install chromium".to_string(),
+        ("ollama", "windows") => "Install Ollama: winget install Ollama.Ollama".to_string(),
+        ("ollama", "macos") => "Install Ollama: brew install ollama".to_string(),
+        ("ollama", _) => "Install Ollama: curl -fsSL https://ollama.com/install.sh | sh".to_string(),
+        ("ollama-server", _) => "Start Ollama: ollama serve".to_string(),
+        _ => format!("Install {}", name),
+    }
+}
+
+/// Runs a program and returns its trimmed stdout if it succeeded.
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    process::command(program)
+        .args(args)
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+}
+
+/// Finds the first `major.minor[.patch]` in tool output such as "v20.11.1"
+/// or "ollama version is 0.3.12".
+pub fn parse_version(text: &str) -> Option<(u64, u64, u64)> {
+    text.split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter(|word| word.contains('.'))
+        .find_map(|word| {
+            let mut parts = word.split('.').map(|p| p.parse::<u64>());
+            let major = parts.next()?.ok()?;
+            let minor = parts.next()?.ok()?;
+            let patch = parts.next().and_then(|p| p.ok()).unwrap_or(0);
+            Some((major, minor, patch))
+        })
+}
+
+fn format_version((major, minor, patch): (u64, u64, u64)) -> String {
+    format!("{}.{}.{}", major, minor, patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("v20.11.1"), Some((20, 11, 1)));
+        assert_eq!(parse_version("10.2.4\n"), Some((10, 2, 4)));
+        assert_eq!(parse_version("ollama version is 0.3.12"), Some((0, 3, 12)));
+        assert_eq!(parse_version("Warning: client version 0.1"), Some((0, 1, 0)));
+        assert_eq!(parse_version("command not found"), None);
+        assert!(parse_version("v16.20.2") < parse_version(MIN_NODE));
+    }
+
+    #[test]
+    fn test_playwright_paths_per_platform() {
+        let linux = playwright_paths("linux");
+        assert!(linux.last().unwrap().ends_with(".cache/ms-playwright") || std::env::var_os("XDG_CACHE_HOME").is_some());
+        assert!(playwright_paths("macos").last().unwrap().ends_with("Library/Caches/ms-playwright"));
+        assert!(playwright_paths("windows").last().unwrap().ends_with("ms-playwright"));
+    }
+
+    #[test]
+    fn test_install_hints() {
+        assert!(install_hint("node", "windows").contains("winget"));
+        assert!(install_hint("ollama", "linux").contains("install.sh"));
+        assert_eq!(install_hint("playwright", "macos"), "Run: npx playwright install chromium");
+    }
+}
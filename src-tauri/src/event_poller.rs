@@ -1,131 +1,603 @@
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::Duration;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use serde::Serialize;
 use crate::database::{Database, EventHandler};
+use crate::feed;
+use crate::settings;
+use crate::worker_pool::WorkerPool;
+
+struct PollerState {
+    running: bool,
+    // Set whenever handlers are added, removed or edited so the polling
+    // thread rebuilds its schedule instead of waiting for the next due time
+    handlers_changed: bool,
+    // Handlers with a check currently running on the worker pool
+    in_flight: HashSet<i64>,
+    // Finished checks waiting to be rescheduled by the polling thread
+    completed: Vec<CheckOutcome>,
+    // Published by the polling thread for get_status()
+    last_tick: Option<String>,
+    due_times: Vec<Instant>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PollerStatus {
+    pub running: bool,
+    pub last_tick: Option<String>,
+    pub handlers_scheduled: usize,
+    pub handlers_due: usize,
+    pub handlers_in_flight: usize,
+    pub next_due_in_seconds: Option<u64>,
+}
+
+/// Result of one handler check made by `poll_once`.
+#[derive(Debug, Serialize)]
+pub struct PollResult {
+    pub handler_id: i64,
+    pub handler_name: String,
+    pub events: usize,
+    pub error: Option<String>,
+    pub disabled: bool,
+}
+
+#[derive(Clone, Copy)]
+struct CheckOutcome {
+    handler_id: i64,
+    consecutive_failures: i64,
+    disabled: bool,
+}
+
+/// A check running on the worker pool. Dropping it hands the outcome back to
+/// the polling thread, even if the check panicked (e.g. in the event sink),
+/// so the handler leaves `in_flight` and is scheduled again.
+struct InFlightCheck {
+    state: Arc<(Mutex<PollerState>, Condvar)>,
+    outcome: CheckOutcome,
+}
+
+impl Drop for InFlightCheck {
+    fn drop(&mut self) {
+        let (lock, cvar) = &*self.state;
+        if let Ok(mut s) = lock.lock() {
+            s.in_flight.remove(&self.outcome.handler_id);
+            s.completed.push(self.outcome);
+            cvar.notify_all();
+        }
+    }
+}
+
+/// Something a handler noticed, e.g. a new RSS item. Delivered to the event
+/// sink so the UI (or an agent trigger) can react to it.
+#[derive(Debug, Clone, Serialize)]
+pub struct HandlerEvent {
+    pub handler_id: i64,
+    pub handler_name: String,
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    pub timestamp: String,
+}
+
+pub type EventSink = Arc<dyn Fn(HandlerEvent) + Send + Sync>;
 
 pub struct EventPoller {
     db: Arc<Mutex<Database>>,
-    running: Arc<Mutex<bool>>,
+    state: Arc<(Mutex<PollerState>, Condvar)>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    sink: Arc<Mutex<Option<EventSink>>>,
 }
 
 impl EventPoller {
     pub fn new(db: Arc<Mutex<Database>>) -> Self {
         EventPoller {
             db,
-            running: Arc::new(Mutex::new(false)),
+            state: Arc::new((
+                Mutex::new(PollerState {
+                    running: false,
+                    handlers_changed: false,
+                    in_flight: HashSet::new(),
+                    completed: Vec::new(),
+                    last_tick: None,
+                    due_times: Vec::new(),
+                }),
+                Condvar::new(),
+            )),
+            thread: Mutex::new(None),
+            sink: Arc::new(Mutex::new(None)),
         }
     }
 
-    pub fn start(&self) {
-        let running = Arc::clone(&self.running);
-        let db = Arc::clone(&self.db);
+    /// Sets where handler events are delivered. Events produced while no sink
+    /// is set are only logged.
+    pub fn set_event_sink(&self, sink: EventSink) {
+        *self.sink.lock().unwrap() = Some(sink);
+    }
 
-        // Set running flag
+    /// Starts the polling thread. Returns false if it is already running.
+    pub fn start(&self) -> bool {
+        let mut thread = self.thread.lock().unwrap();
+        let (lock, _) = &*self.state;
+
+        if lock.lock().unwrap().running {
+            return false;
+        }
+
+        // Reap a thread left over from an earlier stop before it can see the new flag
+        if let Some(old) = thread.take() {
+            let _ = old.join();
+        }
+
+        // Set running flag and force an initial schedule build
         {
-            let mut r = running.lock().unwrap();
-            *r = true;
+            let mut s = lock.lock().unwrap();
+            s.running = true;
+            s.handlers_changed = true;
         }
 
+        let state = Arc::clone(&self.state);
+        let db = Arc::clone(&self.db);
+        let sink = Arc::clone(&self.sink);
+
         // Spawn polling thread
-        thread::spawn(move || {
-            loop {
-                // Check if we should stop
-                {
-                    let r = running.lock().unwrap();
-                    if !*r {
-                        break;
-                    }
+        let handle = thread::Builder::new()
+            .name("event-poller".to_string())
+            .spawn(move || {
+                Self::run_loop(&db, &state, &sink);
+
+                // Make sure a thread that exits on its own is reported as stopped
+                let (lock, _) = &*state;
+                let mut s = lock.lock().unwrap();
+                s.running = false;
+                s.due_times.clear();
+            })
+            .expect("Failed to spawn event poller thread");
+
+        *thread = Some(handle);
+        true
+    }
+
+    /// Stops the polling thread and waits for it to exit. In-flight checks are
+    /// allowed to finish, which is bounded by the per-check timeout.
+    /// Returns false if the poller was not running.
+    pub fn stop(&self) -> bool {
+        let was_running = {
+            let (lock, cvar) = &*self.state;
+            let mut s = lock.lock().unwrap();
+            let was_running = s.running;
+            s.running = false;
+            cvar.notify_all();
+            was_running
+        };
+
+        let handle = self.thread.lock().unwrap().take();
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+        was_running
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.state.0.lock().unwrap().running
+    }
+
+    pub fn get_status(&self) -> PollerStatus {
+        let s = self.state.0.lock().unwrap();
+        let now = Instant::now();
+
+        PollerStatus {
+            running: s.running,
+            last_tick: s.last_tick.clone(),
+            handlers_scheduled: s.due_times.len(),
+            handlers_due: s.due_times.iter().filter(|due| **due <= now).count(),
+            handlers_in_flight: s.in_flight.len(),
+            next_due_in_seconds: s
+                .due_times
+                .iter()
+                .min()
+                .map(|due| due.saturating_duration_since(now).as_secs()),
+        }
+    }
+
+    /// Checks handlers once on the calling thread, without the background
+    /// loop: all active handlers if `include_not_due`, otherwise only those
+    /// whose interval has elapsed. Events go to the sink as usual. Used by
+    /// the CLI, e.g. from cron.
+    pub fn poll_once(&self, include_not_due: bool) -> Vec<PollResult> {
+        let app_settings = settings::load_shared(&self.db);
+        let client = match reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(app_settings.poller_check_timeout_seconds.max(1)))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("[EventPoller] Failed to create HTTP client: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let mut handlers: Vec<EventHandler> = Self::load_handlers(&self.db).into_values().collect();
+        handlers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        handlers
+            .into_iter()
+            .filter(|h| include_not_due || Self::time_until_due(h, app_settings.poller_max_backoff_seconds).is_zero())
+            .map(|handler| {
+                let result = Self::process_event_handler(&self.db, &client, &handler);
+                let events = result.as_ref().map(|events| events.len()).unwrap_or(0);
+                let error = result.as_ref().err().cloned();
+                let result = result.map(|events| Self::deliver_events(&self.sink, events));
+                let outcome = Self::record_outcome(&self.db, &handler, result, app_settings.poller_failure_threshold);
+
+                PollResult {
+                    handler_id: outcome.handler_id,
+                    handler_name: handler.name,
+                    events,
+                    error,
+                    disabled: outcome.disabled,
                 }
+            })
+            .collect()
+    }
+
+    /// Wakes the polling thread so it reloads handlers from the database.
+    /// Call this after creating, editing or deleting an event handler.
+    pub fn notify_handlers_changed(&self) {
+        let (lock, cvar) = &*self.state;
+        let mut s = lock.lock().unwrap();
+        s.handlers_changed = true;
+        cvar.notify_all();
+    }
+
+    fn run_loop(
+        db: &Arc<Mutex<Database>>,
+        state: &Arc<(Mutex<PollerState>, Condvar)>,
+        sink: &Arc<Mutex<Option<EventSink>>>,
+    ) {
+        let app_settings = settings::load_shared(db);
+        let pool = WorkerPool::new("event-poller", app_settings.poller_max_concurrency);
+        let client = match reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(app_settings.poller_check_timeout_seconds.max(1)))
+            .build()
+        {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("[EventPoller] Failed to create HTTP client: {}", e);
+                return;
+            }
+        };
+        let max_backoff = app_settings.poller_max_backoff_seconds;
+        let failure_threshold = app_settings.poller_failure_threshold;
 
-                // Get all active event handlers
-                let handlers = {
-                    let db_lock = db.lock().unwrap();
-                    match db_lock.get_all_event_handlers() {
-                        Ok(h) => h,
-                        Err(e) => {
-                            eprintln!("Error getting event handlers: {}", e);
-                            vec![]
+        let mut handlers: HashMap<i64, EventHandler> = HashMap::new();
+        // Min-heap of (next due time, handler id). A handler is taken off the
+        // queue while its check runs and put back when the check completes,
+        // so each handler has at most one check in flight.
+        let mut queue: BinaryHeap<Reverse<(Instant, i64)>> = BinaryHeap::new();
+
+        loop {
+            // Sleep until the next handler is due and a worker is free, a check
+            // completes, handlers change, or we are stopped
+            let (reload, completed) = {
+                let (lock, cvar) = &**state;
+                let mut s = lock.lock().unwrap();
+                loop {
+                    if !s.running {
+                        return;
+                    }
+                    if s.handlers_changed {
+                        s.handlers_changed = false;
+                        break (true, Vec::new());
+                    }
+                    if !s.completed.is_empty() {
+                        break (false, std::mem::take(&mut s.completed));
+                    }
+                    match queue.peek() {
+                        Some(Reverse((due, _))) if s.in_flight.len() < pool.size() => {
+                            let now = Instant::now();
+                            if *due <= now {
+                                break (false, Vec::new());
+                            }
+                            s = cvar.wait_timeout(s, *due - now).unwrap().0;
+                        }
+                        // Nothing scheduled, or every worker is busy: wait to be notified
+                        _ => {
+                            s = cvar.wait(s).unwrap();
                         }
                     }
+                }
+            };
+
+            if reload {
+                // Checks that completed before the reload are requeued from the
+                // fresh rows below, so their outcomes must not push them again
+                let in_flight = {
+                    let mut s = state.0.lock().unwrap();
+                    s.completed.clear();
+                    s.in_flight.clone()
                 };
+                handlers = Self::load_handlers(db);
+                queue = handlers
+                    .values()
+                    .filter_map(|h| h.id)
+                    .filter(|id| !in_flight.contains(id))
+                    .map(|id| Reverse((Instant::now() + Self::time_until_due(&handlers[&id], max_backoff), id)))
+                    .collect();
+                Self::publish_schedule(state, &queue, false);
+                continue;
+            }
 
-                // Process each handler
-                for handler in handlers {
-                    Self::process_event_handler(&db, &handler);
+            if !completed.is_empty() {
+                for outcome in completed {
+                    if outcome.disabled {
+                        handlers.remove(&outcome.handler_id);
+                        continue;
+                    }
+                    // The handler may have been deleted while its check was running
+                    if let Some(handler) = handlers.get_mut(&outcome.handler_id) {
+                        handler.consecutive_failures = outcome.consecutive_failures;
+                        let due = Instant::now() + Self::next_interval(handler, max_backoff);
+                        queue.push(Reverse((due, outcome.handler_id)));
+                    }
                 }
-
-                // Sleep for 10 seconds before next poll
-                thread::sleep(Duration::from_secs(10));
+                Self::publish_schedule(state, &queue, false);
+                continue;
             }
-        });
+
+            let Some(Reverse((_, id))) = queue.pop() else {
+                continue;
+            };
+
+            let Some(handler) = handlers.get(&id) else {
+                continue;
+            };
+
+            state.0.lock().unwrap().in_flight.insert(id);
+            Self::publish_schedule(state, &queue, true);
+
+            let db = Arc::clone(db);
+            let state = Arc::clone(state);
+            let sink = Arc::clone(sink);
+            let client = client.clone();
+            let handler = handler.clone();
+            pool.execute(move || {
+                // Counts as a failed check unless it gets to record its outcome
+                let mut check = InFlightCheck {
+                    state,
+                    outcome: CheckOutcome {
+                        handler_id: id,
+                        consecutive_failures: handler.consecutive_failures + 1,
+                        disabled: false,
+                    },
+                };
+                let result = Self::process_event_handler(&db, &client, &handler);
+                let result = result.map(|events| Self::deliver_events(&sink, events));
+                check.outcome = Self::record_outcome(&db, &handler, result, failure_threshold);
+            });
+        }
     }
 
-    pub fn stop(&self) {
-        let mut running = self.running.lock().unwrap();
-        *running = false;
+    fn publish_schedule(
+        state: &Arc<(Mutex<PollerState>, Condvar)>,
+        queue: &BinaryHeap<Reverse<(Instant, i64)>>,
+        ticked: bool,
+    ) {
+        let mut s = state.0.lock().unwrap();
+        s.due_times = queue.iter().map(|Reverse((due, _))| *due).collect();
+        if ticked {
+            s.last_tick = Some(chrono::Utc::now().to_rfc3339());
+        }
     }
 
-    fn process_event_handler(db: &Arc<Mutex<Database>>, handler: &EventHandler) {
-        // Check if enough time has passed since last check
-        let should_check = if let Some(ref last_check) = handler.last_check {
-            // Parse last check time
-            if let Ok(last_time) = chrono::DateTime::parse_from_rfc3339(last_check) {
-                let now = chrono::Utc::now();
-                let elapsed = now.signed_duration_since(last_time.with_timezone(&chrono::Utc));
-                elapsed.num_seconds() >= handler.interval_seconds
-            } else {
-                true // If we can't parse, check anyway
+    fn load_handlers(db: &Arc<Mutex<Database>>) -> HashMap<i64, EventHandler> {
+        let db_lock = db.lock().unwrap();
+        match db_lock.get_active_event_handlers() {
+            Ok(list) => list
+                .into_iter()
+                .filter_map(|h| h.id.map(|id| (id, h)))
+                .collect(),
+            Err(e) => {
+                eprintln!("Error getting event handlers: {}", e);
+                HashMap::new()
             }
-        } else {
-            true // First check
+        }
+    }
+
+    /// Time until the next check: the handler's interval, doubled for every
+    /// consecutive failure up to `max_backoff_seconds`.
+    fn next_interval(handler: &EventHandler, max_backoff_seconds: u64) -> Duration {
+        backoff_interval(handler.interval_seconds, handler.consecutive_failures, max_backoff_seconds)
+    }
+
+    /// How long until a freshly loaded handler is due (zero if it is due now),
+    /// based on its persisted last check time so restarts don't re-poll
+    /// everything at once.
+    fn time_until_due(handler: &EventHandler, max_backoff_seconds: u64) -> Duration {
+        let Some(ref last_check) = handler.last_check else {
+            return Duration::ZERO; // First check
         };
 
-        if !should_check {
-            return;
+        match chrono::DateTime::parse_from_rfc3339(last_check) {
+            Ok(last_time) => {
+                let elapsed = chrono::Utc::now()
+                    .signed_duration_since(last_time.with_timezone(&chrono::Utc))
+                    .to_std()
+                    .unwrap_or(Duration::ZERO);
+                Self::next_interval(handler, max_backoff_seconds).saturating_sub(elapsed)
+            }
+            Err(_) => Duration::ZERO, // If we can't parse, check anyway
         }
+    }
 
-        println!("[EventPoller] Checking event handler: {}", handler.name);
+    /// Persists the result of a check and reports the handler's new health.
+    fn record_outcome(
+        db: &Arc<Mutex<Database>>,
+        handler: &EventHandler,
+        result: Result<(), String>,
+        failure_threshold: i64,
+    ) -> CheckOutcome {
+        let id = handler.id.unwrap_or_default();
+        let db_lock = db.lock().unwrap();
 
-        // Process based on event type
-        match handler.event_type.as_str() {
-            "polling" => {
-                if let Some(ref url) = handler.url {
-                    Self::check_url(url);
+        match result {
+            Ok(()) => {
+                if let Err(e) = db_lock.record_event_handler_success(id) {
+                    eprintln!("Error updating last check: {}", e);
                 }
+                CheckOutcome { handler_id: id, consecutive_failures: 0, disabled: false }
             }
-            "web" => {
-                if let Some(ref url) = handler.url {
-                    Self::check_web_event(url);
+            Err(error) => {
+                eprintln!("[EventPoller] {} failed: {}", handler.name, error);
+                match db_lock.record_event_handler_failure(id, &error, failure_threshold) {
+                    Ok((consecutive_failures, disabled)) => {
+                        if disabled {
+                            eprintln!(
+                                "[EventPoller] Disabled {} after {} consecutive failures",
+                                handler.name, consecutive_failures
+                            );
+                        }
+                        CheckOutcome { handler_id: id, consecutive_failures, disabled }
+                    }
+                    Err(e) => {
+                        eprintln!("Error recording handler failure: {}", e);
+                        CheckOutcome {
+                            handler_id: id,
+                            consecutive_failures: handler.consecutive_failures + 1,
+                            disabled: false,
+                        }
+                    }
                 }
             }
-            "periodic" => {
-                println!("[EventPoller] Periodic check for: {}", handler.name);
-                // Trigger periodic action
-            }
-            _ => {
-                eprintln!("Unknown event type: {}", handler.event_type);
+        }
+    }
+
+    fn deliver_events(sink: &Arc<Mutex<Option<EventSink>>>, events: Vec<HandlerEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        let sink = sink.lock().unwrap().clone();
+        for event in events {
+            println!("[EventPoller] {} event from {}", event.event_type, event.handler_name);
+            if let Some(ref sink) = sink {
+                sink(event);
             }
         }
+    }
+
+    fn process_event_handler(
+        db: &Arc<Mutex<Database>>,
+        client: &reqwest::blocking::Client,
+        handler: &EventHandler,
+    ) -> Result<Vec<HandlerEvent>, String> {
+        println!("[EventPoller] Checking event handler: {}", handler.name);
 
-        // Update last check time
-        if let Some(id) = handler.id {
-            let db_lock = db.lock().unwrap();
-            if let Err(e) = db_lock.update_event_handler_last_check(id) {
-                eprintln!("Error updating last check: {}", e);
+        // Process based on event type
+        match handler.event_type.as_str() {
+            "polling" => match handler.url {
+                Some(ref url) => Self::check_url(client, url).map(|_| vec![]),
+                None => Err("No URL configured".to_string()),
+            },
+            "web" => match handler.url {
+                Some(ref url) => Self::check_web_event(client, url).map(|_| vec![]),
+                None => Err("No URL configured".to_string()),
+            },
+            "rss" => match handler.url {
+                Some(ref url) => Self::check_feed(db, client, handler, url),
+                None => Err("No URL configured".to_string()),
+            },
+            "periodic" => {
+                println!("[EventPoller] Periodic check for: {}", handler.name);
+                // Trigger periodic action
+                Ok(vec![])
             }
+            _ => Err(format!("Unknown event type: {}", handler.event_type)),
         }
     }
 
-    fn check_url(url: &str) {
-        // Simple HTTP check (could be enhanced with reqwest crate)
+    fn check_url(client: &reqwest::blocking::Client, url: &str) -> Result<(), String> {
         println!("[EventPoller] Polling URL: {}", url);
-        // For now, just log. In production, use reqwest to make HTTP request
+        let response = client
+            .get(url)
+            .send()
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        Ok(())
     }
 
-    fn check_web_event(url: &str) {
+    fn check_web_event(client: &reqwest::blocking::Client, url: &str) -> Result<(), String> {
         // Check for web events
         println!("[EventPoller] Checking web event at: {}", url);
-        // Could fetch and parse HTML/JSON for events
+        let response = client
+            .get(url)
+            .send()
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        // Could parse HTML/JSON body for events
+        Ok(())
+    }
+
+    /// Fetches an RSS/Atom feed and returns one event per item not seen before.
+    /// The first successful fetch only records existing items unless the
+    /// handler's config sets `"emit_existing": true`.
+    fn check_feed(
+        db: &Arc<Mutex<Database>>,
+        client: &reqwest::blocking::Client,
+        handler: &EventHandler,
+        url: &str,
+    ) -> Result<Vec<HandlerEvent>, String> {
+        let handler_id = handler.id.ok_or("Handler has no id")?;
+        println!("[EventPoller] Fetching feed: {}", url);
+
+        let response = client
+            .get(url)
+            .send()
+            .map_err(|e| format!("Request failed: {}", e))?;
+
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+
+        let body = response
+            .bytes()
+            .map_err(|e| format!("Failed to read feed: {}", e))?;
+        let items = feed::parse_feed(&body)?;
+
+        let emit_existing = serde_json::from_str::<serde_json::Value>(&handler.config_json)
+            .ok()
+            .and_then(|c| c.get("emit_existing").and_then(|v| v.as_bool()))
+            .unwrap_or(false);
+
+        let db_lock = db.lock().unwrap();
+        let first_fetch = !db_lock
+            .has_seen_feed_items(handler_id)
+            .map_err(|e| format!("Failed to read seen items: {}", e))?;
+
+        let mut events = Vec::new();
+        // Oldest first, so events fire in publication order
+        for item in items.into_iter().rev() {
+            let is_new = db_lock
+                .mark_feed_item_seen(handler_id, &item.guid)
+                .map_err(|e| format!("Failed to record feed item: {}", e))?;
+
+            if is_new && (!first_fetch || emit_existing) {
+                events.push(HandlerEvent {
+                    handler_id,
+                    handler_name: handler.name.clone(),
+                    event_type: "rss_item".to_string(),
+                    payload: serde_json::json!(item),
+                    timestamp: chrono::Utc::now().to_rfc3339(),
+                });
+            }
+        }
+
+        Ok(events)
     }
 }
 
@@ -134,3 +606,205 @@ impl Drop for EventPoller {
         self.stop();
     }
 }
+
+/// Exponential backoff for a handler with `consecutive_failures` failed checks
+/// in a row. The cap never shortens a handler's own interval.
+pub fn backoff_interval(interval_seconds: i64, consecutive_failures: i64, max_backoff_seconds: u64) -> Duration {
+    let base = interval_seconds.max(1) as u64;
+    let factor = 1u64 << consecutive_failures.clamp(0, 32);
+    Duration::from_secs(base.saturating_mul(factor).min(max_backoff_seconds.max(base)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockServer;
+
+    fn handler(name: &str, event_type: &str, url: Option<String>, interval_seconds: i64) -> EventHandler {
+        EventHandler {
+            id: None,
+            name: name.to_string(),
+            event_type: event_type.to_string(),
+            url,
+            interval_seconds,
+            last_check: None,
+            is_active: true,
+            config_json: "{}".to_string(),
+            consecutive_failures: 0,
+            last_success: None,
+            last_error: None,
+        }
+    }
+
+    fn poller(handlers: &[EventHandler]) -> EventPoller {
+        let db = Database::in_memory().unwrap();
+        for handler in handlers {
+            db.create_event_handler(handler).unwrap();
+        }
+        EventPoller::new(Arc::new(Mutex::new(db)))
+    }
+
+    #[test]
+    fn test_poll_once_checks_only_due_handlers() {
+        let never_checked = handler("never checked", "periodic", None, 3600);
+        let recent = EventHandler {
+            last_check: Some(chrono::Utc::now().to_rfc3339()),
+            ..handler("checked just now", "periodic", None, 3600)
+        };
+        let poller = poller(&[never_checked, recent]);
+
+        let checked: Vec<String> = poller.poll_once(false).into_iter().map(|r| r.handler_name).collect();
+        assert_eq!(checked, ["never checked"]);
+        assert_eq!(poller.poll_once(true).len(), 2);
+    }
+
+    fn rss(guids: &[&str]) -> String {
+        let items: String = guids
+            .iter()
+            .map(|guid| format!("<item><guid>{}</guid><title>Post {}</title></item>", guid, guid))
+            .collect();
+        format!(r#"<?xml version="1.0"?><rss version="2.0"><channel><title>OpenClaw</title>{}</channel></rss>"#, items)
+    }
+
+    fn collect_events(poller: &EventPoller) -> Arc<Mutex<Vec<HandlerEvent>>> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink_events = Arc::clone(&events);
+        poller.set_event_sink(Arc::new(move |event| sink_events.lock().unwrap().push(event)));
+        events
+    }
+
+    fn guids(events: &Mutex<Vec<HandlerEvent>>) -> Vec<String> {
+        events.lock().unwrap().drain(..).map(|e| e.payload["guid"].as_str().unwrap().to_string()).collect()
+    }
+
+    #[test]
+    fn test_feed_emits_only_new_items() {
+        // Feeds list the newest item first
+        let server = MockServer::start(vec![
+            (200, "application/rss+xml", rss(&["post-2", "post-1"])),
+            (200, "application/rss+xml", rss(&["post-4", "post-3", "post-2", "post-1"])),
+        ]);
+        let poller = poller(&[handler("feed", "rss", Some(server.url.clone()), 60)]);
+        let events = collect_events(&poller);
+
+        // The first fetch only records what is already there
+        assert_eq!(poller.poll_once(true)[0].events, 0);
+        assert!(guids(&events).is_empty());
+
+        let results = poller.poll_once(true);
+        assert_eq!(results[0].events, 2);
+        assert_eq!(guids(&events), ["post-3", "post-4"], "oldest first");
+    }
+
+    #[test]
+    fn test_feed_emit_existing() {
+        let server = MockServer::start(vec![
+            (200, "application/rss+xml", rss(&["post-2", "post-1"])),
+            (200, "application/rss+xml", rss(&["post-2", "post-1"])),
+        ]);
+        let feed = EventHandler {
+            config_json: r#"{"emit_existing": true}"#.to_string(),
+            ..handler("feed", "rss", Some(server.url.clone()), 60)
+        };
+        let poller = poller(&[feed]);
+        let events = collect_events(&poller);
+
+        poller.poll_once(true);
+        assert_eq!(guids(&events), ["post-1", "post-2"]);
+        let results = poller.poll_once(true);
+        assert_eq!(results[0].events, 0, "seen items don't repeat");
+        assert!(results[0].error.is_none());
+    }
+
+    #[test]
+    fn test_panicking_sink_requeues_handler() {
+        let server = MockServer::start(vec![
+            (200, "application/rss+xml", rss(&["post-1"])),
+            (200, "application/rss+xml", rss(&["post-1"])),
+        ]);
+        let feed = EventHandler {
+            config_json: r#"{"emit_existing": true}"#.to_string(),
+            ..handler("feed", "rss", Some(server.url.clone()), 1)
+        };
+        let poller = poller(&[feed]);
+        poller.set_event_sink(Arc::new(|_| panic!("sink failed")));
+
+        poller.start();
+        server.request();
+        // Checked again once its interval passes, though the first check panicked
+        server.request();
+        poller.stop();
+        assert_eq!(poller.get_status().handlers_in_flight, 0);
+    }
+
+    #[derive(Default)]
+    struct PathStats {
+        checks: usize,
+        active: usize,
+        max_active: usize,
+    }
+
+    // Answers every request after `delay`, on a thread per connection, and
+    // counts checks per path along with how many ever overlapped
+    fn counting_server(delay: Duration) -> (String, Arc<Mutex<HashMap<String, PathStats>>>) {
+        use std::io::{BufRead, BufReader, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let stats: Arc<Mutex<HashMap<String, PathStats>>> = Arc::default();
+        let server_stats = Arc::clone(&stats);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let stats = Arc::clone(&server_stats);
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream);
+                    let mut request_line = String::new();
+                    let _ = reader.read_line(&mut request_line);
+                    let path = request_line.split_whitespace().nth(1).unwrap_or_default().to_string();
+                    {
+                        let mut stats = stats.lock().unwrap();
+                        let entry = stats.entry(path.clone()).or_default();
+                        entry.checks += 1;
+                        entry.active += 1;
+                        entry.max_active = entry.max_active.max(entry.active);
+                    }
+                    thread::sleep(delay);
+                    stats.lock().unwrap().get_mut(&path).unwrap().active -= 1;
+                    let mut stream = reader.into_inner();
+                    let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok");
+                });
+            }
+        });
+        (url, stats)
+    }
+
+    #[test]
+    fn test_run_loop_schedules_by_interval() {
+        let (url, stats) = counting_server(Duration::from_millis(300));
+        let poller = poller(&[
+            handler("fast", "polling", Some(format!("{}/fast", url)), 1),
+            handler("slow", "polling", Some(format!("{}/slow", url)), 60),
+        ]);
+
+        poller.start();
+        // A reload while both first checks are in flight must not queue them twice
+        thread::sleep(Duration::from_millis(150));
+        poller.notify_handlers_changed();
+        thread::sleep(Duration::from_millis(3000));
+        poller.stop();
+
+        let stats = stats.lock().unwrap();
+        assert!(stats["/fast"].checks >= 2, "fast checked {} times", stats["/fast"].checks);
+        assert_eq!(stats["/slow"].checks, 1);
+        assert_eq!(stats["/fast"].max_active, 1);
+        assert_eq!(stats["/slow"].max_active, 1);
+    }
+
+    #[test]
+    fn test_backoff_interval() {
+        assert_eq!(backoff_interval(5, 0, 3600), Duration::from_secs(5));
+        assert_eq!(backoff_interval(5, 3, 3600), Duration::from_secs(40));
+        assert_eq!(backoff_interval(60, 10, 3600), Duration::from_secs(3600));
+        assert_eq!(backoff_interval(7200, 2, 3600), Duration::from_secs(7200));
+        assert_eq!(backoff_interval(1, 500, 600), Duration::from_secs(600));
+    }
+}
@@ -0,0 +1,82 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedItem {
+    pub guid: String,
+    pub title: Option<String>,
+    pub link: Option<String>,
+    pub published: Option<String>,
+}
+
+/// Parses an RSS or Atom document into its items, newest-first as published.
+pub fn parse_feed(body: &[u8]) -> Result<Vec<FeedItem>, String> {
+    let feed = feed_rs::parser::parse(body)
+        .map_err(|e| format!("Failed to parse feed: {}", e))?;
+
+    let items = feed
+        .entries
+        .into_iter()
+        .map(|entry| FeedItem {
+            guid: entry.id,
+            title: entry.title.map(|t| t.content.trim().to_string()),
+            link: entry.links.into_iter().next().map(|l| l.href),
+            published: entry.published.or(entry.updated).map(|d| d.to_rfc3339()),
+        })
+        .collect();
+
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rss() {
+        let rss = r#"<?xml version="1.0"?>
+            <rss version="2.0"><channel><title>OpenClaw</title>
+              <item>
+                <guid>post-2</guid>
+                <title>Second post</title>
+                <link>https://example.com/2</link>
+                <pubDate>Tue, 02 Jan 2024 10:00:00 GMT</pubDate>
+              </item>
+              <item><guid>post-1</guid><title>First post</title><link>https://example.com/1</link></item>
+            </channel></rss>"#;
+
+        let items = parse_feed(rss.as_bytes()).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].guid, "post-2");
+        assert_eq!(items[0].title.as_deref(), Some("Second post"));
+        assert_eq!(items[0].link.as_deref(), Some("https://example.com/2"));
+        assert_eq!(items[0].published.as_deref(), Some("2024-01-02T10:00:00+00:00"));
+        assert!(items[1].published.is_none());
+    }
+
+    #[test]
+    fn test_parse_atom() {
+        let atom = r#"<?xml version="1.0" encoding="utf-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom">
+              <title>Releases</title>
+              <id>urn:feed</id>
+              <updated>2024-03-01T00:00:00Z</updated>
+              <entry>
+                <id>urn:release:1</id>
+                <title>v1.0</title>
+                <link href="https://example.com/v1"/>
+                <updated>2024-03-01T00:00:00Z</updated>
+              </entry>
+            </feed>"#;
+
+        let items = parse_feed(atom.as_bytes()).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].guid, "urn:release:1");
+        assert_eq!(items[0].link.as_deref(), Some("https://example.com/v1"));
+        assert_eq!(items[0].published.as_deref(), Some("2024-03-01T00:00:00+00:00"));
+    }
+
+    #[test]
+    fn test_parse_garbage() {
+        assert!(parse_feed(b"not a feed").is_err());
+    }
+}
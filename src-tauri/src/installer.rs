@@ -0,0 +1,430 @@
+use serde::Serialize;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::process::{Child, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::database::{Database, InstallLogEntry};
+use crate::dependencies::DependencyReport;
+use crate::process;
+use crate::settings::{project_dir, AppSettings};
+
+// Output lines kept per step in the install log
+const LOG_TAIL_LINES: usize = 50;
+
+/// One command the installer runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallStep {
+    pub id: String, // "npm-install", "playwright", "ollama-pull"
+    pub label: String,
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallEvent {
+    pub run_id: String,
+    pub step_id: String,
+    pub step_index: usize,
+    pub step_count: usize,
+    pub status: String, // "started", "output", "succeeded", "failed", "cancelled", "done"
+    pub line: Option<String>,
+    pub exit_code: Option<i32>,
+}
+
+pub type InstallSink = Arc<dyn Fn(InstallEvent) + Send + Sync>;
+
+/// Works out which steps fix what the dependency report says is missing.
+/// `ollama pull` runs for the configured chat model when it is local and for
+/// the embedding model, both only when the Ollama server is up.
+pub fn plan(report: &DependencyReport, settings: &AppSettings) -> Vec<InstallStep> {
+    let missing = |name: &str| report.dependencies.iter().any(|d| d.name == name && !d.ok());
+    let present = |name: &str| report.dependencies.iter().any(|d| d.name == name && d.ok());
+    let mut steps = Vec::new();
+
+    if present("npm") {
+        steps.push(step("npm-install", "Install Node packages", "npm", &["install"], Some(project_dir())));
+        if missing("playwright") {
+            steps.push(step(
+                "playwright",
+                "Download Playwright Chromium",
+                "npx",
+                &["playwright", "install", "chromium"],
+                Some(project_dir()),
+            ));
+        }
+    }
+
+    if present("ollama-server") {
+        let mut models = Vec::new();
+        if matches!(settings.llm_provider.as_str(), "local" | "ollama") {
+            models.push(settings.llm_model.clone());
+        }
+        models.push(settings.llm_embedding_model.clone());
+        models.dedup();
+
+        for model in models {
+            steps.push(step(
+                &format!("ollama-pull:{}", model),
+                &format!("Download the {} model", model),
+                "ollama",
+                &["pull", &model],
+                None,
+            ));
+        }
+    }
+
+    steps
+}
+
+/// Runs install steps one at a time as child processes, reporting progress
+/// through the sink. Only one run can be active; `cancel` kills the running
+/// step and skips the rest.
+pub struct Installer {
+    db: Arc<Mutex<Database>>,
+    cancel: Arc<AtomicBool>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Installer {
+    pub fn new(db: Arc<Mutex<Database>>) -> Self {
+        Installer {
+            db,
+            cancel: Arc::new(AtomicBool::new(false)),
+            thread: Mutex::new(None),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.thread.lock().unwrap().as_ref().is_some_and(|t| !t.is_finished())
+    }
+
+    /// Starts running `steps` in the background and returns the run id.
+    pub fn start(&self, steps: Vec<InstallStep>, sink: InstallSink) -> Result<String, String> {
+        let mut thread = self.thread.lock().unwrap();
+        if thread.as_ref().is_some_and(|t| !t.is_finished()) {
+            return Err("An installation is already running".to_string());
+        }
+        if steps.is_empty() {
+            return Err("Nothing to install".to_string());
+        }
+
+        let run_id = chrono::Utc::now().format("%Y%m%d%H%M%S%3f").to_string();
+        self.cancel.store(false, Ordering::SeqCst);
+
+        let db = Arc::clone(&self.db);
+        let cancel = Arc::clone(&self.cancel);
+        let id = run_id.clone();
+        *thread = Some(
+            thread::Builder::new()
+                .name("installer".to_string())
+                .spawn(move || run_steps(&id, &steps, &db, &cancel, &sink))
+                .map_err(|e| format!("Failed to start installer: {}", e))?,
+        );
+
+        println!("[Installer] Started run {}", run_id);
+        Ok(run_id)
+    }
+
+    pub fn cancel(&self) -> bool {
+        if !self.is_running() {
+            return false;
+        }
+        self.cancel.store(true, Ordering::SeqCst);
+        true
+    }
+}
+
+fn run_steps(run_id: &str, steps: &[InstallStep], db: &Arc<Mutex<Database>>, cancel: &AtomicBool, sink: &InstallSink) {
+    let event = |index: usize, status: &str, line: Option<String>, exit_code: Option<i32>| InstallEvent {
+        run_id: run_id.to_string(),
+        step_id: steps.get(index).map(|s| s.id.clone()).unwrap_or_default(),
+        step_index: index,
+        step_count: steps.len(),
+        status: status.to_string(),
+        line,
+        exit_code,
+    };
+
+    for (index, install_step) in steps.iter().enumerate() {
+        if cancel.load(Ordering::SeqCst) {
+            sink(event(index, "cancelled", None, None));
+            record(db, run_id, install_step, "cancelled", None, &[], &chrono::Utc::now().to_rfc3339());
+            continue;
+        }
+
+        let started_at = chrono::Utc::now().to_rfc3339();
+        sink(event(index, "started", Some(command_line(install_step)), None));
+
+        let mut tail: Vec<String> = Vec::new();
+        let result = run_step(install_step, cancel, |line| {
+            if tail.len() == LOG_TAIL_LINES {
+                tail.remove(0);
+            }
+            tail.push(line.clone());
+            sink(event(index, "output", Some(line), None));
+        });
+
+        let (status, exit_code) = match result {
+            Ok(0) => ("succeeded", Some(0)),
+            Ok(code) => ("failed", Some(code)),
+            Err(StepError::Cancelled) => ("cancelled", None),
+            Err(StepError::Spawn(e)) => {
+                tail.push(e);
+                ("failed", None)
+            }
+        };
+        println!("[Installer] {} {}", install_step.id, status);
+        sink(event(index, status, tail.last().cloned().filter(|_| status == "failed"), exit_code));
+        record(db, run_id, install_step, status, exit_code, &tail, &started_at);
+
+        // Later steps usually depend on earlier ones (npx needs npm install)
+        if status == "failed" {
+            cancel.store(true, Ordering::SeqCst);
+        }
+    }
+
+    sink(event(steps.len(), "done", None, None));
+}
+
+enum StepError {
+    Spawn(String),
+    Cancelled,
+}
+
+/// Runs one step, handing each line of stdout and stderr to `on_line`.
+/// Returns the exit code (-1 if killed by a signal).
+fn run_step(install_step: &InstallStep, cancel: &AtomicBool, mut on_line: impl FnMut(String)) -> Result<i32, StepError> {
+    let mut command = process::command(&install_step.program);
+    command
+        .args(&install_step.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(ref cwd) = install_step.cwd {
+        command.current_dir(cwd);
+    }
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| StepError::Spawn(format!("Failed to start {}: {}", install_step.program, e)))?;
+
+    // The readers aren't joined: a process that escaped a cancel, or one the
+    // step started in the background (e.g. `ollama serve`), can hold the pipes
+    // open indefinitely
+    let (tx, rx) = mpsc::channel();
+    for stream in [
+        child.stdout.take().map(|s| Box::new(s) as Box<dyn Read + Send>),
+        child.stderr.take().map(|s| Box::new(s) as Box<dyn Read + Send>),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        let tx = tx.clone();
+        thread::spawn(move || forward_lines(stream, tx));
+    }
+    drop(tx);
+
+    // Once the step exits, its output is read until both pipes close or for
+    // OUTPUT_GRACE, whichever comes first
+    let mut exited_at = None;
+    let status = loop {
+        if cancel.load(Ordering::SeqCst) {
+            kill(&mut child);
+            break None;
+        }
+        match exited_at {
+            None => match child.try_wait() {
+                Ok(Some(_)) => exited_at = Some(Instant::now()),
+                Ok(None) => {}
+                Err(_) => break child.wait().ok(),
+            },
+            Some(at) if at.elapsed() >= process::OUTPUT_GRACE => break child.wait().ok(),
+            Some(_) => {}
+        }
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(line) => on_line(line),
+            // Both pipes closed: the process is exiting
+            Err(mpsc::RecvTimeoutError::Disconnected) => break child.wait().ok(),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+        }
+    };
+
+    match status {
+        Some(status) => Ok(status.code().unwrap_or(-1)),
+        None => Err(StepError::Cancelled),
+    }
+}
+
+/// Splits on `\r` as well as `\n`, since npm and ollama redraw progress bars
+/// with carriage returns.
+fn forward_lines(stream: Box<dyn Read + Send>, tx: mpsc::Sender<String>) {
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(_) => {
+                for part in String::from_utf8_lossy(&buf).split('\r') {
+                    let line = part.trim();
+                    if !line.is_empty() && tx.send(line.to_string()).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Stops the step along with what it started: npm's node, npx's Playwright
+/// download, `cmd` on Windows and the program under it.
+fn kill(child: &mut Child) {
+    if let Err(e) = process::kill_tree(child) {
+        eprintln!("[Installer] Failed to stop process: {}", e);
+    }
+}
+
+fn record(
+    db: &Arc<Mutex<Database>>,
+    run_id: &str,
+    install_step: &InstallStep,
+    status: &str,
+    exit_code: Option<i32>,
+    output: &[String],
+    started_at: &str,
+) {
+    let entry = InstallLogEntry {
+        id: None,
+        run_id: run_id.to_string(),
+        step_id: install_step.id.clone(),
+        command: command_line(install_step),
+        status: status.to_string(),
+        exit_code,
+        output: output.join("\n"),
+        started_at: started_at.to_string(),
+        finished_at: chrono::Utc::now().to_rfc3339(),
+    };
+    match db.lock() {
+        Ok(db) => {
+            if let Err(e) = db.add_install_log_entry(&entry) {
+                eprintln!("[Installer] Failed to write install log: {}", e);
+            }
+        }
+        Err(e) => eprintln!("[Installer] Failed to write install log: {}", e),
+    }
+}
+
+fn command_line(install_step: &InstallStep) -> String {
+    std::iter::once(install_step.program.as_str())
+        .chain(install_step.args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn step(id: &str, label: &str, program: &str, args: &[&str], cwd: Option<PathBuf>) -> InstallStep {
+    InstallStep {
+        id: id.to_string(),
+        label: label.to_string(),
+        program: program.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        cwd,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependencies::DependencyStatus;
+
+    fn status(name: &str, installed: bool) -> DependencyStatus {
+        DependencyStatus {
+            name: name.to_string(),
+            installed,
+            version: None,
+            min_version: None,
+            meets_minimum: true,
+            path: None,
+            remediation: None,
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let report = DependencyReport {
+            os: "linux".to_string(),
+            ready: false,
+            dependencies: vec![status("npm", true), status("playwright", false), status("ollama-server", true)],
+        };
+
+        let ids: Vec<String> = plan(&report, &AppSettings::defaults()).into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec!["npm-install", "playwright", "ollama-pull:phi3", "ollama-pull:nomic-embed-text"]);
+
+        // Without npm or a running Ollama there is nothing we can run
+        let report = DependencyReport {
+            os: "linux".to_string(),
+            ready: false,
+            dependencies: vec![status("npm", false), status("playwright", false), status("ollama-server", false)],
+        };
+        assert!(plan(&report, &AppSettings::defaults()).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_step_streams_output() {
+        let install_step = step("echo", "Echo", "sh", &["-c", "echo one; printf 'two\\rthree\\n' >&2; exit 3"], None);
+        let mut lines = Vec::new();
+        let code = run_step(&install_step, &AtomicBool::new(false), |line| lines.push(line)).ok();
+
+        assert_eq!(code, Some(3));
+        lines.sort();
+        assert_eq!(lines, vec!["one", "three", "two"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_step_cancel() {
+        let install_step = step("sleep", "Sleep", "sleep", &["30"], None);
+        let cancel = AtomicBool::new(true);
+        assert!(matches!(run_step(&install_step, &cancel, |_| {}), Err(StepError::Cancelled)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_step_cancel_stops_child_processes() {
+        // Like npm running node: the background sleep holds the pipes too
+        let install_step = step("sh", "Shell", "sh", &["-c", "sleep 30 & sleep 30"], None);
+        let cancel = AtomicBool::new(false);
+        let started = std::time::Instant::now();
+        let result = thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(300));
+                cancel.store(true, Ordering::SeqCst);
+            });
+            run_step(&install_step, &cancel, |_| {})
+        });
+
+        assert!(matches!(result, Err(StepError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_step_ends_when_step_exits_before_its_pipes_close() {
+        // Like a step that starts a server: the background sleep outlives the
+        // step and keeps its stdout open
+        let install_step = step("sh", "Shell", "sh", &["-c", "sleep 30 & echo started"], None);
+        let started = Instant::now();
+        let mut lines = Vec::new();
+        let code = run_step(&install_step, &AtomicBool::new(false), |line| lines.push(line)).ok();
+
+        assert_eq!(code, Some(0));
+        assert_eq!(lines, vec!["started"]);
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+}
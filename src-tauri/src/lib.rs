@@ -1,27 +1,189 @@
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
-#[tauri::command]
-fn greet(name: &str) -> String {
-    format!("Hello, {}! You've been greeted from Rust!", name)
+//! Agent core shared by the desktop app and the headless CLI
+//! (bin/personaliz.rs): database, settings, LLM providers, the event poller,
+//! agent execution and scheduling, and the daemon that hosts them. `configure`
+//! assembles the desktop app itself; main.rs and the mobile entry point run it.
+
+pub mod agent_builder;
+pub mod agent_runner;
+pub mod backup;
+pub mod bundle;
+pub mod channels;
+mod commands;
+pub mod daemon;
+pub mod database;
+pub mod dependencies;
+pub mod event_poller;
+pub mod feed;
+pub mod installer;
+pub mod llm;
+pub mod memory;
+pub mod notifications;
+pub mod process;
+pub mod prompt_template;
+pub mod scheduler;
+pub mod secrets;
+pub mod settings;
+pub mod worker_pool;
+#[cfg(test)]
+mod test_support;
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager, Runtime};
+
+use daemon::DaemonClient;
+use database::Database;
+use event_poller::EventPoller;
+use secrets::SecretStore;
+
+/// What the commands reach beyond the database: the daemon, the project
+/// directory with the agent scripts, the backup directory and the secret
+/// store. Tests swap in a disabled daemon, temp directories and a file store.
+#[derive(Clone)]
+pub struct AppEnv {
+    pub daemon: DaemonClient,
+    pub project_dir: PathBuf,
+    pub backup_dir: PathBuf,
+    pub secrets: SecretStore,
 }
 
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
+impl AppEnv {
+    pub fn system() -> Self {
+        AppEnv {
+            daemon: DaemonClient::socket(),
+            project_dir: settings::project_dir(),
+            backup_dir: backup::backup_dir(),
+            secrets: SecretStore::open_default(),
+        }
+    }
+}
+
+/// Adds the app's state, setup and every command to `builder`. Generic over
+/// the runtime so tests can pass `tauri::test::mock_builder()`.
+pub fn configure<R: Runtime>(builder: tauri::Builder<R>, db: Database, env: AppEnv) -> tauri::Builder<R> {
+    let db = Arc::new(Mutex::new(db));
+    let daemon = env.daemon;
+
+    // Initialize event poller; it only starts on launch if the user opted in,
+    // otherwise it is started with the start_event_poller command
+    let event_poller = Arc::new(EventPoller::new(Arc::clone(&db)));
+    let poller_for_setup = Arc::clone(&event_poller);
+    let db_for_setup = Arc::clone(&db);
+    let installer = Arc::new(installer::Installer::new(Arc::clone(&db)));
+
+    builder
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![run_command])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .manage(db)
+        .manage(event_poller)
+        .manage(installer)
+        .manage(Arc::new(llm::StreamRegistry::default()))
+        .manage(env)
+        .setup(move |app| {
+            // Forward handler events (e.g. new RSS items) to the frontend
+            let handle = app.handle().clone();
+            let emitter = handle.clone();
+            poller_for_setup.set_event_sink(Arc::new(move |event: event_poller::HandlerEvent| {
+                if let Err(e) = emitter.emit("handler-event", &event) {
+                    eprintln!("Failed to emit handler event: {}", e);
+                }
+            }));
+
+            // A running daemon already owns polling and scheduling
+            if daemon.is_running() {
+                println!("[Daemon] Connected to running daemon");
+                commands::forward_daemon_events(handle, daemon);
+            } else if settings::load_shared(&db_for_setup).poller_auto_start {
+                poller_for_setup.start();
+            }
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::run_command,
+            commands::create_agent_file,
+            commands::save_settings,
+            commands::load_settings,
+            commands::get_setting_entries,
+            commands::set_setting,
+            commands::update_settings,
+            commands::has_api_key,
+            commands::clear_api_key,
+            commands::save_provider_api_key,
+            commands::save_llm_fallback_providers,
+            commands::llm_probe_providers,
+            commands::llm_complete,
+            commands::llm_chat,
+            commands::llm_stream,
+            commands::llm_cancel,
+            commands::get_llm_usage,
+            commands::save_llm_budget,
+            commands::save_llm_prices,
+            commands::draft_agent,
+            commands::check_dependencies,
+            commands::get_install_plan,
+            commands::start_install,
+            commands::cancel_install,
+            commands::get_install_log,
+            commands::db_create_agent,
+            commands::db_get_all_agents,
+            commands::db_get_agent_by_name,
+            commands::export_agents,
+            commands::preview_agent_bundle,
+            commands::import_agent_bundle,
+            commands::db_log_agent_event,
+            commands::db_get_agent_logs,
+            commands::get_notification_history,
+            commands::clear_notification_history,
+            commands::get_notification_deliveries,
+            commands::test_notification_channel,
+            commands::save_channel_password,
+            commands::save_channel_header,
+            commands::backup_database,
+            commands::list_backups,
+            commands::restore_database,
+            commands::db_create_event_handler,
+            commands::db_get_all_event_handlers,
+            commands::db_update_event_handler,
+            commands::db_delete_event_handler,
+            commands::db_create_prompt_template,
+            commands::db_get_all_prompt_templates,
+            commands::db_update_prompt_template,
+            commands::db_get_prompt_template_versions,
+            commands::db_rollback_prompt_template,
+            commands::db_delete_prompt_template,
+            commands::render_prompt_template,
+            commands::memory_add,
+            commands::memory_query,
+            commands::memory_list,
+            commands::memory_forget,
+            commands::memory_clear,
+            commands::start_event_poller,
+            commands::stop_event_poller,
+            commands::get_event_poller_status,
+            commands::get_daemon_status,
+            commands::start_daemon,
+            commands::stop_daemon,
+            commands::run_browser_script
+        ])
 }
-#[tauri::command]
-fn run_command(cmd: String) -> String {
-    use std::process::Command;
 
-    let output = Command::new("cmd")
-        .args(["/C", &cmd])
-        .output();
+#[cfg_attr(mobile, tauri::mobile_entry_point)]
+pub fn run() {
+    let db = Database::new().expect("Failed to initialize database");
+    if let Err(e) = settings::import_legacy_file(&db) {
+        eprintln!("[Settings] Failed to import settings.json: {}", e);
+    }
+
+    let env = AppEnv::system();
+    let daemon = env.daemon;
+    let app = configure(tauri::Builder::default(), db, env)
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application");
 
-    match output {
-        Ok(o) => String::from_utf8_lossy(&o.stdout).to_string(),
-        Err(_) => "Failed to run command".to_string(),
+    // Automatic backups write to ~/.personaliz/backups, so they start here
+    // rather than in `configure`, which tests share. A running daemon makes
+    // its own.
+    if !daemon.is_running() {
+        backup::start_auto_backups(Arc::clone(&app.state::<Arc<Mutex<Database>>>()));
     }
+    app.run(|_, _| {});
 }
@@ -0,0 +1,225 @@
+use serde_json::json;
+use std::sync::atomic::AtomicBool;
+
+use super::stream::{parse_chunk, sse_data, stream_lines};
+use super::{http_client, missing, send_json, ChatRequest, ChatResponse, LlmError, LlmProvider};
+
+pub const DEFAULT_ENDPOINT: &str = "https://api.anthropic.com/v1/messages";
+const API_VERSION: &str = "2023-06-01";
+// The messages API requires max_tokens
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// Claude models via the Anthropic messages API.
+pub struct AnthropicProvider {
+    endpoint: String,
+    api_key: String,
+    model: String,
+    client: reqwest::blocking::Client,
+}
+
+impl AnthropicProvider {
+    pub fn new(endpoint: &str, api_key: &str, model: &str) -> Self {
+        AnthropicProvider {
+            endpoint: endpoint.to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            client: http_client(),
+        }
+    }
+
+    fn request(&self, request: &ChatRequest, stream: bool) -> reqwest::blocking::RequestBuilder {
+        let messages: Vec<_> = request
+            .messages
+            .iter()
+            .map(|m| json!({"role": m.role, "content": m.content}))
+            .collect();
+
+        let mut body = json!({
+            "model": self.model,
+            "max_tokens": request.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS),
+            "messages": messages,
+        });
+        if let Some(ref system) = request.system {
+            body["system"] = json!(system);
+        }
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if stream {
+            body["stream"] = json!(true);
+        }
+
+        self.client
+            .post(&self.endpoint)
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&body)
+    }
+}
+
+impl LlmProvider for AnthropicProvider {
+    fn name(&self) -> &str {
+        "anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let data = send_json(self.request(request, false))?;
+
+        let blocks = data["content"].as_array().ok_or_else(|| missing("content"))?;
+        let content: String = blocks
+            .iter()
+            .filter(|b| b["type"] == "text")
+            .filter_map(|b| b["text"].as_str())
+            .collect();
+
+        Ok(ChatResponse {
+            provider: self.name().to_string(),
+            model: data["model"].as_str().unwrap_or(&self.model).to_string(),
+            content: content.trim().to_string(),
+            prompt_tokens: data["usage"]["input_tokens"].as_u64(),
+            completion_tokens: data["usage"]["output_tokens"].as_u64(),
+        })
+    }
+
+    /// Server-sent events: text arrives in `content_block_delta` events, input
+    /// tokens in `message_start` and output tokens in `message_delta`.
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &mut dyn FnMut(&str),
+        cancel: &AtomicBool,
+    ) -> Result<ChatResponse, LlmError> {
+        let mut result = ChatResponse {
+            provider: self.name().to_string(),
+            model: self.model.clone(),
+            content: String::new(),
+            prompt_tokens: None,
+            completion_tokens: None,
+        };
+
+        stream_lines(self.request(request, true), cancel, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(false);
+            };
+
+            let event = parse_chunk(data)?;
+            match event["type"].as_str().unwrap_or_default() {
+                "message_start" => {
+                    if let Some(model) = event["message"]["model"].as_str() {
+                        result.model = model.to_string();
+                    }
+                    result.prompt_tokens = event["message"]["usage"]["input_tokens"].as_u64();
+                }
+                "content_block_delta" => {
+                    if let Some(delta) = event["delta"]["text"].as_str() {
+                        result.content.push_str(delta);
+                        on_delta(delta);
+                    }
+                }
+                "message_delta" => {
+                    result.completion_tokens = event["usage"]["output_tokens"].as_u64();
+                }
+                "message_stop" => return Ok(true),
+                "error" => {
+                    let message = event["error"]["message"].as_str().unwrap_or("stream error");
+                    return Err(LlmError::InvalidResponse(message.to_string()));
+                }
+                _ => {}
+            }
+            Ok(false)
+        })?;
+
+        Ok(result)
+    }
+
+    /// Lists models at `/v1/models`, which checks the key without spending tokens.
+    fn probe(&self) -> Result<(), LlmError> {
+        let base = self.endpoint.trim_end_matches('/').trim_end_matches("/messages");
+        send_json(
+            self.client
+                .get(format!("{}/models", base))
+                .header("x-api-key", &self.api_key)
+                .header("anthropic-version", API_VERSION),
+        )
+        .map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockServer;
+
+    #[test]
+    fn test_chat() {
+        let server = MockServer::start(vec![(
+            200,
+            "application/json",
+            r#"{"model":"claude-3-haiku","content":[{"type":"text","text":"Hello"},{"type":"text","text":" world"}],
+                "usage":{"input_tokens":20,"output_tokens":4}}"#.to_string(),
+        )]);
+
+        let provider = AnthropicProvider::new(&format!("{}/v1/messages", server.url), "sk-ant", "claude-3-haiku");
+        let response = provider.complete("Greet", Some("You are terse".to_string())).unwrap();
+        assert_eq!(response.content, "Hello world");
+        assert_eq!(response.prompt_tokens, Some(20));
+        assert_eq!(response.completion_tokens, Some(4));
+
+        let request = server.request();
+        assert_eq!(request.header("x-api-key"), Some("sk-ant"));
+        assert_eq!(request.header("anthropic-version"), Some(API_VERSION));
+        let body = request.json();
+        assert_eq!(body["system"], "You are terse");
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+    }
+
+    #[test]
+    fn test_unreachable_is_connection_error() {
+        // Nothing listens on port 9 (discard) locally
+        let provider = AnthropicProvider::new("http://127.0.0.1:9/v1/messages", "sk-ant", "claude-3-haiku");
+        let err = provider.complete("Greet", None).unwrap_err();
+        assert!(matches!(err, LlmError::Connection(_)));
+    }
+
+    #[test]
+    fn test_chat_stream() {
+        let sse = [
+            "event: message_start",
+            r#"data: {"type":"message_start","message":{"model":"claude-3-haiku","usage":{"input_tokens":20}}}"#,
+            "",
+            "event: content_block_delta",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Hello"}}"#,
+            "",
+            "event: content_block_delta",
+            r#"data: {"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":" world"}}"#,
+            "",
+            "event: message_delta",
+            r#"data: {"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":4}}"#,
+            "",
+            "event: message_stop",
+            r#"data: {"type":"message_stop"}"#,
+            "",
+        ]
+        .join("\n");
+        let server = MockServer::start(vec![(200, "text/event-stream", sse)]);
+
+        let provider = AnthropicProvider::new(&server.url, "sk-ant", "claude-3-haiku");
+        let mut deltas = Vec::new();
+        let response = provider
+            .chat_stream(
+                &ChatRequest::from_prompt("Greet", None),
+                &mut |d| deltas.push(d.to_string()),
+                &AtomicBool::new(false),
+            )
+            .unwrap();
+
+        assert_eq!(deltas, vec!["Hello", " world"]);
+        assert_eq!(response.content, "Hello world");
+        assert_eq!(response.prompt_tokens, Some(20));
+        assert_eq!(response.completion_tokens, Some(4));
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::atomic::AtomicBool;
+
+use super::{ChatRequest, ChatResponse, LlmError, LlmProvider};
+
+/// Tries each provider in order, moving on to the next one when a provider
+/// can't be reached or is rate limiting us. Any other error (bad request,
+/// invalid key, ...) is returned straight away, since another provider would
+/// most likely not do better. The response's `provider` field records which
+/// one actually answered.
+pub struct FallbackChain {
+    providers: Vec<Box<dyn LlmProvider>>,
+}
+
+impl FallbackChain {
+    pub fn new(providers: Vec<Box<dyn LlmProvider>>) -> Self {
+        FallbackChain { providers }
+    }
+
+    fn run<T>(&self, mut call: impl FnMut(&dyn LlmProvider) -> Result<T, LlmError>) -> Result<T, LlmError> {
+        let mut last_error = LlmError::Config("No LLM provider configured".to_string());
+
+        for (i, provider) in self.providers.iter().enumerate() {
+            match call(provider.as_ref()) {
+                Err(e) if e.should_fall_back() => {
+                    if let Some(next) = self.providers.get(i + 1) {
+                        eprintln!(
+                            "[LLM] {} ({}) unavailable, falling back to {} ({}): {}",
+                            provider.name(), provider.model(), next.name(), next.model(), e
+                        );
+                    }
+                    last_error = e;
+                }
+                result => return result,
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+impl LlmError {
+    /// Errors that another provider in the chain might not run into.
+    pub fn should_fall_back(&self) -> bool {
+        matches!(self, LlmError::Connection(_) | LlmError::RateLimited(_))
+    }
+}
+
+impl LlmProvider for FallbackChain {
+    fn name(&self) -> &str {
+        self.providers.first().map(|p| p.name()).unwrap_or("none")
+    }
+
+    fn model(&self) -> &str {
+        self.providers.first().map(|p| p.model()).unwrap_or_default()
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        self.run(|provider| provider.chat(request))
+    }
+
+    /// Only falls back if the failing provider hadn't streamed anything yet,
+    /// so the frontend never sees two answers spliced together.
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &mut dyn FnMut(&str),
+        cancel: &AtomicBool,
+    ) -> Result<ChatResponse, LlmError> {
+        let mut streamed = false;
+        self.run(|provider| {
+            let mut forward = |delta: &str| {
+                streamed = true;
+                on_delta(delta);
+            };
+            match provider.chat_stream(request, &mut forward, cancel) {
+                Err(e) if streamed && e.should_fall_back() => {
+                    Err(LlmError::InvalidResponse(format!("stream interrupted: {}", e)))
+                }
+                result => result,
+            }
+        })
+    }
+
+    fn probe(&self) -> Result<(), LlmError> {
+        self.run(|provider| provider.probe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{OllamaProvider, OpenAiProvider};
+    use crate::test_support::MockServer;
+
+    #[test]
+    fn test_falls_back_on_rate_limit() {
+        let limited = MockServer::start(vec![(429, "application/json", "{}".to_string())]);
+        let backup = MockServer::start(vec![(
+            200,
+            "application/json",
+            r#"{"message":{"content":"from backup"}}"#.to_string(),
+        )]);
+
+        let chain = FallbackChain::new(vec![
+            Box::new(OpenAiProvider::new(&format!("{}/v1/chat/completions", limited.url), "sk", "gpt-4o")),
+            Box::new(OllamaProvider::new(&backup.url, "phi3")),
+        ]);
+
+        let response = chain.complete("hi", None).unwrap();
+        assert_eq!(response.content, "from backup");
+        assert_eq!(response.provider, "ollama");
+    }
+
+    #[test]
+    fn test_does_not_fall_back_on_auth_error() {
+        let unauthorized = MockServer::start(vec![(401, "application/json", "{}".to_string())]);
+
+        let chain = FallbackChain::new(vec![
+            Box::new(OpenAiProvider::new(&format!("{}/v1/chat/completions", unauthorized.url), "bad", "gpt-4o")),
+            Box::new(OllamaProvider::new("http://127.0.0.1:9", "phi3")),
+        ]);
+
+        let err = chain.complete("hi", None).unwrap_err();
+        assert!(matches!(err, LlmError::Http { status: 401, .. }));
+    }
+
+    #[test]
+    fn test_reports_last_error_when_all_fail() {
+        let chain = FallbackChain::new(vec![
+            Box::new(OllamaProvider::new("http://127.0.0.1:9", "phi3")),
+            Box::new(OllamaProvider::new("http://127.0.0.1:9", "llama3")),
+        ]);
+
+        let err = chain.complete("hi", None).unwrap_err();
+        assert!(matches!(err, LlmError::Connection(_)));
+    }
+}
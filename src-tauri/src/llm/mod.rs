@@ -0,0 +1,312 @@
+mod anthropic;
+mod fallback;
+mod ollama;
+mod openai;
+mod stream;
+mod usage;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::database::Database;
+use crate::secrets::{self, SecretStore};
+use crate::settings::{AppSettings, LlmProviderConfig};
+
+pub use anthropic::AnthropicProvider;
+pub use fallback::FallbackChain;
+pub use ollama::OllamaProvider;
+pub use openai::OpenAiProvider;
+pub use stream::StreamRegistry;
+pub use usage::{month_start, MeteredProvider};
+
+// Local models on CPU can take minutes for a long answer
+const REQUEST_TIMEOUT_SECS: u64 = 300;
+// An unreachable provider should fail over quickly, whatever the model's speed
+const CONNECT_TIMEOUT_SECS: u64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: String, // "user" or "assistant"
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatRequest {
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+impl ChatRequest {
+    pub fn from_prompt(prompt: &str, system: Option<String>) -> Self {
+        ChatRequest {
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: prompt.to_string(),
+            }],
+            system,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatResponse {
+    pub provider: String,
+    pub model: String,
+    pub content: String,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+}
+
+#[derive(Debug)]
+pub enum LlmError {
+    /// Settings are incomplete, e.g. a cloud provider without an API key
+    Config(String),
+    /// The provider could not be reached or timed out
+    Connection(String),
+    /// HTTP 429
+    RateLimited(String),
+    Http { status: u16, body: String },
+    InvalidResponse(String),
+    /// A streaming request was cancelled by the caller
+    Cancelled,
+    /// The monthly LLM budget in settings has been spent
+    BudgetExceeded(String),
+}
+
+impl fmt::Display for LlmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LlmError::Config(msg) => write!(f, "LLM not configured: {}", msg),
+            LlmError::Connection(msg) => write!(f, "Could not reach LLM provider: {}", msg),
+            LlmError::RateLimited(msg) => write!(f, "LLM provider rate limit hit: {}", msg),
+            LlmError::Http { status, body } => write!(f, "LLM provider returned HTTP {}: {}", status, body),
+            LlmError::InvalidResponse(msg) => write!(f, "Unexpected LLM response: {}", msg),
+            LlmError::Cancelled => write!(f, "LLM request cancelled"),
+            LlmError::BudgetExceeded(msg) => write!(f, "Monthly LLM budget exceeded: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for LlmError {}
+
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn model(&self) -> &str;
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError>;
+
+    fn complete(&self, prompt: &str, system: Option<String>) -> Result<ChatResponse, LlmError> {
+        self.chat(&ChatRequest::from_prompt(prompt, system))
+    }
+
+    /// Like `chat`, but hands each piece of text to `on_delta` as it arrives.
+    /// Setting `cancel` abandons the request at any point, even before the
+    /// first token, and returns `LlmError::Cancelled`. The returned response holds the full text.
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &mut dyn FnMut(&str),
+        cancel: &AtomicBool,
+    ) -> Result<ChatResponse, LlmError>;
+
+    /// Cheap request checking the provider is reachable, accepts our
+    /// credentials and can serve the configured model.
+    fn probe(&self) -> Result<(), LlmError>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub model: String,
+    pub healthy: bool,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Builds the provider chain from settings: the primary provider followed by
+/// `llm_fallback_providers`. Entries that can't be built (e.g. a cloud
+/// provider without an API key) are skipped with a warning.
+pub fn from_settings(settings: &AppSettings, secrets: &SecretStore) -> Result<Box<dyn LlmProvider>, LlmError> {
+    let mut providers = Vec::new();
+    let mut last_error = None;
+
+    for config in settings.llm_provider_chain() {
+        match build_provider(&config, &settings.llm_provider, secrets) {
+            Ok(provider) => providers.push(provider),
+            Err(e) => {
+                eprintln!("[LLM] Skipping {} ({}): {}", config.provider, config.model, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    match providers.len() {
+        0 => Err(last_error.unwrap_or_else(|| LlmError::Config("No LLM provider configured".to_string()))),
+        1 => Ok(providers.remove(0)),
+        _ => Ok(Box::new(FallbackChain::new(providers))),
+    }
+}
+
+/// Like `from_settings`, but records usage against `agent_id` and enforces
+/// the monthly budget.
+pub fn metered_from_settings(
+    settings: &AppSettings,
+    db: Arc<Mutex<Database>>,
+    agent_id: Option<i64>,
+    secrets: &SecretStore,
+) -> Result<Box<dyn LlmProvider>, LlmError> {
+    let provider = from_settings(settings, secrets)?;
+    Ok(Box::new(MeteredProvider::new(provider, settings, db, agent_id)))
+}
+
+/// The Ollama server used for embeddings: the first local provider in the
+/// chain, or the default local endpoint, with `llm_embedding_model`.
+pub fn embedder_from_settings(settings: &AppSettings) -> OllamaProvider {
+    let endpoint = settings
+        .llm_provider_chain()
+        .into_iter()
+        .find(|config| matches!(config.provider.as_str(), "local" | "ollama") && !config.endpoint.is_empty())
+        .map(|config| config.endpoint)
+        .unwrap_or_else(|| ollama::DEFAULT_ENDPOINT.to_string());
+    OllamaProvider::new(&endpoint, &settings.llm_embedding_model)
+}
+
+/// Probes every provider in the chain, in order.
+pub fn probe_all(settings: &AppSettings, secrets: &SecretStore) -> Vec<ProviderHealth> {
+    settings
+        .llm_provider_chain()
+        .iter()
+        .map(|config| {
+            let started = Instant::now();
+            let result = build_provider(config, &settings.llm_provider, secrets).and_then(|p| p.probe());
+            ProviderHealth {
+                provider: config.provider.clone(),
+                model: config.model.clone(),
+                healthy: result.is_ok(),
+                latency_ms: started.elapsed().as_millis() as u64,
+                error: result.err().map(|e| e.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// Builds one provider. `primary` is the settings' `llm_provider`: only that
+/// provider may use the key saved by save_settings, fallbacks need their own.
+/// API keys are read from `secrets`.
+pub fn build_provider(
+    config: &LlmProviderConfig,
+    primary: &str,
+    secrets: &SecretStore,
+) -> Result<Box<dyn LlmProvider>, LlmError> {
+    let api_key = api_key_for(&config.provider, primary, secrets)?;
+
+    match config.provider.as_str() {
+        "local" | "ollama" => {
+            let endpoint = if config.endpoint.is_empty() { ollama::DEFAULT_ENDPOINT } else { &config.endpoint };
+            Ok(Box::new(OllamaProvider::new(endpoint, &config.model)))
+        }
+        "openai" => Ok(Box::new(OpenAiProvider::new(
+            endpoint_or(&config.endpoint, "/chat/completions", openai::DEFAULT_ENDPOINT),
+            &api_key,
+            &config.model,
+        ))),
+        "claude" | "anthropic" => {
+            if api_key.is_empty() {
+                return Err(LlmError::Config("Claude requires an API key".to_string()));
+            }
+            Ok(Box::new(AnthropicProvider::new(
+                endpoint_or(&config.endpoint, "/messages", anthropic::DEFAULT_ENDPOINT),
+                &api_key,
+                &config.model,
+            )))
+        }
+        other => Err(LlmError::Config(format!("Unknown LLM provider: {}", other))),
+    }
+}
+
+fn api_key_for(provider: &str, primary: &str, store: &SecretStore) -> Result<String, LlmError> {
+    if let Some(key) = store.get(&secrets::provider_api_key(provider)).map_err(LlmError::Config)? {
+        return Ok(key);
+    }
+    if provider == primary {
+        return Ok(store.get(secrets::LLM_API_KEY).map_err(LlmError::Config)?.unwrap_or_default());
+    }
+    Ok(String::new())
+}
+
+/// `llm_endpoint` defaults to the local Ollama URL, so cloud providers only use
+/// it when it looks like their own API (e.g. an OpenAI-compatible server).
+fn endpoint_or<'a>(endpoint: &'a str, expected_suffix: &str, default: &'a str) -> &'a str {
+    if endpoint.trim_end_matches('/').ends_with(expected_suffix) {
+        endpoint
+    } else {
+        default
+    }
+}
+
+fn http_client() -> reqwest::blocking::Client {
+    reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_else(|_| reqwest::blocking::Client::new())
+}
+
+/// Sends a request and returns the JSON body, classifying failures so callers
+/// can tell unreachable or rate-limited providers apart from bad requests.
+fn send_json(request: reqwest::blocking::RequestBuilder) -> Result<serde_json::Value, LlmError> {
+    send(request)?
+        .json()
+        .map_err(|e| LlmError::InvalidResponse(e.to_string()))
+}
+
+fn send(request: reqwest::blocking::RequestBuilder) -> Result<reqwest::blocking::Response, LlmError> {
+    let response = request.send().map_err(|e| {
+        if e.is_connect() || e.is_timeout() {
+            LlmError::Connection(e.to_string())
+        } else {
+            LlmError::InvalidResponse(e.to_string())
+        }
+    })?;
+
+    let status = response.status();
+    if status.as_u16() == 429 {
+        let body = response.text().unwrap_or_default();
+        return Err(LlmError::RateLimited(body));
+    }
+    if !status.is_success() {
+        let body = response.text().unwrap_or_default();
+        return Err(LlmError::Http { status: status.as_u16(), body });
+    }
+
+    Ok(response)
+}
+
+fn missing(field: &str) -> LlmError {
+    LlmError::InvalidResponse(format!("missing {}", field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_or() {
+        assert_eq!(
+            endpoint_or("http://localhost:11434/api/generate", "/chat/completions", openai::DEFAULT_ENDPOINT),
+            openai::DEFAULT_ENDPOINT
+        );
+        assert_eq!(
+            endpoint_or("http://localhost:1234/v1/chat/completions", "/chat/completions", openai::DEFAULT_ENDPOINT),
+            "http://localhost:1234/v1/chat/completions"
+        );
+    }
+}
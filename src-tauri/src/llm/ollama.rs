@@ -0,0 +1,298 @@
+use serde_json::json;
+use std::sync::atomic::AtomicBool;
+
+use super::stream::{parse_chunk, stream_lines};
+use super::{http_client, missing, send_json, ChatRequest, ChatResponse, LlmError, LlmProvider};
+
+pub const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+/// Local models served by Ollama, via its `/api/chat` endpoint.
+pub struct OllamaProvider {
+    base_url: String,
+    model: String,
+    client: reqwest::blocking::Client,
+}
+
+impl OllamaProvider {
+    /// `endpoint` may be the server root or any Ollama API URL, such as the
+    /// `http://localhost:11434/api/generate` stored in settings.
+    pub fn new(endpoint: &str, model: &str) -> Self {
+        OllamaProvider {
+            base_url: base_url(endpoint),
+            model: model.to_string(),
+            client: http_client(),
+        }
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Embeds each text with this provider's model via `/api/embed`. The
+    /// model must be an embedding model, e.g. `nomic-embed-text`.
+    pub fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, LlmError> {
+        let data = send_json(
+            self.client
+                .post(format!("{}/api/embed", self.base_url))
+                .json(&json!({"model": self.model, "input": texts})),
+        )?;
+
+        let embeddings: Vec<Vec<f32>> = data["embeddings"]
+            .as_array()
+            .ok_or_else(|| missing("embeddings"))?
+            .iter()
+            .map(|vector| {
+                vector
+                    .as_array()
+                    .map(|values| values.iter().filter_map(|v| v.as_f64()).map(|v| v as f32).collect())
+                    .ok_or_else(|| missing("embeddings[]"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        if embeddings.len() != texts.len() {
+            return Err(LlmError::InvalidResponse(format!(
+                "expected {} embeddings, got {}",
+                texts.len(),
+                embeddings.len()
+            )));
+        }
+        Ok(embeddings)
+    }
+
+    fn body(&self, request: &ChatRequest, stream: bool) -> serde_json::Value {
+        let mut messages = Vec::new();
+        if let Some(ref system) = request.system {
+            messages.push(json!({"role": "system", "content": system}));
+        }
+        messages.extend(
+            request
+                .messages
+                .iter()
+                .map(|m| json!({"role": m.role, "content": m.content})),
+        );
+
+        let mut options = serde_json::Map::new();
+        if let Some(temperature) = request.temperature {
+            options.insert("temperature".to_string(), json!(temperature));
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            options.insert("num_predict".to_string(), json!(max_tokens));
+        }
+
+        json!({
+            "model": self.model,
+            "messages": messages,
+            "stream": stream,
+            "options": options,
+        })
+    }
+}
+
+pub(super) fn base_url(endpoint: &str) -> String {
+    match endpoint.find("/api/") {
+        Some(pos) => endpoint[..pos].to_string(),
+        None => endpoint.trim_end_matches('/').to_string(),
+    }
+}
+
+impl LlmProvider for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let data = send_json(
+            self.client
+                .post(format!("{}/api/chat", self.base_url))
+                .json(&self.body(request, false)),
+        )?;
+
+        let content = data["message"]["content"]
+            .as_str()
+            .ok_or_else(|| missing("message.content"))?;
+
+        Ok(ChatResponse {
+            provider: self.name().to_string(),
+            model: self.model.clone(),
+            content: content.trim().to_string(),
+            prompt_tokens: data["prompt_eval_count"].as_u64(),
+            completion_tokens: data["eval_count"].as_u64(),
+        })
+    }
+
+    /// Ollama streams newline-delimited JSON objects, the last one with `"done": true`.
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &mut dyn FnMut(&str),
+        cancel: &AtomicBool,
+    ) -> Result<ChatResponse, LlmError> {
+        let http_request = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&self.body(request, true));
+
+        let mut result = ChatResponse {
+            provider: self.name().to_string(),
+            model: self.model.clone(),
+            content: String::new(),
+            prompt_tokens: None,
+            completion_tokens: None,
+        };
+
+        stream_lines(http_request, cancel, |line| {
+            let chunk = parse_chunk(line)?;
+            if let Some(error) = chunk["error"].as_str() {
+                return Err(LlmError::InvalidResponse(error.to_string()));
+            }
+            if let Some(delta) = chunk["message"]["content"].as_str() {
+                if !delta.is_empty() {
+                    result.content.push_str(delta);
+                    on_delta(delta);
+                }
+            }
+            if chunk["done"].as_bool().unwrap_or(false) {
+                result.prompt_tokens = chunk["prompt_eval_count"].as_u64();
+                result.completion_tokens = chunk["eval_count"].as_u64();
+                return Ok(true);
+            }
+            Ok(false)
+        })?;
+
+        Ok(result)
+    }
+
+    /// Lists local models via `/api/tags`, the HTTP equivalent of `ollama list`.
+    fn probe(&self) -> Result<(), LlmError> {
+        let data = send_json(self.client.get(format!("{}/api/tags", self.base_url)))?;
+        let models = data["models"].as_array().ok_or_else(|| missing("models"))?;
+
+        // "phi3" is stored as "phi3:latest"
+        let wanted = if self.model.contains(':') { self.model.clone() } else { format!("{}:latest", self.model) };
+        let pulled = models
+            .iter()
+            .filter_map(|m| m["name"].as_str())
+            .any(|name| name == self.model || name == wanted);
+
+        if pulled {
+            Ok(())
+        } else {
+            Err(LlmError::Config(format!("model {} is not pulled (run: ollama pull {})", self.model, self.model)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockServer;
+
+    #[test]
+    fn test_base_url() {
+        assert_eq!(base_url("http://localhost:11434/api/generate"), "http://localhost:11434");
+        assert_eq!(base_url("http://gpu-box:11434/"), "http://gpu-box:11434");
+    }
+
+    #[test]
+    fn test_embed() {
+        let server = MockServer::start(vec![(
+            200,
+            "application/json",
+            r#"{"model":"nomic-embed-text","embeddings":[[0.1,0.2],[0.3,0.4]]}"#.to_string(),
+        )]);
+        let provider = OllamaProvider::new(&server.url, "nomic-embed-text");
+
+        let embeddings = provider.embed(&["a".to_string(), "b".to_string()]).unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+
+        let request = server.request();
+        assert_eq!(request.path, "/api/embed");
+        assert_eq!(request.json()["input"][1], "b");
+    }
+
+    #[test]
+    fn test_chat() {
+        let server = MockServer::start(vec![(
+            200,
+            "application/json",
+            r#"{"message":{"role":"assistant","content":" Hello! "},"prompt_eval_count":12,"eval_count":3}"#.to_string(),
+        )]);
+
+        let provider = OllamaProvider::new(&format!("{}/api/generate", server.url), "phi3");
+        let response = provider.complete("Say hello", Some("Be brief".to_string())).unwrap();
+        assert_eq!(response.content, "Hello!");
+        assert_eq!(response.provider, "ollama");
+        assert_eq!(response.prompt_tokens, Some(12));
+        assert_eq!(response.completion_tokens, Some(3));
+
+        let request = server.request();
+        assert_eq!(request.path, "/api/chat");
+        let body = request.json();
+        assert_eq!(body["model"], "phi3");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["messages"][0]["role"], "system");
+        assert_eq!(body["messages"][1]["content"], "Say hello");
+    }
+
+    #[test]
+    fn test_chat_stream() {
+        let ndjson = [
+            r#"{"message":{"role":"assistant","content":"Hel"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":"lo"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true,"prompt_eval_count":5,"eval_count":2}"#,
+        ]
+        .join("\n");
+        let server = MockServer::start(vec![(200, "application/x-ndjson", ndjson)]);
+
+        let provider = OllamaProvider::new(&server.url, "phi3");
+        let mut deltas = Vec::new();
+        let response = provider
+            .chat_stream(
+                &ChatRequest::from_prompt("Say hello", None),
+                &mut |d| deltas.push(d.to_string()),
+                &AtomicBool::new(false),
+            )
+            .unwrap();
+
+        assert_eq!(deltas, vec!["Hel", "lo"]);
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.completion_tokens, Some(2));
+        assert_eq!(server.request().json()["stream"], true);
+    }
+
+    #[test]
+    fn test_probe() {
+        let tags = r#"{"models":[{"name":"phi3:latest"},{"name":"llama3:8b"}]}"#;
+        let server = MockServer::start(vec![
+            (200, "application/json", tags.to_string()),
+            (200, "application/json", tags.to_string()),
+        ]);
+
+        assert!(OllamaProvider::new(&server.url, "phi3").probe().is_ok());
+        assert_eq!(server.request().path, "/api/tags");
+        assert!(matches!(OllamaProvider::new(&server.url, "mistral").probe(), Err(LlmError::Config(_))));
+    }
+
+    #[test]
+    fn test_chat_stream_cancelled() {
+        let server = MockServer::start(vec![(
+            200,
+            "application/x-ndjson",
+            r#"{"message":{"content":"Hel"},"done":false}"#.to_string(),
+        )]);
+
+        let provider = OllamaProvider::new(&server.url, "phi3");
+        let err = provider
+            .chat_stream(
+                &ChatRequest::from_prompt("Say hello", None),
+                &mut |_| {},
+                &AtomicBool::new(true),
+            )
+            .unwrap_err();
+        assert!(matches!(err, LlmError::Cancelled));
+    }
+}
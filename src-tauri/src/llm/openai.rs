@@ -0,0 +1,212 @@
+use serde_json::json;
+use std::sync::atomic::AtomicBool;
+
+use super::stream::{parse_chunk, sse_data, stream_lines};
+use super::{http_client, missing, send_json, ChatRequest, ChatResponse, LlmError, LlmProvider};
+
+pub const DEFAULT_ENDPOINT: &str = "https://api.openai.com/v1/chat/completions";
+
+/// OpenAI or any server exposing the OpenAI chat-completions API.
+pub struct OpenAiProvider {
+    endpoint: String,
+    api_key: String,
+    model: String,
+    client: reqwest::blocking::Client,
+}
+
+impl OpenAiProvider {
+    /// `endpoint` is the full chat-completions URL. The API key may be empty
+    /// for local OpenAI-compatible servers.
+    pub fn new(endpoint: &str, api_key: &str, model: &str) -> Self {
+        OpenAiProvider {
+            endpoint: endpoint.to_string(),
+            api_key: api_key.to_string(),
+            model: model.to_string(),
+            client: http_client(),
+        }
+    }
+
+    fn request(&self, request: &ChatRequest, stream: bool) -> reqwest::blocking::RequestBuilder {
+        let mut messages = Vec::new();
+        if let Some(ref system) = request.system {
+            messages.push(json!({"role": "system", "content": system}));
+        }
+        messages.extend(
+            request
+                .messages
+                .iter()
+                .map(|m| json!({"role": m.role, "content": m.content})),
+        );
+
+        let mut body = json!({
+            "model": self.model,
+            "messages": messages,
+        });
+        if let Some(temperature) = request.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(max_tokens) = request.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if stream {
+            body["stream"] = json!(true);
+            // Ask for a final chunk with token usage
+            body["stream_options"] = json!({"include_usage": true});
+        }
+
+        let mut http_request = self.client.post(&self.endpoint).json(&body);
+        if !self.api_key.is_empty() {
+            http_request = http_request.bearer_auth(&self.api_key);
+        }
+        http_request
+    }
+}
+
+impl LlmProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        let data = send_json(self.request(request, false))?;
+
+        let content = data["choices"][0]["message"]["content"]
+            .as_str()
+            .ok_or_else(|| missing("choices[0].message.content"))?;
+
+        Ok(ChatResponse {
+            provider: self.name().to_string(),
+            model: data["model"].as_str().unwrap_or(&self.model).to_string(),
+            content: content.trim().to_string(),
+            prompt_tokens: data["usage"]["prompt_tokens"].as_u64(),
+            completion_tokens: data["usage"]["completion_tokens"].as_u64(),
+        })
+    }
+
+    /// Server-sent events with `choices[0].delta.content`, ending in `data: [DONE]`.
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &mut dyn FnMut(&str),
+        cancel: &AtomicBool,
+    ) -> Result<ChatResponse, LlmError> {
+        let mut result = ChatResponse {
+            provider: self.name().to_string(),
+            model: self.model.clone(),
+            content: String::new(),
+            prompt_tokens: None,
+            completion_tokens: None,
+        };
+
+        stream_lines(self.request(request, true), cancel, |line| {
+            let Some(data) = sse_data(line) else {
+                return Ok(false);
+            };
+            if data == "[DONE]" {
+                return Ok(true);
+            }
+
+            let chunk = parse_chunk(data)?;
+            if let Some(model) = chunk["model"].as_str() {
+                result.model = model.to_string();
+            }
+            if let Some(delta) = chunk["choices"][0]["delta"]["content"].as_str() {
+                if !delta.is_empty() {
+                    result.content.push_str(delta);
+                    on_delta(delta);
+                }
+            }
+            if let Some(prompt_tokens) = chunk["usage"]["prompt_tokens"].as_u64() {
+                result.prompt_tokens = Some(prompt_tokens);
+                result.completion_tokens = chunk["usage"]["completion_tokens"].as_u64();
+            }
+            Ok(false)
+        })?;
+
+        Ok(result)
+    }
+
+    /// Lists models at the `/models` endpoint next to chat completions.
+    fn probe(&self) -> Result<(), LlmError> {
+        let base = self.endpoint.trim_end_matches('/').trim_end_matches("/chat/completions");
+        let mut http_request = self.client.get(format!("{}/models", base));
+        if !self.api_key.is_empty() {
+            http_request = http_request.bearer_auth(&self.api_key);
+        }
+        send_json(http_request).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockServer;
+
+    #[test]
+    fn test_chat() {
+        let server = MockServer::start(vec![(
+            200,
+            "application/json",
+            r#"{"model":"gpt-4o-mini","choices":[{"message":{"role":"assistant","content":"Hi there"}}],
+                "usage":{"prompt_tokens":9,"completion_tokens":2}}"#.to_string(),
+        )]);
+
+        let provider = OpenAiProvider::new(&format!("{}/v1/chat/completions", server.url), "sk-test", "gpt-4o-mini");
+        let response = provider.complete("Say hi", None).unwrap();
+        assert_eq!(response.content, "Hi there");
+        assert_eq!(response.prompt_tokens, Some(9));
+        assert_eq!(response.completion_tokens, Some(2));
+
+        let request = server.request();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/v1/chat/completions");
+        assert_eq!(request.header("authorization"), Some("Bearer sk-test"));
+        assert_eq!(request.json()["messages"][0]["content"], "Say hi");
+    }
+
+    #[test]
+    fn test_rate_limit_is_classified() {
+        let server = MockServer::start(vec![(429, "application/json", r#"{"error":"slow down"}"#.to_string())]);
+
+        let provider = OpenAiProvider::new(&format!("{}/v1/chat/completions", server.url), "sk-test", "gpt-4o-mini");
+        let err = provider.complete("Say hi", None).unwrap_err();
+        assert!(matches!(err, LlmError::RateLimited(_)));
+    }
+
+    #[test]
+    fn test_chat_stream() {
+        let sse = [
+            r#"data: {"model":"gpt-4o-mini","choices":[{"delta":{"role":"assistant"}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"content":"Hi"}}]}"#,
+            "",
+            r#"data: {"choices":[{"delta":{"content":" there"}}]}"#,
+            "",
+            r#"data: {"choices":[],"usage":{"prompt_tokens":9,"completion_tokens":2}}"#,
+            "",
+            "data: [DONE]",
+            "",
+        ]
+        .join("\n");
+        let server = MockServer::start(vec![(200, "text/event-stream", sse)]);
+
+        let provider = OpenAiProvider::new(&server.url, "sk-test", "gpt-4o-mini");
+        let mut deltas = Vec::new();
+        let response = provider
+            .chat_stream(
+                &ChatRequest::from_prompt("Say hi", None),
+                &mut |d| deltas.push(d.to_string()),
+                &AtomicBool::new(false),
+            )
+            .unwrap();
+
+        assert_eq!(deltas, vec!["Hi", " there"]);
+        assert_eq!(response.content, "Hi there");
+        assert_eq!(response.prompt_tokens, Some(9));
+        assert_eq!(server.request().json()["stream"], true);
+    }
+}
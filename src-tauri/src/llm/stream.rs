@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::{send, LlmError};
+
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Cancellation flags for in-flight streaming requests, keyed by the request
+/// id the frontend chose.
+#[derive(Default)]
+pub struct StreamRegistry {
+    active: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl StreamRegistry {
+    pub fn register(&self, request_id: &str) -> Result<Arc<AtomicBool>, String> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(request_id) {
+            return Err(format!("Request {} is already streaming", request_id));
+        }
+        let flag = Arc::new(AtomicBool::new(false));
+        active.insert(request_id.to_string(), Arc::clone(&flag));
+        Ok(flag)
+    }
+
+    /// Returns false if no request with this id is running.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        match self.active.lock().unwrap().get(request_id) {
+            Some(flag) => {
+                flag.store(true, Ordering::SeqCst);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn finish(&self, request_id: &str) {
+        self.active.lock().unwrap().remove(request_id);
+    }
+}
+
+/// Sends a streaming request and feeds each non-empty line of the response
+/// to `on_line` until it returns `Ok(true)` (done) or the body ends.
+///
+/// The request runs on its own thread while this one watches `cancel`, so a
+/// cancel takes effect at once whether the request is connecting, waiting for
+/// the first token or between lines. The abandoned thread drops the response,
+/// closing the connection, as soon as its current read returns.
+///
+/// The client's timeout (see `http_client`) bounds the wait for headers and
+/// for each read, not the whole body, so long answers aren't cut off. Don't
+/// set one on `request`: reqwest applies a per-request timeout to the body as
+/// a whole.
+pub(super) fn stream_lines(
+    request: reqwest::blocking::RequestBuilder,
+    cancel: &AtomicBool,
+    mut on_line: impl FnMut(&str) -> Result<bool, LlmError>,
+) -> Result<(), LlmError> {
+    let (tx, rx) = mpsc::sync_channel::<Result<String, LlmError>>(64);
+    thread::Builder::new()
+        .name("llm-stream".to_string())
+        .spawn(move || {
+            let response = match send(request) {
+                Ok(response) => response,
+                Err(e) => {
+                    let _ = tx.send(Err(e));
+                    return;
+                }
+            };
+            for line in BufReader::new(response).lines() {
+                let line = line.map_err(|e| LlmError::Connection(e.to_string()));
+                let failed = line.is_err();
+                // A closed channel means the caller is done or cancelled
+                if tx.send(line).is_err() || failed {
+                    return;
+                }
+            }
+        })
+        .map_err(|e| LlmError::Connection(format!("Failed to start stream thread: {}", e)))?;
+
+    loop {
+        if cancel.load(Ordering::SeqCst) {
+            return Err(LlmError::Cancelled);
+        }
+        match rx.recv_timeout(CANCEL_POLL_INTERVAL) {
+            Ok(line) => {
+                let line = line?;
+                let line = line.trim();
+                if !line.is_empty() && on_line(line)? {
+                    return Ok(());
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// The payload of a server-sent events `data:` line; other SSE fields are ignored.
+pub(super) fn sse_data(line: &str) -> Option<&str> {
+    line.strip_prefix("data:").map(str::trim_start)
+}
+
+pub(super) fn parse_chunk(data: &str) -> Result<serde_json::Value, LlmError> {
+    serde_json::from_str(data).map_err(|e| LlmError::InvalidResponse(format!("bad stream chunk: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_cancel() {
+        let registry = StreamRegistry::default();
+        let flag = registry.register("req-1").unwrap();
+        assert!(registry.register("req-1").is_err());
+
+        assert!(registry.cancel("req-1"));
+        assert!(flag.load(Ordering::SeqCst));
+
+        registry.finish("req-1");
+        assert!(!registry.cancel("req-1"));
+    }
+
+    #[test]
+    fn test_cancel_while_waiting_for_response() {
+        // Accepts the connection but never answers
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let _connection = listener.accept();
+            thread::sleep(Duration::from_secs(5));
+        });
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancel);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            flag.store(true, Ordering::SeqCst);
+        });
+
+        let started = std::time::Instant::now();
+        let result = stream_lines(reqwest::blocking::Client::new().post(url), &cancel, |_| Ok(false));
+        assert!(matches!(result, Err(LlmError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    // Serves one line every `interval`, then closes; the body has no length
+    fn slow_server(lines: usize, interval: Duration) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            use std::io::{Read, Write};
+            let (mut connection, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let _ = connection.read(&mut request);
+            let _ = connection.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n");
+            for n in 0..lines {
+                thread::sleep(interval);
+                let _ = connection.write_all(format!("{{\"n\":{}}}\n", n).as_bytes());
+                let _ = connection.flush();
+            }
+        });
+        url
+    }
+
+    fn client(timeout: Duration) -> reqwest::blocking::Client {
+        reqwest::blocking::Client::builder().timeout(timeout).build().unwrap()
+    }
+
+    #[test]
+    fn test_stream_outlasts_client_timeout() {
+        // 2 s in total, but never more than 300 ms between lines
+        let url = slow_server(6, Duration::from_millis(300));
+        let mut lines = Vec::new();
+        let result = stream_lines(client(Duration::from_secs(1)).post(url), &AtomicBool::new(false), |line| {
+            lines.push(line.to_string());
+            Ok(false)
+        });
+
+        assert!(result.is_ok(), "{:?}", result);
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn test_stalled_stream_times_out() {
+        let url = slow_server(2, Duration::from_secs(2));
+        let result = stream_lines(client(Duration::from_millis(500)).post(url), &AtomicBool::new(false), |_| Ok(false));
+        assert!(matches!(result, Err(LlmError::Connection(_))), "{:?}", result);
+    }
+
+    #[test]
+    fn test_sse_data() {
+        assert_eq!(sse_data("data: {\"a\":1}"), Some("{\"a\":1}"));
+        assert_eq!(sse_data("data:[DONE]"), Some("[DONE]"));
+        assert_eq!(sse_data("event: message_start"), None);
+    }
+}
@@ -0,0 +1,180 @@
+use chrono::{Datelike, TimeZone, Utc};
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::{ChatRequest, ChatResponse, LlmError, LlmProvider};
+use crate::database::{Database, LlmUsage};
+use crate::settings::{AppSettings, ModelPrice};
+
+/// Wraps a provider (usually the whole fallback chain) to record every call
+/// in `llm_usage` and refuse new calls once the monthly budget is spent.
+/// The database lock is only held for the bookkeeping, not during the call.
+pub struct MeteredProvider {
+    inner: Box<dyn LlmProvider>,
+    db: Arc<Mutex<Database>>,
+    agent_id: Option<i64>,
+    prices: Vec<ModelPrice>,
+    monthly_budget_usd: f64,
+}
+
+impl MeteredProvider {
+    pub fn new(inner: Box<dyn LlmProvider>, settings: &AppSettings, db: Arc<Mutex<Database>>, agent_id: Option<i64>) -> Self {
+        MeteredProvider {
+            inner,
+            db,
+            agent_id,
+            prices: settings.llm_prices.clone(),
+            monthly_budget_usd: settings.llm_monthly_budget_usd,
+        }
+    }
+
+    fn check_budget(&self) -> Result<(), LlmError> {
+        if self.monthly_budget_usd <= 0.0 {
+            return Ok(());
+        }
+        let spent = self
+            .db
+            .lock()
+            .map_err(|e| LlmError::Config(format!("Failed to lock database: {}", e)))?
+            .get_llm_cost_since(&month_start())
+            .map_err(|e| LlmError::Config(format!("Failed to read LLM usage: {}", e)))?;
+
+        if spent >= self.monthly_budget_usd {
+            return Err(LlmError::BudgetExceeded(format!(
+                "${:.2} spent of the ${:.2} monthly budget",
+                spent, self.monthly_budget_usd
+            )));
+        }
+        Ok(())
+    }
+
+    fn metered(
+        &self,
+        call: impl FnOnce() -> Result<ChatResponse, LlmError>,
+    ) -> Result<ChatResponse, LlmError> {
+        self.check_budget()?;
+
+        let started = Instant::now();
+        let result = call();
+        let latency_ms = started.elapsed().as_millis() as i64;
+
+        let usage = match result {
+            Ok(ref response) => {
+                let prompt_tokens = response.prompt_tokens.unwrap_or(0) as i64;
+                let completion_tokens = response.completion_tokens.unwrap_or(0) as i64;
+                LlmUsage {
+                    id: None,
+                    agent_id: self.agent_id,
+                    provider: response.provider.clone(),
+                    model: response.model.clone(),
+                    prompt_tokens,
+                    completion_tokens,
+                    latency_ms,
+                    cost_usd: estimate_cost(&self.prices, &response.provider, &response.model, prompt_tokens, completion_tokens),
+                    success: true,
+                    created_at: String::new(),
+                }
+            }
+            // Nothing is billed for a call that never started
+            Err(LlmError::BudgetExceeded(_)) => return result,
+            Err(_) => LlmUsage {
+                id: None,
+                agent_id: self.agent_id,
+                provider: self.inner.name().to_string(),
+                model: self.inner.model().to_string(),
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                latency_ms,
+                cost_usd: 0.0,
+                success: false,
+                created_at: String::new(),
+            },
+        };
+
+        match self.db.lock() {
+            Ok(db) => {
+                if let Err(e) = db.record_llm_usage(&usage) {
+                    eprintln!("[LLM] Failed to record usage: {}", e);
+                }
+            }
+            Err(e) => eprintln!("[LLM] Failed to record usage: {}", e),
+        }
+
+        result
+    }
+}
+
+impl LlmProvider for MeteredProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    fn chat(&self, request: &ChatRequest) -> Result<ChatResponse, LlmError> {
+        self.metered(|| self.inner.chat(request))
+    }
+
+    fn chat_stream(
+        &self,
+        request: &ChatRequest,
+        on_delta: &mut dyn FnMut(&str),
+        cancel: &AtomicBool,
+    ) -> Result<ChatResponse, LlmError> {
+        self.metered(|| self.inner.chat_stream(request, on_delta, cancel))
+    }
+
+    fn probe(&self) -> Result<(), LlmError> {
+        self.inner.probe()
+    }
+}
+
+/// Estimated cost in USD. Prices are matched on provider and the longest
+/// model prefix; models without a price (e.g. local ones) cost nothing.
+pub fn estimate_cost(prices: &[ModelPrice], provider: &str, model: &str, prompt_tokens: i64, completion_tokens: i64) -> f64 {
+    prices
+        .iter()
+        .filter(|p| p.provider == provider && model.starts_with(&p.model))
+        .max_by_key(|p| p.model.len())
+        .map(|p| {
+            (prompt_tokens as f64 * p.input_per_million + completion_tokens as f64 * p.output_per_million) / 1_000_000.0
+        })
+        .unwrap_or(0.0)
+}
+
+/// Start of the current UTC month, in the format `created_at` is stored in.
+pub fn month_start() -> String {
+    let now = Utc::now();
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .unwrap()
+        .to_rfc3339()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_cost_uses_longest_prefix() {
+        let prices = AppSettings::defaults().llm_prices;
+
+        // gpt-4o-mini, not gpt-4o or gpt-4
+        let cost = estimate_cost(&prices, "openai", "gpt-4o-mini-2024-07-18", 1_000_000, 1_000_000);
+        assert!((cost - 0.75).abs() < 1e-9);
+
+        let cost = estimate_cost(&prices, "anthropic", "claude-3-5-sonnet-20241022", 2000, 500);
+        assert!((cost - 0.0135).abs() < 1e-9);
+
+        assert_eq!(estimate_cost(&prices, "ollama", "phi3", 5000, 5000), 0.0);
+    }
+
+    #[test]
+    fn test_month_start() {
+        let start = month_start();
+        assert!(start.ends_with("-01T00:00:00+00:00"));
+        assert!(Utc::now().to_rfc3339() >= start);
+    }
+}
@@ -0,0 +1,145 @@
+use serde::Serialize;
+
+use crate::database::{AgentMemory, Database};
+use crate::llm::OllamaProvider;
+
+// Above this similarity a new memory is treated as a repeat of an old one
+const DUPLICATE_THRESHOLD: f32 = 0.97;
+
+#[derive(Debug, Serialize)]
+pub struct MemoryMatch {
+    pub memory: AgentMemory,
+    pub score: f32,
+}
+
+/// Outcome of `remember`: the new memory's id, or the existing memory it
+/// duplicates (e.g. a post the hashtag monitor has already seen).
+#[derive(Debug, Serialize)]
+pub struct Remembered {
+    pub id: i64,
+    pub duplicate: bool,
+}
+
+/// Stores `content` for the agent, unless the agent already remembers
+/// something nearly identical. `embedding` comes from `embed`, made without
+/// holding the database lock.
+pub fn remember(db: &Database, agent_id: i64, content: &str, embedding: Vec<f32>, model: &str) -> Result<Remembered, String> {
+    let memories = db
+        .get_agent_memories(agent_id)
+        .map_err(|e| format!("Failed to read agent memories: {}", e))?;
+
+    if let Some(best) = search(memories, &embedding, model, 1, DUPLICATE_THRESHOLD).into_iter().next() {
+        return Ok(Remembered {
+            id: best.memory.id.unwrap_or_default(),
+            duplicate: true,
+        });
+    }
+
+    let memory = AgentMemory {
+        id: None,
+        agent_id,
+        content: content.to_string(),
+        embedding,
+        model: model.to_string(),
+        created_at: String::new(),
+    };
+    let id = db
+        .add_agent_memory(&memory)
+        .map_err(|e| format!("Failed to save agent memory: {}", e))?;
+    Ok(Remembered { id, duplicate: false })
+}
+
+/// The agent's memories most similar to the query embedding, best first.
+pub fn recall(
+    db: &Database,
+    agent_id: i64,
+    query: &[f32],
+    model: &str,
+    limit: usize,
+    min_score: f32,
+) -> Result<Vec<MemoryMatch>, String> {
+    let memories = db
+        .get_agent_memories(agent_id)
+        .map_err(|e| format!("Failed to read agent memories: {}", e))?;
+    Ok(search(memories, query, model, limit, min_score))
+}
+
+/// Brute-force cosine similarity search. An agent holds at most a few
+/// thousand memories, so scanning them all is fast enough and needs no index.
+/// Memories embedded with another model are skipped.
+pub fn search(memories: Vec<AgentMemory>, query: &[f32], model: &str, limit: usize, min_score: f32) -> Vec<MemoryMatch> {
+    let mut matches: Vec<MemoryMatch> = memories
+        .into_iter()
+        .filter(|memory| memory.model == model && memory.embedding.len() == query.len())
+        .map(|memory| MemoryMatch {
+            score: cosine_similarity(&memory.embedding, query),
+            memory,
+        })
+        .filter(|m| m.score >= min_score)
+        .collect();
+
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    matches.truncate(limit);
+    matches
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a.sqrt() * norm_b.sqrt())
+}
+
+pub fn embed(embedder: &OllamaProvider, text: &str) -> Result<Vec<f32>, String> {
+    embedder
+        .embed(&[text.to_string()])
+        .map_err(|e| e.to_string())?
+        .pop()
+        .ok_or_else(|| "Embedding request returned nothing".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory(id: i64, embedding: Vec<f32>, model: &str) -> AgentMemory {
+        AgentMemory {
+            id: Some(id),
+            agent_id: 1,
+            content: format!("memory {}", id),
+            embedding,
+            model: model.to_string(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 0.0], &[-1.0, 0.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+    }
+
+    #[test]
+    fn test_search_ranks_and_filters() {
+        let memories = vec![
+            memory(1, vec![0.0, 1.0], "nomic-embed-text"),
+            memory(2, vec![1.0, 0.1], "nomic-embed-text"),
+            memory(3, vec![1.0, 1.0], "nomic-embed-text"),
+            memory(4, vec![1.0, 0.0], "mxbai-embed-large"), // other model
+            memory(5, vec![1.0, 0.0, 0.0], "nomic-embed-text"), // other dimensions
+        ];
+
+        let matches = search(memories, &[1.0, 0.0], "nomic-embed-text", 5, 0.5);
+        let ids: Vec<i64> = matches.iter().map(|m| m.memory.id.unwrap()).collect();
+        assert_eq!(ids, vec![2, 3]);
+        assert!(matches[0].score > matches[1].score);
+    }
+}
@@ -0,0 +1,252 @@
+//! Desktop notifications for finished agent runs, driven by the agent
+//! config's `notifications.on_success` / `notifications.on_failure` flags
+//! (both off when missing).
+//!
+//! Each agent's successes and failures are rate limited separately: after a
+//! notification is shown, further ones within `notification_min_interval_minutes`
+//! are recorded as "suppressed" and summed up in the next one that is shown,
+//! e.g. "Failed 6 times since 01:00". Every decision lands in the
+//! `notifications` table, which doubles as the history.
+
+use chrono::{DateTime, Local, Utc};
+use std::sync::{Arc, Mutex};
+
+use crate::agent_runner::AgentRun;
+use crate::database::{Database, NotificationRecord};
+use crate::settings;
+
+const APP_NAME: &str = "Personaliz";
+// Enough of stderr to say what went wrong without filling the screen
+const MAX_DETAIL_CHARS: usize = 200;
+
+/// Shows a native notification (notification center, Action Center or the
+/// freedesktop notification daemon).
+pub fn show_desktop_notification(title: &str, body: &str) -> Result<(), String> {
+    notify_rust::Notification::new()
+        .appname(APP_NAME)
+        .summary(title)
+        .body(body)
+        .show()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+/// Notifies about a finished run if the agent asks for it, showing it with
+/// `deliver(title, body)`. Returns what was recorded, or None if the agent
+/// doesn't want a notification for this outcome.
+pub fn notify_run(
+    db: &Arc<Mutex<Database>>,
+    run: &AgentRun,
+    deliver: impl Fn(&str, &str) -> Result<(), String>,
+) -> Option<NotificationRecord> {
+    notify_run_at(db, run, Utc::now(), deliver)
+}
+
+fn notify_run_at(
+    db: &Arc<Mutex<Database>>,
+    run: &AgentRun,
+    now: DateTime<Utc>,
+    deliver: impl Fn(&str, &str) -> Result<(), String>,
+) -> Option<NotificationRecord> {
+    let settings = settings::load_shared(db);
+    if !settings.notifications_enabled {
+        return None;
+    }
+
+    let outcome = if run.success { "success" } else { "failure" };
+    let (last_shown, suppressed) = {
+        let db = db.lock().unwrap();
+        let agent = db.get_agent_by_name(&run.agent_name).ok().flatten()?;
+        if !wants_notification(&agent.config_json, run.success) {
+            return None;
+        }
+
+        let last_shown = match db.get_last_shown_notification(run.agent_id, outcome) {
+            Ok(last) => last,
+            Err(e) => {
+                eprintln!("[Notifications] Failed to read history: {}", e);
+                None
+            }
+        };
+        let after = last_shown.as_ref().and_then(|n| n.id).unwrap_or(0);
+        let suppressed = db.count_suppressed_notifications(run.agent_id, outcome, after).unwrap_or(0);
+        (last_shown, suppressed)
+    };
+
+    let last_shown_at = last_shown
+        .as_ref()
+        .and_then(|n| DateTime::parse_from_rfc3339(&n.created_at).ok())
+        .map(|t| t.with_timezone(&Utc));
+    let min_interval = i64::try_from(settings.notification_min_interval_minutes)
+        .ok()
+        .and_then(chrono::Duration::try_minutes)
+        .unwrap_or(chrono::Duration::MAX);
+    let throttled = last_shown_at.is_some_and(|at| now - at < min_interval);
+
+    let grouped_count = suppressed + 1;
+    let mut record = NotificationRecord {
+        id: None,
+        agent_id: run.agent_id,
+        agent_name: run.agent_name.clone(),
+        outcome: outcome.to_string(),
+        title: title(run),
+        body: body(run, grouped_count, last_shown_at),
+        status: "suppressed".to_string(),
+        grouped_count: 1,
+        created_at: now.to_rfc3339(),
+    };
+
+    if !throttled {
+        record.grouped_count = grouped_count;
+        record.status = match deliver(&record.title, &record.body) {
+            Ok(()) => "shown".to_string(),
+            Err(e) => {
+                eprintln!("[Notifications] {}", e);
+                "failed".to_string()
+            }
+        };
+    }
+
+    match db.lock().unwrap().add_notification(&record) {
+        Ok(id) => record.id = Some(id),
+        Err(e) => eprintln!("[Notifications] Failed to record notification: {}", e),
+    }
+    Some(record)
+}
+
+/// Reads `notifications.on_success` / `on_failure` from an agent config.
+pub fn wants_notification(config_json: &str, success: bool) -> bool {
+    let config: serde_json::Value = serde_json::from_str(config_json).unwrap_or_default();
+    let flag = if success { "on_success" } else { "on_failure" };
+    config["notifications"][flag].as_bool().unwrap_or(false)
+}
+
+fn title(run: &AgentRun) -> String {
+    if run.success {
+        format!("{} finished", run.agent_name)
+    } else {
+        format!("{} failed", run.agent_name)
+    }
+}
+
+fn body(run: &AgentRun, grouped_count: i64, since: Option<DateTime<Utc>>) -> String {
+    let latest = if run.success {
+        format!("Finished in {:.1} s", run.duration_ms as f64 / 1000.0)
+    } else if run.timed_out {
+        format!("Timed out after {:.1} s", run.duration_ms as f64 / 1000.0)
+    } else {
+        let code = run.exit_code.map_or("unknown".to_string(), |c| c.to_string());
+        match run.stderr.lines().rev().map(str::trim).find(|l| !l.is_empty()) {
+            Some(line) => {
+                let line: String = line.chars().take(MAX_DETAIL_CHARS).collect();
+                format!("Exited with code {}: {}", code, line)
+            }
+            None => format!("Exited with code {}", code),
+        }
+    };
+
+    if grouped_count <= 1 {
+        return latest;
+    }
+    let verb = if run.success { "Succeeded" } else { "Failed" };
+    match since {
+        Some(since) => format!(
+            "{} {} times since {}. Latest: {}",
+            verb,
+            grouped_count,
+            since.with_timezone(&Local).format("%H:%M"),
+            latest
+        ),
+        None => format!("{} {} times. Latest: {}", verb, grouped_count, latest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Agent;
+
+    fn setup(notifications: serde_json::Value) -> (Arc<Mutex<Database>>, i64) {
+        let db = Database::in_memory().unwrap();
+        let config = serde_json::json!({"command": "node", "notifications": notifications}).to_string();
+        let id = db.create_agent(&Agent::from_config("monitor", &config).unwrap()).unwrap();
+        (Arc::new(Mutex::new(db)), id)
+    }
+
+    fn run(agent_id: i64, success: bool) -> AgentRun {
+        AgentRun {
+            agent_id,
+            agent_name: "monitor".to_string(),
+            success,
+            exit_code: Some(if success { 0 } else { 1 }),
+            timed_out: false,
+            duration_ms: 1500,
+            stdout: String::new(),
+            stderr: "Navigating...\nlogin page not found\n".to_string(),
+        }
+    }
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_follows_agent_flags() {
+        let (db, id) = setup(serde_json::json!({"on_success": false, "on_failure": true}));
+        let shown = Mutex::new(Vec::new());
+        let deliver = &|title: &str, body: &str| {
+            shown.lock().unwrap().push(format!("{}: {}", title, body));
+            Ok(())
+        };
+
+        assert!(notify_run(&db, &run(id, true), deliver).is_none());
+        let record = notify_run(&db, &run(id, false), deliver).unwrap();
+        assert_eq!(record.status, "shown");
+        assert_eq!(*shown.lock().unwrap(), ["monitor failed: Exited with code 1: login page not found"]);
+
+        let (db, id) = setup(serde_json::Value::Null);
+        assert!(notify_run(&db, &run(id, false), deliver).is_none());
+    }
+
+    #[test]
+    fn test_rate_limits_and_groups() {
+        let (db, id) = setup(serde_json::json!({"on_failure": true}));
+        let shown = Mutex::new(Vec::new());
+        let deliver = &|_: &str, body: &str| {
+            shown.lock().unwrap().push(body.to_string());
+            Ok(())
+        };
+
+        // Fails every 15 minutes overnight; only one notification an hour gets through
+        let times = ["01:00", "01:15", "01:30", "01:45", "02:00", "02:15"];
+        let statuses: Vec<String> = times
+            .iter()
+            .map(|t| {
+                let now = at(&format!("2026-10-18T{}:00Z", t));
+                notify_run_at(&db, &run(id, false), now, deliver).unwrap().status
+            })
+            .collect();
+        assert_eq!(statuses, ["shown", "suppressed", "suppressed", "suppressed", "shown", "suppressed"]);
+
+        let shown = shown.lock().unwrap();
+        assert_eq!(shown.len(), 2);
+        assert!(shown[1].starts_with("Failed 4 times since "), "{}", shown[1]);
+        assert_eq!(db.lock().unwrap().get_notifications(10).unwrap()[1].grouped_count, 4);
+
+        // Successes are limited separately
+        let agent = Agent::from_config("monitor", r#"{"notifications":{"on_success":true}}"#).unwrap();
+        db.lock().unwrap().update_agent(id, &agent).unwrap();
+        let record = notify_run_at(&db, &run(id, true), at("2026-10-18T02:20:00Z"), |_: &str, _: &str| Ok(())).unwrap();
+        assert_eq!(record.status, "shown");
+    }
+
+    #[test]
+    fn test_delivery_failure_is_recorded() {
+        let (db, id) = setup(serde_json::json!({"on_failure": true}));
+        let record = notify_run(&db, &run(id, false), |_: &str, _: &str| Err("no notification daemon".to_string())).unwrap();
+        assert_eq!(record.status, "failed");
+
+        settings::set(&db.lock().unwrap(), "notifications_enabled", serde_json::json!(false)).unwrap();
+        assert!(notify_run(&db, &run(id, false), |_: &str, _: &str| Ok(())).is_none());
+    }
+}
@@ -0,0 +1,117 @@
+//! Child processes started by the app: dependency checks, install steps and
+//! agent runs. npm and npx are batch scripts on Windows, so programs go
+//! through `cmd /C` there, and a run is killed together with everything it
+//! started (npm's node, a script's browser), not just the direct child.
+
+use std::io;
+use std::process::{Child, Command};
+use std::time::Duration;
+
+/// How long to keep reading a process's output after it ends, for a
+/// descendant that escaped the kill or was left running and still holds the
+/// pipes.
+pub const OUTPUT_GRACE: Duration = Duration::from_secs(2);
+
+/// A command for `program`, in its own process group so `kill_tree` reaches
+/// its descendants.
+pub fn command(program: &str) -> Command {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", program]);
+        command
+    } else {
+        Command::new(program)
+    };
+    own_group(&mut command);
+    command
+}
+
+/// Starts `command` in a new process group on unix. Windows needs nothing,
+/// since `kill_tree` walks the process tree there.
+pub fn own_group(command: &mut Command) -> &mut Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command
+}
+
+/// Kills `child` and its descendants, then reaps it. On unix that's the
+/// process group `own_group` put it in; on Windows, the tree under its pid.
+pub fn kill_tree(child: &mut Child) -> io::Result<()> {
+    let result = kill_descendants(child).and_then(|_| match child.kill() {
+        // Already exited, which kill_descendants may have caused
+        Err(e) if e.kind() == io::ErrorKind::InvalidInput => Ok(()),
+        other => other,
+    });
+    let _ = child.wait();
+    result
+}
+
+#[cfg(unix)]
+fn kill_descendants(child: &Child) -> io::Result<()> {
+    let group = child.id() as libc::pid_t;
+    // SAFETY: kill has no memory-safety preconditions; a negative pid names the group
+    if unsafe { libc::kill(-group, libc::SIGKILL) } == 0 {
+        return Ok(());
+    }
+    match io::Error::last_os_error() {
+        // The group is already gone
+        e if e.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+        e => Err(e),
+    }
+}
+
+#[cfg(windows)]
+fn kill_descendants(child: &Child) -> io::Result<()> {
+    let status = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()?;
+    // 128: no such process, i.e. it already exited
+    if status.success() || status.code() == Some(128) {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("taskkill exited with {}", status)))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn kill_descendants(_child: &Child) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::process::Stdio;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_kill_tree_closes_pipes_held_by_grandchildren() {
+        // The shell's background sleep inherits stdout; killing only the
+        // shell would leave the pipe open until the sleep ends
+        let mut child = command("sh")
+            .args(["-c", "sleep 30 & echo started; wait"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = String::new();
+            let _ = stdout.read_to_string(&mut output);
+            let _ = tx.send(output);
+        });
+        thread::sleep(Duration::from_millis(200));
+
+        kill_tree(&mut child).unwrap();
+        let output = rx.recv_timeout(Duration::from_secs(5)).expect("pipe still open");
+        assert_eq!(output.trim(), "started");
+    }
+}
@@ -0,0 +1,137 @@
+use serde_json::{Map, Value};
+
+use crate::database::Agent;
+
+/// Renders a template body, replacing `{{ path }}` with values from `context`.
+/// Paths are dot-separated, e.g. `{{agent.goal}}` or `{{event.payload.title}}`.
+/// Strings are inserted as-is and other values as JSON. Every variable must
+/// resolve; otherwise the error lists the missing ones.
+pub fn render(body: &str, context: &Value) -> Result<String, String> {
+    let mut output = String::with_capacity(body.len());
+    let mut missing = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| format!("Unclosed {{{{ at byte {}", body.len() - rest.len() + start))?;
+        let path = after[..end].trim();
+
+        match lookup(context, path) {
+            Some(Value::String(text)) => output.push_str(text),
+            Some(Value::Null) | None => missing.push(path.to_string()),
+            Some(value) => output.push_str(&value.to_string()),
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+
+    if !missing.is_empty() {
+        return Err(format!("Missing template variables: {}", missing.join(", ")));
+    }
+    Ok(output)
+}
+
+/// The variable paths a template uses, in order of first appearance.
+pub fn variables(body: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut rest = body;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        let path = after[..end].trim().to_string();
+        if !found.contains(&path) {
+            found.push(path);
+        }
+        rest = &after[end + 2..];
+    }
+    found
+}
+
+/// Builds the rendering context: `agent` (its columns plus the config's
+/// `metadata`), `event` (an event payload) and any extra top-level variables.
+pub fn build_context(agent: Option<&Agent>, event: Option<&Value>, extra: Option<&Value>) -> Value {
+    let mut context = Map::new();
+
+    if let Some(agent) = agent {
+        let metadata = serde_json::from_str::<Value>(&agent.config_json)
+            .ok()
+            .and_then(|config| config.get("metadata").cloned())
+            .unwrap_or(Value::Null);
+        context.insert(
+            "agent".to_string(),
+            serde_json::json!({
+                "name": agent.name,
+                "description": agent.description,
+                "role": agent.role,
+                "goal": agent.goal,
+                "schedule": agent.schedule,
+                "metadata": metadata,
+            }),
+        );
+    }
+    if let Some(event) = event {
+        context.insert("event".to_string(), event.clone());
+    }
+    if let Some(Value::Object(extra)) = extra {
+        for (key, value) in extra {
+            context.insert(key.clone(), value.clone());
+        }
+    }
+
+    Value::Object(context)
+}
+
+fn lookup<'a>(context: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.is_empty() {
+        return None;
+    }
+    path.split('.').try_fold(context, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|i| items.get(i)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent() -> Agent {
+        let config = r##"{"schedule":"hourly","metadata":{"role":"Researcher","goal":"Track #openclaw","hashtag":"#openclaw"}}"##;
+        Agent::from_config("Hashtag Watcher", config).unwrap()
+    }
+
+    #[test]
+    fn test_render_from_agent_and_event() {
+        let event = serde_json::json!({"title": "New post", "tags": ["ai", "rust"], "likes": 12});
+        let context = build_context(Some(&agent()), Some(&event), None);
+
+        let body = "You are a {{ agent.role }}. Goal: {{agent.goal}}.\n\
+                    Summarise \"{{event.title}}\" ({{event.likes}} likes, first tag {{event.tags.0}}) for {{agent.metadata.hashtag}}.";
+        assert_eq!(
+            render(body, &context).unwrap(),
+            "You are a Researcher. Goal: Track #openclaw.\nSummarise \"New post\" (12 likes, first tag ai) for #openclaw."
+        );
+    }
+
+    #[test]
+    fn test_render_reports_missing_variables() {
+        let context = build_context(Some(&agent()), None, Some(&serde_json::json!({"tone": "friendly"})));
+        let err = render("{{tone}} {{event.title}} {{agent.description}}", &context).unwrap_err();
+        assert_eq!(err, "Missing template variables: event.title, agent.description");
+
+        assert!(render("Hello {{name", &context).is_err());
+    }
+
+    #[test]
+    fn test_variables() {
+        assert_eq!(
+            variables("{{a}} {{ b.c }} {{a}} no vars here"),
+            vec!["a".to_string(), "b.c".to_string()]
+        );
+    }
+}
@@ -0,0 +1,378 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Local, NaiveTime, TimeZone, Utc, Weekday};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::agent_runner::{self, AgentRun};
+use crate::database::{Agent, Database};
+
+// Schedules have minute resolution, so checking twice a minute is plenty
+const TICK: Duration = Duration::from_secs(30);
+const DEFAULT_TIME: &str = "09:00";
+
+struct SchedulerState {
+    running: bool,
+    // Names of agents with a run in progress; an agent never overlaps itself
+    in_flight: HashSet<String>,
+    last_tick: Option<String>,
+    agents_scheduled: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SchedulerStatus {
+    pub running: bool,
+    pub last_tick: Option<String>,
+    pub agents_scheduled: usize,
+    pub agents_running: Vec<String>,
+}
+
+/// Receives every finished run, scheduled or started with `run_now`.
+pub type RunSink = Arc<dyn Fn(AgentRun) + Send + Sync>;
+
+/// Runs active agents according to their `schedule` ("hourly", "daily",
+/// "weekly") and `schedule_time`. A run that was missed while nothing was
+/// scheduling (e.g. the machine was off) happens once on the next tick.
+pub struct AgentScheduler {
+    db: Arc<Mutex<Database>>,
+    script_dir: PathBuf,
+    state: Arc<(Mutex<SchedulerState>, Condvar)>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+    sink: Arc<Mutex<Option<RunSink>>>,
+}
+
+impl AgentScheduler {
+    pub fn new(db: Arc<Mutex<Database>>, script_dir: PathBuf) -> Self {
+        AgentScheduler {
+            db,
+            script_dir,
+            state: Arc::new((
+                Mutex::new(SchedulerState {
+                    running: false,
+                    in_flight: HashSet::new(),
+                    last_tick: None,
+                    agents_scheduled: 0,
+                }),
+                Condvar::new(),
+            )),
+            thread: Mutex::new(None),
+            sink: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn set_run_sink(&self, sink: RunSink) {
+        *self.sink.lock().unwrap() = Some(sink);
+    }
+
+    /// Starts the scheduling thread. Returns false if it is already running.
+    pub fn start(&self) -> bool {
+        let mut thread = self.thread.lock().unwrap();
+        {
+            let mut s = self.state.0.lock().unwrap();
+            if s.running {
+                return false;
+            }
+            s.running = true;
+        }
+        if let Some(old) = thread.take() {
+            let _ = old.join();
+        }
+
+        let runner = self.runner();
+        let handle = thread::Builder::new()
+            .name("agent-scheduler".to_string())
+            .spawn(move || {
+                runner.run_loop();
+                runner.state.0.lock().unwrap().running = false;
+            })
+            .expect("Failed to spawn agent scheduler thread");
+
+        *thread = Some(handle);
+        true
+    }
+
+    /// Stops the scheduling thread. Runs already in progress finish on their
+    /// own, bounded by each agent's timeout. Returns false if it was not running.
+    pub fn stop(&self) -> bool {
+        let was_running = {
+            let (lock, cvar) = &*self.state;
+            let mut s = lock.lock().unwrap();
+            let was_running = s.running;
+            s.running = false;
+            cvar.notify_all();
+            was_running
+        };
+
+        let handle = self.thread.lock().unwrap().take();
+        if let Some(handle) = handle {
+            let _ = handle.join();
+        }
+        was_running
+    }
+
+    pub fn get_status(&self) -> SchedulerStatus {
+        let s = self.state.0.lock().unwrap();
+        let mut agents_running: Vec<String> = s.in_flight.iter().cloned().collect();
+        agents_running.sort();
+
+        SchedulerStatus {
+            running: s.running,
+            last_tick: s.last_tick.clone(),
+            agents_scheduled: s.agents_scheduled,
+            agents_running,
+        }
+    }
+
+    /// Starts a run of the named agent in the background, regardless of its
+    /// schedule. Fails if the agent is unknown or already running.
+    pub fn run_now(&self, name: &str) -> Result<(), String> {
+        let agent = self
+            .db
+            .lock()
+            .unwrap()
+            .get_agent_by_name(name)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("No agent named {}", name))?;
+
+        if self.runner().spawn_run(agent) {
+            Ok(())
+        } else {
+            Err(format!("{} is already running", name))
+        }
+    }
+
+    fn runner(&self) -> Runner {
+        Runner {
+            db: Arc::clone(&self.db),
+            script_dir: self.script_dir.clone(),
+            state: Arc::clone(&self.state),
+            sink: Arc::clone(&self.sink),
+        }
+    }
+}
+
+// The parts of the scheduler the background threads need
+#[derive(Clone)]
+struct Runner {
+    db: Arc<Mutex<Database>>,
+    script_dir: PathBuf,
+    state: Arc<(Mutex<SchedulerState>, Condvar)>,
+    sink: Arc<Mutex<Option<RunSink>>>,
+}
+
+impl Runner {
+    fn run_loop(&self) {
+        println!("[Scheduler] Started");
+        loop {
+            let agents = match self.db.lock().unwrap().get_all_agents() {
+                Ok(agents) => agents.into_iter().filter(|a| a.is_active).collect::<Vec<_>>(),
+                Err(e) => {
+                    eprintln!("[Scheduler] Failed to load agents: {}", e);
+                    Vec::new()
+                }
+            };
+
+            {
+                let mut s = self.state.0.lock().unwrap();
+                s.last_tick = Some(Utc::now().to_rfc3339());
+                s.agents_scheduled = agents.len();
+            }
+
+            let now = Local::now();
+            for agent in agents {
+                if self.agent_is_due(&agent, now) {
+                    self.spawn_run(agent);
+                }
+            }
+
+            let (lock, cvar) = &*self.state;
+            let s = lock.lock().unwrap();
+            let (s, _) = cvar.wait_timeout_while(s, TICK, |s| s.running).unwrap();
+            if !s.running {
+                break;
+            }
+        }
+        println!("[Scheduler] Stopped");
+    }
+
+    fn agent_is_due(&self, agent: &Agent, now: DateTime<Local>) -> bool {
+        let Some(agent_id) = agent.id else { return false };
+        // Never-run agents count from their creation so they wait for their first slot
+        let last_run = match self.db.lock().unwrap().get_last_agent_run(agent_id) {
+            Ok(last) => last.unwrap_or_else(|| agent.created_at.clone()),
+            Err(e) => {
+                eprintln!("[Scheduler] Failed to read last run of {}: {}", agent.name, e);
+                return false;
+            }
+        };
+        let Ok(last_run) = DateTime::parse_from_rfc3339(&last_run) else {
+            return false;
+        };
+
+        let config: serde_json::Value = serde_json::from_str(&agent.config_json).unwrap_or_default();
+        is_due(
+            &agent.schedule,
+            agent.schedule_time.as_deref(),
+            config["schedule_day"].as_str(),
+            last_run.with_timezone(&Utc),
+            now,
+        )
+    }
+
+    /// Runs the agent on its own thread. Returns false if it is already running.
+    fn spawn_run(&self, agent: Agent) -> bool {
+        if !self.state.0.lock().unwrap().in_flight.insert(agent.name.clone()) {
+            return false;
+        }
+        // Moved into the thread; dropped with it, or with the closure if the
+        // thread can't be spawned
+        let in_flight = InFlight { state: Arc::clone(&self.state), name: agent.name.clone() };
+
+        let runner = self.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("agent-{}", agent.name))
+            .spawn(move || {
+                let _in_flight = in_flight;
+                match agent_runner::run_agent(&runner.db, &agent, &runner.script_dir) {
+                    Ok(run) => {
+                        let sink = runner.sink.lock().unwrap().clone();
+                        if let Some(sink) = sink {
+                            sink(run);
+                        }
+                    }
+                    Err(e) => eprintln!("[Scheduler] {} could not run: {}", agent.name, e),
+                }
+            });
+
+        if let Err(e) = spawned {
+            eprintln!("[Scheduler] Failed to spawn run thread: {}", e);
+            return false;
+        }
+        true
+    }
+}
+
+/// Clears an agent's in-flight mark when its run ends, even if the run sink panicked.
+struct InFlight {
+    state: Arc<(Mutex<SchedulerState>, Condvar)>,
+    name: String,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Ok(mut s) = self.state.0.lock() {
+            s.in_flight.remove(&self.name);
+        }
+    }
+}
+
+/// Whether an agent last run at `last_run` should run again at `now`:
+/// hourly agents an hour after the last run, daily and weekly agents once
+/// their most recent `time` (on `day`, for weekly) has passed since then.
+pub fn is_due<Tz: TimeZone>(
+    schedule: &str,
+    time: Option<&str>,
+    day: Option<&str>,
+    last_run: DateTime<Utc>,
+    now: DateTime<Tz>,
+) -> bool {
+    let slot = match schedule {
+        "hourly" => return now.with_timezone(&Utc) - last_run >= ChronoDuration::hours(1),
+        "daily" => last_slot(&now, time, None),
+        "weekly" => last_slot(&now, time, Some(day.and_then(|d| d.parse().ok()).unwrap_or(Weekday::Mon))),
+        _ => None,
+    };
+    slot.is_some_and(|slot| last_run < slot)
+}
+
+// The latest `time` (on `weekday`, if given) at or before `now`, in `now`'s time zone
+fn last_slot<Tz: TimeZone>(now: &DateTime<Tz>, time: Option<&str>, weekday: Option<Weekday>) -> Option<DateTime<Utc>> {
+    let time = NaiveTime::parse_from_str(time.unwrap_or(DEFAULT_TIME), "%H:%M").ok()?;
+    let today = now.date_naive();
+
+    let mut date = match weekday {
+        Some(weekday) => {
+            let back = (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+            today - ChronoDuration::days(back as i64)
+        }
+        None => today,
+    };
+    let step = if weekday.is_some() { 7 } else { 1 };
+
+    loop {
+        // Times skipped by a DST change have no slot that day
+        let slot = now.timezone().from_local_datetime(&date.and_time(time)).earliest();
+        match slot {
+            Some(slot) if slot <= *now => return Some(slot.with_timezone(&Utc)),
+            _ => date -= ChronoDuration::days(step),
+        }
+        if today - date > ChronoDuration::days(14) {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_hourly() {
+        let now = at("2024-05-06T10:30:00Z");
+        assert!(is_due("hourly", None, None, at("2024-05-06T09:30:00Z"), now));
+        assert!(!is_due("hourly", None, None, at("2024-05-06T09:45:00Z"), now));
+    }
+
+    #[test]
+    fn test_daily() {
+        let now = at("2024-05-06T10:30:00Z");
+        // Ran yesterday, today's 09:00 has passed
+        assert!(is_due("daily", Some("09:00"), None, at("2024-05-05T09:00:05Z"), now));
+        // Already ran after today's 09:00
+        assert!(!is_due("daily", Some("09:00"), None, at("2024-05-06T09:00:05Z"), now));
+        // Today's 14:00 is still ahead and yesterday's was covered
+        assert!(!is_due("daily", Some("14:00"), None, at("2024-05-05T14:00:05Z"), now));
+        assert!(!is_due("daily", Some("25:00"), None, at("2024-01-01T00:00:00Z"), now));
+    }
+
+    #[test]
+    fn test_weekly() {
+        // 2024-05-06 is a Monday
+        let now = at("2024-05-08T12:00:00Z");
+        assert!(!is_due("weekly", Some("10:00"), Some("monday"), at("2024-05-06T10:00:05Z"), now));
+        assert!(is_due("weekly", Some("10:00"), Some("monday"), at("2024-04-29T10:00:05Z"), now));
+        assert!(is_due("weekly", Some("10:00"), Some("wednesday"), at("2024-05-06T10:00:05Z"), now));
+        assert!(!is_due("weekly", Some("13:00"), Some("wed"), at("2024-05-06T10:00:05Z"), now));
+    }
+
+    #[test]
+    fn test_unknown_schedule_never_due() {
+        assert!(!is_due("sometimes", None, None, at("2000-01-01T00:00:00Z"), Utc::now()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_panicking_sink_clears_in_flight() {
+        let db = Arc::new(Mutex::new(Database::in_memory().unwrap()));
+        let mut agent = Agent::from_config("quick", r#"{"command": "true"}"#).unwrap();
+        agent.id = Some(db.lock().unwrap().create_agent(&agent).unwrap());
+        let scheduler = AgentScheduler::new(db, std::env::temp_dir());
+        scheduler.set_run_sink(Arc::new(|_| panic!("sink failed")));
+
+        scheduler.run_now("quick").unwrap();
+        for _ in 0..50 {
+            if scheduler.get_status().agents_running.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(scheduler.get_status().agents_running.is_empty());
+        assert!(scheduler.run_now("quick").is_ok());
+    }
+}
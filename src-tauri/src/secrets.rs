@@ -0,0 +1,370 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::settings;
+
+pub const LLM_API_KEY: &str = "llm_api_key";
+
+/// Secret name for the API key of a specific provider in the fallback chain.
+pub fn provider_api_key(provider: &str) -> String {
+    format!("{}.{}", LLM_API_KEY, provider)
+}
+
+/// Secret name for the SMTP password of a notification channel.
+pub fn channel_password(channel: &str) -> String {
+    format!("notification_channel.{}", channel)
+}
+
+/// Secret name for a webhook header value, e.g. `Authorization`, of a
+/// notification channel. Header names are case-insensitive.
+pub fn channel_header(channel: &str, header: &str) -> String {
+    format!("notification_channel.{}.header.{}", channel, header.to_ascii_lowercase())
+}
+
+/// Overrides the generated key file as the source of the encryption passphrase.
+pub const PASSPHRASE_ENV: &str = "PERSONALIZ_SECRET_PASSPHRASE";
+
+const SECRETS_FILE: &str = "secrets.json";
+const KEY_FILE: &str = "secret.key";
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const NONCE_LEN: usize = 12;
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+const KEYRING_SERVICE: &str = "personaliz-desktop";
+
+#[derive(Serialize, Deserialize)]
+struct SecretsFile {
+    kdf: String,
+    iterations: u32,
+    salt: String,
+    entries: BTreeMap<String, String>, // name -> base64(nonce || ciphertext)
+}
+
+/// Stores secrets such as the LLM API key outside of `settings.json`.
+///
+/// The OS keyring (Keychain, Credential Manager, or the Secret Service on
+/// Linux desktops) is tried first. On headless Linux, other platforms, and
+/// whenever the keyring fails, secrets go to an AES-256-GCM encrypted file
+/// whose key is derived with PBKDF2 from `PERSONALIZ_SECRET_PASSPHRASE`, or
+/// from a random passphrase generated once into `secret.key`.
+#[derive(Clone)]
+pub struct SecretStore {
+    dir: PathBuf,
+    passphrase: Option<String>,
+    use_keyring: bool,
+}
+
+impl SecretStore {
+    pub fn open_default() -> Self {
+        SecretStore {
+            dir: settings::data_dir(),
+            passphrase: std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()),
+            use_keyring: keyring_available(),
+        }
+    }
+
+    /// A file-only store, used for headless setups and tests.
+    pub fn file_store(dir: PathBuf, passphrase: Option<String>) -> Self {
+        SecretStore {
+            dir,
+            passphrase,
+            use_keyring: false,
+        }
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        if self.use_keyring {
+            match keyring_set(name, value) {
+                Ok(()) => {
+                    // Don't leave an older copy behind in the file
+                    return self.file_remove(name);
+                }
+                Err(e) => eprintln!("[Secrets] OS keyring unavailable, using encrypted file: {}", e),
+            }
+        }
+        self.file_set(name, value)
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<String>, String> {
+        if self.use_keyring {
+            match keyring_get(name) {
+                Ok(Some(value)) => return Ok(Some(value)),
+                Ok(None) => {}
+                Err(e) => eprintln!("[Secrets] Failed to read {} from OS keyring, trying encrypted file: {}", name, e),
+            }
+        }
+        self.file_get(name)
+    }
+
+    pub fn has(&self, name: &str) -> Result<bool, String> {
+        Ok(self.get(name)?.is_some_and(|v| !v.is_empty()))
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        if self.use_keyring {
+            if let Err(e) = keyring_delete(name) {
+                eprintln!("[Secrets] Failed to remove {} from OS keyring: {}", name, e);
+            }
+        }
+        self.file_remove(name)
+    }
+
+    fn file_set(&self, name: &str, value: &str) -> Result<(), String> {
+        let mut file = self.read_file()?.unwrap_or_else(|| {
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            SecretsFile {
+                kdf: "pbkdf2-sha256".to_string(),
+                iterations: PBKDF2_ITERATIONS,
+                salt: BASE64.encode(salt),
+                entries: BTreeMap::new(),
+            }
+        });
+
+        let cipher = self.cipher(&file)?;
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, value.as_bytes())
+            .map_err(|_| "Failed to encrypt secret".to_string())?;
+
+        let mut blob = nonce.to_vec();
+        blob.extend_from_slice(&ciphertext);
+        file.entries.insert(name.to_string(), BASE64.encode(blob));
+
+        self.write_file(&file)
+    }
+
+    fn file_get(&self, name: &str) -> Result<Option<String>, String> {
+        let Some(file) = self.read_file()? else {
+            return Ok(None);
+        };
+        let Some(encoded) = file.entries.get(name) else {
+            return Ok(None);
+        };
+
+        let blob = BASE64
+            .decode(encoded)
+            .map_err(|e| format!("Corrupt secret {}: {}", name, e))?;
+        if blob.len() < NONCE_LEN {
+            return Err(format!("Corrupt secret {}", name));
+        }
+        let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+
+        let plaintext = self
+            .cipher(&file)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| format!("Failed to decrypt {}: wrong passphrase or corrupt file", name))?;
+
+        String::from_utf8(plaintext)
+            .map(Some)
+            .map_err(|e| format!("Corrupt secret {}: {}", name, e))
+    }
+
+    fn file_remove(&self, name: &str) -> Result<(), String> {
+        let Some(mut file) = self.read_file()? else {
+            return Ok(());
+        };
+        if file.entries.remove(name).is_some() {
+            self.write_file(&file)?;
+        }
+        Ok(())
+    }
+
+    fn read_file(&self) -> Result<Option<SecretsFile>, String> {
+        let path = self.dir.join(SECRETS_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read secrets: {}", e))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse secrets: {}", e))
+    }
+
+    fn write_file(&self, file: &SecretsFile) -> Result<(), String> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create secrets directory: {}", e))?;
+        let json = serde_json::to_string_pretty(file)
+            .map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        write_private(&self.dir.join(SECRETS_FILE), json.as_bytes())
+    }
+
+    fn cipher(&self, file: &SecretsFile) -> Result<Aes256Gcm, String> {
+        let salt = BASE64
+            .decode(&file.salt)
+            .map_err(|e| format!("Corrupt secrets salt: {}", e))?;
+        let passphrase = self.passphrase()?;
+
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), &salt, file.iterations, &mut key);
+        Ok(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)))
+    }
+
+    /// The configured passphrase, or the contents of the key file (created on first use).
+    fn passphrase(&self) -> Result<String, String> {
+        if let Some(ref passphrase) = self.passphrase {
+            return Ok(passphrase.clone());
+        }
+
+        let key_path = self.dir.join(KEY_FILE);
+        if key_path.exists() {
+            return fs::read_to_string(&key_path)
+                .map(|k| k.trim().to_string())
+                .map_err(|e| format!("Failed to read secret key: {}", e));
+        }
+
+        let mut random = [0u8; 32];
+        OsRng.fill_bytes(&mut random);
+        let generated = BASE64.encode(random);
+
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create secrets directory: {}", e))?;
+        write_private(&key_path, generated.as_bytes())?;
+        Ok(generated)
+    }
+}
+
+/// Writes a file readable only by the current user where the platform
+/// supports it. The file is created with those permissions rather than
+/// restricted afterwards, and written to a temporary file that is renamed
+/// over `path`, so a crash can't leave it truncated.
+fn write_private(path: &Path, content: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    // A leftover from a crash may have other permissions; create_new below needs it gone
+    let _ = fs::remove_file(&tmp);
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let written = options
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+
+    written.map_err(|e| {
+        let _ = fs::remove_file(&tmp);
+        format!("Failed to write {}: {}", path.display(), e)
+    })
+}
+
+// The Secret Service lives on the session bus, which headless machines
+// (servers, SSH sessions, containers) don't have
+fn keyring_available() -> bool {
+    if cfg!(target_os = "linux") {
+        std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some_and(|a| !a.is_empty())
+    } else {
+        cfg!(any(target_os = "macos", target_os = "windows"))
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn keyring_set(name: &str, value: &str) -> Result<(), String> {
+    keyring::Entry::new(KEYRING_SERVICE, name)
+        .and_then(|entry| entry.set_password(value))
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn keyring_get(name: &str) -> Result<Option<String>, String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, name).map_err(|e| e.to_string())?;
+    match entry.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "windows", target_os = "linux"))]
+fn keyring_delete(name: &str) -> Result<(), String> {
+    let entry = keyring::Entry::new(KEYRING_SERVICE, name).map_err(|e| e.to_string())?;
+    match entry.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn keyring_set(_name: &str, _value: &str) -> Result<(), String> {
+    Err("No OS keyring backend on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn keyring_get(_name: &str) -> Result<Option<String>, String> {
+    Ok(None)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "windows", target_os = "linux")))]
+fn keyring_delete(_name: &str) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempDir;
+
+    #[test]
+    fn test_roundtrip_with_generated_key() {
+        let dir = TempDir::new("secrets-roundtrip");
+        let store = SecretStore::file_store(dir.path().to_path_buf(), None);
+
+        assert_eq!(store.get(LLM_API_KEY).unwrap(), None);
+        store.set(LLM_API_KEY, "sk-test-123").unwrap();
+        assert_eq!(store.get(LLM_API_KEY).unwrap().as_deref(), Some("sk-test-123"));
+        assert!(store.has(LLM_API_KEY).unwrap());
+
+        // Never stored in clear text
+        let raw = fs::read_to_string(dir.path().join(SECRETS_FILE)).unwrap();
+        assert!(!raw.contains("sk-test-123"));
+
+        store.delete(LLM_API_KEY).unwrap();
+        assert!(!store.has(LLM_API_KEY).unwrap());
+    }
+
+    #[test]
+    fn test_wrong_passphrase_fails() {
+        let dir = TempDir::new("secrets-passphrase");
+        SecretStore::file_store(dir.path().to_path_buf(), Some("correct horse".to_string()))
+            .set(LLM_API_KEY, "sk-test-456")
+            .unwrap();
+
+        let wrong = SecretStore::file_store(dir.path().to_path_buf(), Some("battery staple".to_string()));
+        assert!(wrong.get(LLM_API_KEY).is_err());
+
+        let right = SecretStore::file_store(dir.path().to_path_buf(), Some("correct horse".to_string()));
+        assert_eq!(right.get(LLM_API_KEY).unwrap().as_deref(), Some("sk-test-456"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_files_are_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = TempDir::new("secrets-private");
+        let store = SecretStore::file_store(dir.path().to_path_buf(), None);
+        store.set(LLM_API_KEY, "sk-test-789").unwrap();
+        store.set(LLM_API_KEY, "sk-test-790").unwrap();
+
+        for file in [SECRETS_FILE, KEY_FILE] {
+            let mode = fs::metadata(dir.path().join(file)).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", file);
+        }
+        assert!(!dir.path().join("secrets.tmp").exists());
+        assert_eq!(store.get(LLM_API_KEY).unwrap().as_deref(), Some("sk-test-790"));
+    }
+}
@@ -0,0 +1,547 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::database::Database;
+use crate::secrets::{self, SecretStore};
+
+/// One entry in the LLM provider chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmProviderConfig {
+    pub provider: String,   // "local" or "openai" or "claude"
+    pub model: String,
+    #[serde(default)]
+    pub endpoint: String,   // empty = the provider's default
+}
+
+/// Price of a model in USD per million tokens, used to estimate LLM spend.
+/// `model` matches by prefix, so "gpt-4o" also covers "gpt-4o-2024-08-06".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub provider: String,   // as reported by the provider: "openai", "anthropic", "ollama"
+    pub model: String,
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+/// An outbound channel for agent run results, see channels.rs. `subject`
+/// and `body` are templates; empty uses the defaults there.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationChannel {
+    pub name: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub on_success: bool,
+    #[serde(default = "default_true")]
+    pub on_failure: bool,
+    #[serde(default)]
+    pub agents: Vec<String>,   // agent names to report on, empty = all
+    #[serde(default)]
+    pub subject: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(flatten)]
+    pub transport: ChannelTransport,
+}
+
+/// Where a channel delivers to, tagged by `"kind"`. The SMTP password and the
+/// values of `secret_headers` are kept in the secret store, see
+/// `secrets::channel_password` and `secrets::channel_header`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ChannelTransport {
+    Smtp {
+        host: String,
+        #[serde(default = "default_smtp_port")]
+        port: u16,
+        #[serde(default)]
+        username: String,
+        #[serde(default = "default_smtp_security")]
+        security: String,   // "starttls", "tls" or "none"
+        from: String,
+        to: Vec<String>,
+    },
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        #[serde(default)]
+        secret_headers: Vec<String>,   // header names, e.g. "Authorization"
+    },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_smtp_security() -> String {
+    "starttls".to_string()
+}
+
+/// Fields missing from stored settings (e.g. options added after they were
+/// saved) take their value from `AppSettings::defaults()`.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppSettings {
+    pub llm_provider: String,     // "local" or "openai" or "claude"
+    // Only read from legacy settings.json files; the key lives in the secret store
+    #[serde(skip_serializing)]
+    pub llm_api_key: String,
+    pub llm_model: String,        // e.g., "gpt-4", "claude-3", "phi3"
+    pub llm_endpoint: String,
+    pub poller_max_concurrency: usize,  // handler checks allowed to run at once
+    pub poller_check_timeout_seconds: u64,
+    pub poller_auto_start: bool,        // start the event poller when the app launches
+    pub poller_max_backoff_seconds: u64,
+    pub poller_failure_threshold: i64,  // consecutive failures before a handler is disabled, 0 = never
+    pub llm_fallback_providers: Vec<LlmProviderConfig>,  // tried in order when the primary is unreachable
+    pub llm_prices: Vec<ModelPrice>,
+    pub llm_monthly_budget_usd: f64,    // LLM calls are refused once this month's spend reaches it, 0 = no cap
+    pub llm_embedding_model: String,    // local Ollama model used for agent memory embeddings
+    pub backup_interval_hours: u64,     // automatic database backups, 0 = off
+    pub backup_keep: usize,             // automatic backups kept; older ones are deleted
+    pub notifications_enabled: bool,    // desktop notifications for agent runs (per agent: `notifications` in its config)
+    pub notification_min_interval_minutes: u64,  // per agent and outcome; runs in between are grouped, 0 = no limit
+    pub notification_channels: Vec<NotificationChannel>,  // email and webhook alerts, see channels.rs
+}
+
+/// Accepted values of `llm_provider` and `LlmProviderConfig::provider`.
+pub const LLM_PROVIDERS: &[&str] = &["local", "ollama", "openai", "claude", "anthropic"];
+
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self::defaults()
+    }
+}
+
+fn default_llm_prices() -> Vec<ModelPrice> {
+    let price = |provider: &str, model: &str, input: f64, output: f64| ModelPrice {
+        provider: provider.to_string(),
+        model: model.to_string(),
+        input_per_million: input,
+        output_per_million: output,
+    };
+    // List prices at the time of writing; local models are free
+    vec![
+        price("openai", "gpt-4o-mini", 0.15, 0.60),
+        price("openai", "gpt-4o", 2.50, 10.00),
+        price("openai", "gpt-4-turbo", 10.00, 30.00),
+        price("openai", "gpt-4", 30.00, 60.00),
+        price("openai", "gpt-3.5-turbo", 0.50, 1.50),
+        price("anthropic", "claude-3-5-haiku", 0.80, 4.00),
+        price("anthropic", "claude-3-5-sonnet", 3.00, 15.00),
+        price("anthropic", "claude-3-haiku", 0.25, 1.25),
+        price("anthropic", "claude-3-sonnet", 3.00, 15.00),
+        price("anthropic", "claude-3-opus", 15.00, 75.00),
+    ]
+}
+
+impl AppSettings {
+    pub fn defaults() -> Self {
+        AppSettings {
+            llm_provider: "local".to_string(),
+            llm_api_key: "".to_string(),
+            llm_model: "phi3".to_string(),
+            llm_endpoint: "http://localhost:11434/api/generate".to_string(),
+            poller_max_concurrency: 4,
+            poller_check_timeout_seconds: 30,
+            poller_auto_start: false,
+            poller_max_backoff_seconds: 3600,
+            poller_failure_threshold: 10,
+            llm_fallback_providers: Vec::new(),
+            llm_prices: default_llm_prices(),
+            llm_monthly_budget_usd: 0.0,
+            llm_embedding_model: "nomic-embed-text".to_string(),
+            backup_interval_hours: 24,
+            backup_keep: 7,
+            notifications_enabled: true,
+            notification_min_interval_minutes: 60,
+            notification_channels: Vec::new(),
+        }
+    }
+
+    /// The primary provider (`llm_provider`, `llm_model`, `llm_endpoint`)
+    /// followed by the fallbacks, in the order they should be tried.
+    pub fn llm_provider_chain(&self) -> Vec<LlmProviderConfig> {
+        let primary = LlmProviderConfig {
+            provider: self.llm_provider.clone(),
+            model: self.llm_model.clone(),
+            endpoint: self.llm_endpoint.clone(),
+        };
+        std::iter::once(primary)
+            .chain(self.llm_fallback_providers.iter().cloned())
+            .collect()
+    }
+}
+
+/// Directory holding the database, settings and secrets. `HOME` is used
+/// where `USERPROFILE` isn't set, so the CLI finds the same data on Unix.
+pub fn data_dir() -> PathBuf {
+    let home = std::env::var("USERPROFILE")
+        .or_else(|_| std::env::var("HOME"))
+        .unwrap_or_else(|_| ".".to_string());
+    PathBuf::from(home).join(".personaliz")
+}
+
+/// Project root holding package.json and the agent scripts:
+/// `PERSONALIZ_PROJECT_DIR`, else the current directory if it has a
+/// package.json, else its parent (the app runs from src-tauri).
+pub fn project_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("PERSONALIZ_PROJECT_DIR").filter(|d| !d.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    if cwd.join("package.json").exists() {
+        return cwd;
+    }
+    cwd.parent().map(PathBuf::from).unwrap_or(cwd)
+}
+
+/// The settings file used before settings moved into the database.
+pub fn get_settings_path() -> PathBuf {
+    data_dir().join("settings.json")
+}
+
+/// A setting as stored in the `settings` table, for settings screens that
+/// show what was changed and when.
+#[derive(Debug, Serialize)]
+pub struct SettingEntry {
+    pub key: String,
+    pub value: Value,
+    pub default: Value,
+    pub updated_at: Option<String>, // None = never changed from the default
+}
+
+/// Reads settings from the `settings` table. Each `AppSettings` field is one
+/// row holding its JSON value; missing rows use the default and rows that no
+/// longer validate are ignored.
+pub fn load(db: &Database) -> AppSettings {
+    match db.get_settings() {
+        Ok(rows) => from_rows(rows.iter().map(|row| (row.key.as_str(), row.value.as_str()))),
+        Err(e) => {
+            eprintln!("[Settings] Failed to read settings, using defaults: {}", e);
+            AppSettings::defaults()
+        }
+    }
+}
+
+/// `load` for callers holding the shared database handle.
+pub fn load_shared(db: &Arc<Mutex<Database>>) -> AppSettings {
+    match db.lock() {
+        Ok(db) => load(&db),
+        Err(e) => {
+            eprintln!("[Settings] Failed to lock database, using defaults: {}", e);
+            AppSettings::defaults()
+        }
+    }
+}
+
+/// Applies a partial update: `patch` is a JSON object of setting keys to new
+/// values. Every key is validated before anything is written, so a bad value
+/// leaves all settings unchanged. Returns the keys that changed.
+pub fn update(db: &Database, patch: &Value) -> Result<Vec<String>, String> {
+    let patch = patch.as_object().ok_or("Settings update must be a JSON object")?;
+
+    let errors: Vec<String> = patch
+        .iter()
+        .filter_map(|(key, value)| validate(key, value).err())
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    let current = to_map(&load(db))?;
+    let mut changed = Vec::new();
+    for (key, value) in patch {
+        if current.get(key) == Some(value) {
+            continue;
+        }
+        set(db, key, value.clone())?;
+        changed.push(key.clone());
+    }
+
+    Ok(changed)
+}
+
+/// Validates and stores a single setting.
+pub fn set(db: &Database, key: &str, value: Value) -> Result<(), String> {
+    validate(key, &value)?;
+    let json = serde_json::to_string(&value)
+        .map_err(|e| format!("Failed to serialize setting {}: {}", key, e))?;
+    db.set_setting(key, &json)
+        .map_err(|e| format!("Failed to save setting {}: {}", key, e))
+}
+
+/// Every setting with its current value, default and when it was last changed.
+pub fn entries(db: &Database) -> Result<Vec<SettingEntry>, String> {
+    let rows = db.get_settings().map_err(|e| format!("Failed to read settings: {}", e))?;
+    let current = to_map(&load(db))?;
+    let defaults = to_map(&AppSettings::defaults())?;
+
+    Ok(defaults
+        .into_iter()
+        .map(|(key, default)| SettingEntry {
+            value: current.get(&key).cloned().unwrap_or_else(|| default.clone()),
+            updated_at: rows.iter().find(|row| row.key == key).map(|row| row.updated_at.clone()),
+            key,
+            default,
+        })
+        .collect())
+}
+
+/// One-time import of the `settings.json` used by older versions. The file is
+/// renamed afterwards so it isn't imported again. A plaintext API key in it is
+/// moved into the secret store.
+pub fn import_legacy_file(db: &Database) -> Result<(), String> {
+    let path = get_settings_path();
+    if !path.exists() {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let legacy: AppSettings = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+    if !legacy.llm_api_key.is_empty() {
+        SecretStore::open_default().set(secrets::LLM_API_KEY, &legacy.llm_api_key)?;
+        println!("[Settings] Moved API key from settings.json to secure storage");
+    }
+
+    // Keys that fail validation keep their defaults rather than blocking the import
+    for (key, value) in to_map(&legacy)? {
+        if let Err(e) = set(db, &key, value) {
+            eprintln!("[Settings] Not importing {}: {}", key, e);
+        }
+    }
+
+    fs::rename(&path, path.with_extension("json.imported"))
+        .map_err(|e| format!("Failed to rename {}: {}", path.display(), e))?;
+    println!("[Settings] Imported settings.json into the database");
+    Ok(())
+}
+
+/// Checks that `key` is a known setting and `value` has its type and an
+/// acceptable value.
+pub fn validate(key: &str, value: &Value) -> Result<(), String> {
+    let mut map = to_map(&AppSettings::defaults())?;
+    if !map.contains_key(key) {
+        return Err(format!("Unknown setting: {}", key));
+    }
+    map.insert(key.to_string(), value.clone());
+
+    let settings: AppSettings = serde_json::from_value(Value::Object(map))
+        .map_err(|e| format!("Invalid value for {}: {}", key, e))?;
+
+    match key {
+        "llm_provider" => validate_provider(&settings.llm_provider),
+        "llm_model" if settings.llm_model.trim().is_empty() => Err("llm_model must not be empty".to_string()),
+        "llm_embedding_model" if settings.llm_embedding_model.trim().is_empty() => {
+            Err("llm_embedding_model must not be empty".to_string())
+        }
+        "llm_endpoint" => validate_endpoint(&settings.llm_endpoint),
+        "llm_fallback_providers" => settings.llm_fallback_providers.iter().try_for_each(|config| {
+            validate_provider(&config.provider)?;
+            validate_endpoint(&config.endpoint)?;
+            if config.model.trim().is_empty() {
+                return Err(format!("Fallback provider {} needs a model", config.provider));
+            }
+            Ok(())
+        }),
+        "llm_prices" => settings.llm_prices.iter().try_for_each(|price| {
+            if price.input_per_million < 0.0 || price.output_per_million < 0.0 {
+                return Err(format!("Price for {} must not be negative", price.model));
+            }
+            Ok(())
+        }),
+        "poller_max_concurrency" if settings.poller_max_concurrency == 0 => {
+            Err("poller_max_concurrency must be at least 1".to_string())
+        }
+        "poller_check_timeout_seconds" if settings.poller_check_timeout_seconds == 0 => {
+            Err("poller_check_timeout_seconds must be at least 1".to_string())
+        }
+        "notification_channels" => validate_channels(&settings.notification_channels),
+        "backup_keep" if settings.backup_keep == 0 => Err("backup_keep must be at least 1".to_string()),
+        "poller_failure_threshold" if settings.poller_failure_threshold < 0 => {
+            Err("poller_failure_threshold must be 0 (never) or more".to_string())
+        }
+        "llm_monthly_budget_usd" if !settings.llm_monthly_budget_usd.is_finite() || settings.llm_monthly_budget_usd < 0.0 => {
+            Err("llm_monthly_budget_usd must be 0 (no cap) or more".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_channels(channels: &[NotificationChannel]) -> Result<(), String> {
+    for (i, channel) in channels.iter().enumerate() {
+        if channel.name.trim().is_empty() {
+            return Err("Notification channels need a name".to_string());
+        }
+        if channels[..i].iter().any(|c| c.name == channel.name) {
+            return Err(format!("There is more than one notification channel named {}", channel.name));
+        }
+        match &channel.transport {
+            ChannelTransport::Smtp { host, security, from, to, .. } => {
+                if host.trim().is_empty() || from.trim().is_empty() || to.is_empty() {
+                    return Err(format!("Channel {} needs a host, a from address and at least one recipient", channel.name));
+                }
+                if !["starttls", "tls", "none"].contains(&security.as_str()) {
+                    return Err(format!("Channel {}: security must be starttls, tls or none", channel.name));
+                }
+            }
+            ChannelTransport::Webhook { url, headers, .. } => {
+                if url.is_empty() {
+                    return Err(format!("Channel {} needs a URL", channel.name));
+                }
+                validate_endpoint(url)?;
+                if let Some(name) = headers.keys().find(|name| is_credential_header(name)) {
+                    return Err(format!(
+                        "Channel {}: list {} under secret_headers and save its value in the secret store",
+                        channel.name, name
+                    ));
+                }
+            }
+        }
+        crate::channels::check_templates(channel).map_err(|e| format!("Channel {}: {}", channel.name, e))?;
+    }
+    Ok(())
+}
+
+// Headers that usually carry credentials, which don't belong in the settings table
+fn is_credential_header(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name == "authorization" || name == "cookie" || ["token", "key", "secret", "password"].iter().any(|s| name.contains(s))
+}
+
+fn validate_provider(provider: &str) -> Result<(), String> {
+    if LLM_PROVIDERS.contains(&provider) {
+        Ok(())
+    } else {
+        Err(format!("Unknown LLM provider {:?}, expected one of {}", provider, LLM_PROVIDERS.join(", ")))
+    }
+}
+
+/// Endpoints must be http(s) URLs; empty means the provider's default.
+fn validate_endpoint(endpoint: &str) -> Result<(), String> {
+    if endpoint.is_empty() {
+        return Ok(());
+    }
+    match reqwest::Url::parse(endpoint) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok(()),
+        _ => Err(format!("Endpoint {:?} is not an http(s) URL", endpoint)),
+    }
+}
+
+fn from_rows<'a>(rows: impl Iterator<Item = (&'a str, &'a str)>) -> AppSettings {
+    let defaults = AppSettings::defaults();
+    let Ok(mut map) = to_map(&defaults) else {
+        return defaults;
+    };
+
+    for (key, json) in rows {
+        let value = serde_json::from_str(json).map_err(|e| e.to_string());
+        match value.and_then(|value| validate(key, &value).map(|_| value)) {
+            Ok(value) => {
+                map.insert(key.to_string(), value);
+            }
+            Err(e) => eprintln!("[Settings] Ignoring stored {}: {}", key, e),
+        }
+    }
+
+    serde_json::from_value(Value::Object(map)).unwrap_or(defaults)
+}
+
+fn to_map(settings: &AppSettings) -> Result<Map<String, Value>, String> {
+    match serde_json::to_value(settings) {
+        Ok(Value::Object(map)) => Ok(map),
+        Ok(_) => Err("Settings did not serialize to an object".to_string()),
+        Err(e) => Err(format!("Failed to serialize settings: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_rows_overlays_defaults() {
+        let settings = from_rows(
+            [
+                ("llm_model", r#""llama3""#),
+                ("poller_max_concurrency", "8"),
+                ("poller_check_timeout_seconds", r#""soon""#), // wrong type, ignored
+                ("removed_option", "true"),                    // unknown, ignored
+            ]
+            .into_iter(),
+        );
+
+        assert_eq!(settings.llm_model, "llama3");
+        assert_eq!(settings.poller_max_concurrency, 8);
+        assert_eq!(settings.poller_check_timeout_seconds, 30);
+        assert_eq!(settings.llm_provider, "local");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("poller_auto_start", &Value::Bool(true)).is_ok());
+        assert!(validate("poller_auto_start", &Value::from("yes")).is_err());
+        assert!(validate("poller_max_concurrency", &Value::from(0)).is_err());
+        assert!(validate("llm_api_key", &Value::from("sk-123")).is_err());
+        assert!(validate("nonexistent", &Value::Null).is_err());
+
+        assert!(validate("llm_provider", &Value::from("openai")).is_ok());
+        assert!(validate("llm_provider", &Value::from("gemini")).is_err());
+        assert!(validate("llm_endpoint", &Value::from("http://localhost:1234/v1/chat/completions")).is_ok());
+        assert!(validate("llm_endpoint", &Value::from("localhost:11434")).is_err());
+        let fallbacks = serde_json::json!([{"provider": "openai", "model": "gpt-4o", "endpoint": "ftp://x"}]);
+        assert!(validate("llm_fallback_providers", &fallbacks).is_err());
+    }
+
+    #[test]
+    fn test_validate_channels() {
+        let email = serde_json::json!({
+            "name": "ops", "kind": "smtp", "host": "smtp.example.com", "from": "bot@example.com", "to": ["ops@example.com"],
+        });
+        let settings: AppSettings = serde_json::from_value(serde_json::json!({"notification_channels": [email]})).unwrap();
+        assert!(matches!(
+            &settings.notification_channels[0].transport,
+            ChannelTransport::Smtp { port: 587, security, .. } if security == "starttls"
+        ));
+        assert!(settings.notification_channels[0].on_failure && !settings.notification_channels[0].on_success);
+        assert!(validate("notification_channels", &serde_json::json!([email])).is_ok());
+
+        let webhook = serde_json::json!({"name": "ops", "kind": "webhook", "url": "https://chat.example.com/hook"});
+        assert!(validate("notification_channels", &serde_json::json!([email, webhook])).is_err()); // same name
+        assert!(validate("notification_channels", &serde_json::json!([{"name": "x", "kind": "pager"}])).is_err());
+        let plaintext_token = serde_json::json!([{"name": "x", "kind": "webhook", "url": "https://h", "headers": {"X-Api-Key": "k"}}]);
+        assert!(validate("notification_channels", &plaintext_token).is_err());
+        let bad_url = serde_json::json!([{"name": "x", "kind": "webhook", "url": "chat.example.com"}]);
+        assert!(validate("notification_channels", &bad_url).is_err());
+        let no_recipients = serde_json::json!([{"name": "x", "kind": "smtp", "host": "h", "from": "a@b.c", "to": []}]);
+        assert!(validate("notification_channels", &no_recipients).is_err());
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        // A settings.json written before the poller options existed
+        let old: AppSettings = serde_json::from_str(
+            r#"{"llm_provider":"openai","llm_api_key":"","llm_model":"gpt-4o","llm_endpoint":""}"#,
+        )
+        .unwrap();
+        assert_eq!(old.llm_provider, "openai");
+        assert_eq!(old.poller_max_concurrency, 4);
+        assert_eq!(old.poller_failure_threshold, 10);
+        assert!(!old.llm_prices.is_empty());
+
+        let empty: AppSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(empty.llm_model, "phi3");
+    }
+}
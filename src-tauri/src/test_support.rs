@@ -0,0 +1,222 @@
+//! Helpers shared by unit tests.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// An empty directory under the system temp dir, unique to this call and
+/// removed on drop, including when an assertion fails first.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "personaliz-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::SeqCst)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create temp dir");
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[derive(Debug)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).expect("request body is not JSON")
+    }
+}
+
+/// A throwaway HTTP server on localhost that answers one connection per
+/// canned `(status, content type, body)` response, in order.
+pub struct MockServer {
+    pub url: String,
+    requests: Receiver<RecordedRequest>,
+}
+
+impl MockServer {
+    pub fn start(responses: Vec<(u16, &'static str, String)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock server");
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (status, content_type, body) in responses {
+                let Ok((stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut reader = BufReader::new(stream);
+                let Some(request) = read_request(&mut reader) else {
+                    continue;
+                };
+                let _ = tx.send(request);
+
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    content_type,
+                    body.len(),
+                    body
+                );
+                let mut stream = reader.into_inner();
+                let _ = stream.write_all(response.as_bytes());
+                let _ = stream.flush();
+            }
+        });
+
+        MockServer { url, requests: rx }
+    }
+
+    /// The next request the server received.
+    pub fn request(&self) -> RecordedRequest {
+        self.requests
+            .recv_timeout(Duration::from_secs(5))
+            .expect("mock server received no request")
+    }
+}
+
+/// An email as received by `MockSmtpServer`.
+#[derive(Debug)]
+pub struct ReceivedMail {
+    pub from: String,
+    pub to: Vec<String>,
+    pub data: String, // headers and body as sent
+}
+
+/// A throwaway SMTP server on localhost that accepts one plain-text session
+/// without TLS or authentication.
+pub struct MockSmtpServer {
+    pub port: u16,
+    messages: Receiver<ReceivedMail>,
+}
+
+impl MockSmtpServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind mock SMTP server");
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let Ok((stream, _)) = listener.accept() else {
+                return;
+            };
+            let mut writer = stream.try_clone().unwrap();
+            let mut reply = |line: &str| {
+                let _ = writer.write_all(format!("{}\r\n", line).as_bytes());
+            };
+            let address = |line: &str| line.split(['<', '>']).nth(1).unwrap_or_default().to_string();
+
+            reply("220 mock ESMTP");
+            let mut reader = BufReader::new(stream);
+            let mut mail = ReceivedMail { from: String::new(), to: Vec::new(), data: String::new() };
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 0) {
+                let command = line.to_ascii_uppercase();
+                if command.starts_with("MAIL FROM") {
+                    mail.from = address(&line);
+                    reply("250 OK");
+                } else if command.starts_with("RCPT TO") {
+                    mail.to.push(address(&line));
+                    reply("250 OK");
+                } else if command.starts_with("DATA") {
+                    reply("354 End data with <CR><LF>.<CR><LF>");
+                    let mut data = String::new();
+                    loop {
+                        let mut data_line = String::new();
+                        if reader.read_line(&mut data_line).unwrap_or(0) == 0 || data_line == ".\r\n" {
+                            break;
+                        }
+                        data.push_str(&data_line);
+                    }
+                    mail.data = data;
+                    reply("250 Queued");
+                } else if command.starts_with("QUIT") {
+                    reply("221 Bye");
+                    break;
+                } else {
+                    // EHLO, NOOP, RSET
+                    reply("250 mock");
+                }
+                line.clear();
+            }
+            let _ = tx.send(mail);
+        });
+
+        MockSmtpServer { port, messages: rx }
+    }
+
+    /// The message the server received, once the client has hung up.
+    pub fn message(&self) -> ReceivedMail {
+        self.messages
+            .recv_timeout(Duration::from_secs(5))
+            .expect("mock SMTP server received no message")
+    }
+}
+
+fn read_request<R: Read>(reader: &mut BufReader<R>) -> Option<RecordedRequest> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((k, v)) = line.split_once(':') {
+            headers.push((k.trim().to_string(), v.trim().to_string()));
+        }
+    }
+
+    let length = headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
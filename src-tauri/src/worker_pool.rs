@@ -0,0 +1,93 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// Fixed-size pool of worker threads pulling jobs from a shared queue.
+/// Dropping the pool lets queued jobs finish and joins every worker.
+pub struct WorkerPool {
+    sender: Option<mpsc::Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn new(name: &str, size: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..size.max(1))
+            .map(|i| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("{}-{}", name, i))
+                    .spawn(move || loop {
+                        let job = receiver.lock().unwrap().recv();
+                        match job {
+                            Ok(job) => {
+                                // A panicking job must not take the worker down with it
+                                if panic::catch_unwind(AssertUnwindSafe(job)).is_err() {
+                                    eprintln!("[WorkerPool] Job panicked");
+                                }
+                            }
+                            Err(_) => break, // Pool dropped
+                        }
+                    })
+                    .expect("Failed to spawn worker thread")
+            })
+            .collect();
+
+        WorkerPool {
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        if let Some(ref sender) = self.sender {
+            if sender.send(Box::new(job)).is_err() {
+                eprintln!("[WorkerPool] Failed to queue job: workers have exited");
+            }
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // Closing the channel makes each worker exit once the queue is drained
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_runs_all_jobs_before_drop_returns() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        {
+            let pool = WorkerPool::new("test", 3);
+            for _ in 0..20 {
+                let counter = Arc::clone(&counter);
+                pool.execute(move || {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                });
+            }
+            pool.execute(|| panic!("boom"));
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 20);
+    }
+}
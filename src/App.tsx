@@ -1,5 +1,6 @@
 import { useState, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 export default function App() {
 
@@ -41,6 +42,7 @@ export default function App() {
   // LLM Settings
   const [llmProvider, setLlmProvider] = useState("local"); // "local", "openai", "claude"
   const [llmApiKey, setLlmApiKey] = useState("");
+  const [hasApiKey, setHasApiKey] = useState(false); // key is kept in secure storage, never sent back
   const [llmModel, setLlmModel] = useState("phi3");
   const [llmEndpoint, setLlmEndpoint] = useState("http://localhost:11434/api/generate");
 
@@ -56,12 +58,48 @@ export default function App() {
     checkDeps();
   }, []);
 
+  // Keep the settings form in sync when settings change elsewhere
+  useEffect(() => {
+    const unlisten = listen<{ keys: string[], settings: any }>("settings_changed", (event) => {
+      const settings = event.payload.settings;
+      setLlmProvider(settings.llm_provider);
+      setLlmModel(settings.llm_model);
+      setLlmEndpoint(settings.llm_endpoint);
+      addLog(`[SYSTEM] Settings changed: ${event.payload.keys.join(", ")}`);
+    });
+    return () => { unlisten.then((fn) => fn()); };
+  }, []);
+
+  // Installer progress: output goes to the log, step results to the chat
+  useEffect(() => {
+    const unlisten = listen<any>("install-progress", (event) => {
+      const e = event.payload;
+      const step = `[${e.step_index + 1}/${e.step_count}] ${e.step_id}`;
+      if (e.status === "output") addLog(`[INSTALL] ${e.line}`);
+      else if (e.status === "started") addMessage(`Assistant: ⏳ ${step}: ${e.line}`);
+      else if (e.status === "succeeded") addMessage(`Assistant: ✅ ${step}`);
+      else if (e.status === "failed") addMessage(`Assistant: ❌ ${step} failed${e.line ? ": " + e.line : ""}`);
+      else if (e.status === "cancelled") addMessage(`Assistant: ⏹️ ${step} skipped`);
+      else if (e.status === "done") addMessage("Assistant: Installation finished. Type 'check dependencies' to verify.");
+    });
+    return () => { unlisten.then((fn) => fn()); };
+  }, []);
+
+  // Agent runs finished by the background daemon
+  useEffect(() => {
+    const unlisten = listen<any>("agent-run", (event) => {
+      const run = event.payload;
+      addLog(`[DAEMON] ${run.agent_name} ${run.success ? "succeeded" : "failed"} in ${run.duration_ms} ms`);
+    });
+    return () => { unlisten.then((fn) => fn()); };
+  }, []);
+
   const loadSettings = async () => {
     try {
       const settingsJson = await invoke("load_settings") as string;
       const settings = JSON.parse(settingsJson);
       setLlmProvider(settings.llm_provider || "local");
-      setLlmApiKey(settings.llm_api_key || "");
+      setHasApiKey(await invoke("has_api_key") as boolean);
       setLlmModel(settings.llm_model || "phi3");
       setLlmEndpoint(settings.llm_endpoint || "http://localhost:11434/api/generate");
       addLog("[SYSTEM] Settings loaded");
@@ -73,12 +111,14 @@ export default function App() {
 
   const saveSettings = async () => {
     try {
-      await invoke("save_settings", {
-        llmProvider,
-        llmApiKey,
-        llmModel,
-        llmEndpoint
-      });
+      const patch: Record<string, string> = {
+        llm_provider: llmProvider,
+        llm_model: llmModel,
+        llm_endpoint: llmEndpoint,
+      };
+      if (llmApiKey) patch.llm_api_key = llmApiKey;
+      await invoke("update_settings", { patch });
+      if (llmApiKey) setHasApiKey(true);
       addMessage("Assistant: ✅ Settings saved successfully!");
       addLog("[SYSTEM] Settings saved");
       setSettingsOpen(false);
@@ -93,8 +133,10 @@ export default function App() {
       const deps = JSON.parse(depsJson);
       setDependencies(deps);
       addLog(`[SYSTEM] Running on ${deps.os}`);
+      return deps;
     } catch (err) {
       console.error("Failed to check dependencies:", err);
+      return null;
     }
   };
 
@@ -158,70 +200,9 @@ export default function App() {
   const callLLM = async (prompt: string): Promise<string> => {
     addLog(`[LLM] Using ${llmProvider} model: ${llmModel}`);
 
-    if (llmProvider === "local") {
-      // Local Ollama
-      const response = await fetch(llmEndpoint, {
-        method: "POST",
-        headers: { "Content-Type": "application/json" },
-        body: JSON.stringify({
-          model: llmModel,
-          prompt: prompt,
-          stream: false
-        })
-      });
-
-      if (!response.ok) {
-        throw new Error(`Ollama returned ${response.status}: ${response.statusText}`);
-      }
-
-      const data = await response.json();
-      return data.response.trim();
-    } else if (llmProvider === "openai") {
-      // OpenAI API
-      const response = await fetch("https://api.openai.com/v1/chat/completions", {
-        method: "POST",
-        headers: {
-          "Content-Type": "application/json",
-          "Authorization": `Bearer ${llmApiKey}`
-        },
-        body: JSON.stringify({
-          model: llmModel,
-          messages: [{ role: "user", content: prompt }],
-          temperature: 0.7
-        })
-      });
-
-      if (!response.ok) {
-        throw new Error(`OpenAI returned ${response.status}: ${response.statusText}`);
-      }
-
-      const data = await response.json();
-      return data.choices[0].message.content.trim();
-    } else if (llmProvider === "claude") {
-      // Claude API
-      const response = await fetch("https://api.anthropic.com/v1/messages", {
-        method: "POST",
-        headers: {
-          "Content-Type": "application/json",
-          "x-api-key": llmApiKey,
-          "anthropic-version": "2023-06-01"
-        },
-        body: JSON.stringify({
-          model: llmModel,
-          max_tokens: 1024,
-          messages: [{ role: "user", content: prompt }]
-        })
-      });
-
-      if (!response.ok) {
-        throw new Error(`Claude returned ${response.status}: ${response.statusText}`);
-      }
-
-      const data = await response.json();
-      return data.content[0].text.trim();
-    }
-
-    throw new Error("Unknown LLM provider");
+    // The Rust side picks the provider from saved settings and holds the API key
+    const result = await invoke("llm_complete", { prompt }) as string;
+    return JSON.parse(result).content;
   };
 
   // ===================================
@@ -257,33 +238,99 @@ export default function App() {
       return;
     }
 
+    // ===================================
+    // INSTALL DEPENDENCIES
+    // ===================================
+    if(lower === "install dependencies"){
+      try {
+        await invoke("start_install");
+        addMessage("Assistant: Installing dependencies... type 'cancel install' to stop.");
+      } catch (err) {
+        handleError("Install", err);
+      }
+      return;
+    }
+
+    if(lower === "cancel install"){
+      try {
+        addMessage("Assistant: " + await invoke("cancel_install"));
+      } catch (err) {
+        handleError("Install", err);
+      }
+      return;
+    }
+
+    // ===================================
+    // BACKGROUND DAEMON
+    // ===================================
+    if(lower === "start daemon" || lower === "stop daemon"){
+      try {
+        addMessage("Assistant: " + await invoke(lower === "start daemon" ? "start_daemon" : "stop_daemon"));
+      } catch (err) {
+        handleError("Daemon", err);
+      }
+      return;
+    }
+
+    if(lower === "daemon status"){
+      try {
+        const daemon = JSON.parse(await invoke("get_daemon_status") as string);
+        if (daemon.running) {
+          const { scheduler, poller } = daemon.status;
+          addMessage(`Assistant: 🟢 Daemon running since ${daemon.status.started_at}`);
+          addMessage(`  • ${scheduler.agents_scheduled} agents scheduled, running: ${scheduler.agents_running.join(", ") || "none"}`);
+          addMessage(`  • Event poller ${poller.running ? "on" : "off"}, ${poller.handlers_scheduled} handlers`);
+        } else {
+          addMessage("Assistant: ⚪ Daemon not running. Type 'start daemon' to keep agents running after the window closes.");
+        }
+      } catch (err) {
+        handleError("Daemon", err);
+      }
+      return;
+    }
+
+    if(lower === "notifications" || lower === "show notifications"){
+      try {
+        const history = JSON.parse(await invoke("get_notification_history", { limit: 10 }) as string);
+        if (history.length === 0) {
+          addMessage("Assistant: No notifications yet. Agents notify when their config sets notifications.on_success or on_failure.");
+        } else {
+          addMessage("Assistant: 🔔 Recent notifications:");
+          history.forEach((n: any) => {
+            const held = n.status === "suppressed" ? " (grouped)" : n.status === "failed" ? " (not delivered)" : "";
+            addMessage(`  • ${new Date(n.created_at).toLocaleString()} ${n.title}${held}: ${n.body}`);
+          });
+        }
+      } catch (err) {
+        handleError("Notifications", err);
+      }
+      return;
+    }
+
     // ===================================
     // CHECK DEPENDENCIES
     // ===================================
     if(lower === "check dependencies" || lower === "check setup" || lower === "system check"){
       addMessage("Assistant: Checking system dependencies...");
       try {
-        await checkDeps();
-        if (dependencies) {
+        const report = await checkDeps();
+        if (report) {
           addMessage("Assistant: 📋 System Status:");
-          addMessage(`  • OS: ${dependencies.os}`);
-          addMessage(`  • Node.js: ${dependencies.node ? '✅ ' + dependencies.node_version : '❌ Not found'}`);
-          addMessage(`  • npm: ${dependencies.npm ? '✅ ' + dependencies.npm_version : '❌ Not found'}`);
-          addMessage(`  • Playwright: ${dependencies.playwright ? '✅ Installed' : '❌ Not installed'}`);
-          addMessage(`  • Ollama: ${dependencies.ollama ? '✅ Running' : '❌ Not running'}`);
-          addMessage(`  • OpenClaw: ${dependencies.openclaw ? '✅ Found' : '❌ Not found'}`);
+          addMessage(`  • OS: ${report.os}`);
+          for (const dep of report.dependencies) {
+            const status = dep.installed && dep.meets_minimum ? "✅" : "❌";
+            addMessage(`  • ${dep.name}: ${status} ${dep.version || (dep.installed ? "Found" : "Not found")}`);
+          }
           addMessage("");
-          
-          const issues = [];
-          if (!dependencies.node) issues.push("Install Node.js from nodejs.org");
-          if (!dependencies.npm) issues.push("npm should come with Node.js");
-          if (!dependencies.playwright) issues.push("Run: npx playwright install chromium");
-          if (!dependencies.ollama) issues.push("Install Ollama from ollama.ai and run: ollama serve");
-          if (!dependencies.openclaw) issues.push("Run: setup openclaw");
+
+          const issues = report.dependencies
+            .filter((dep: any) => dep.remediation)
+            .map((dep: any) => dep.remediation);
 
           if (issues.length > 0) {
             addMessage("Assistant: 💡 Setup Required:");
-            issues.forEach(issue => addMessage(`  • ${issue}`));
+            issues.forEach((issue: string) => addMessage(`  • ${issue}`));
+            addMessage("Assistant: Type 'install dependencies' to fix what can be installed automatically.");
           } else {
             addMessage("Assistant: ✅ All dependencies are ready!");
           }
@@ -1249,7 +1296,7 @@ Return ONLY the post text, no explanations.`,
                 type="password"
                 value={llmApiKey}
                 onChange={(e)=>setLlmApiKey(e.target.value)}
-                placeholder="Enter your API key"
+                placeholder={hasApiKey ? "Saved - leave blank to keep it" : "Enter your API key"}
                 style={{width:"100%", padding:5}}
               />
             </div>
@@ -1285,13 +1332,16 @@ Return ONLY the post text, no explanations.`,
             <b>Current Status:</b><br/>
             Provider: {llmProvider}<br/>
             Model: {llmModel}<br/>
-            {llmProvider !== "local" && `API Key: ${llmApiKey ? "✅ Set" : "❌ Not set"}`}
+            {llmProvider !== "local" && `API Key: ${llmApiKey || hasApiKey ? "✅ Set" : "❌ Not set"}`}
           </div>
 
           {dependencies && (
             <div style={{marginBottom:15, padding:10, background:"#e8f4f8", borderRadius:5}}>
               <b>System:</b><br/>
-              OS: {dependencies.os} | Node: {dependencies.node ? dependencies.node_version : "❌"} | Ollama: {dependencies.ollama ? "✅" : "❌"}
+              OS: {dependencies.os} | {dependencies.dependencies
+                .filter((dep: any) => dep.name === "node" || dep.name === "ollama-server")
+                .map((dep: any) => `${dep.name}: ${dep.installed && dep.meets_minimum ? (dep.version || "✅") : "❌"}`)
+                .join(" | ")}
             </div>
           )}
 